@@ -4,29 +4,48 @@
 // LICENSE file in the root directory of this source tree.
 
 use crate::abstract_value::{AbstractValue, Path};
+use crate::bdd;
+use crate::congruence_domain::{self, CongruenceDomain};
 use crate::constant_domain::ConstantDomain;
 use crate::environment::Environment;
 use crate::expression::{Expression, ExpressionType};
+use crate::expression_arena;
+use crate::float_interval_domain::{self, FloatIntervalDomain};
 use crate::interval_domain::{self, IntervalDomain};
+use crate::reduced_product;
+use crate::statistics;
+use crate::string_domain::{self, StringDomain};
 
 use rustc::ty::TyKind;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::{Debug, Formatter, Result};
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::rc::Rc;
 use syntax::ast;
 
 // See https://github.com/facebookexperimental/MIRAI/blob/master/documentation/AbstractValues.md.
 
 /// Basically, this domain is a structured container for other domains. It is also the only
-/// client for the other domains.
-#[derive(Serialize, Deserialize, Clone, Eq, Ord, PartialOrd)]
+/// client for the other domains. The interval domain is built in, since intervals are cached and
+/// used to reduce the elements of other domains, while the remaining domains are components of
+/// the reduced product, which can be extended by registering domains with it.
+/// Expressions are interned (see expression_arena.rs), so elements are cheap to clone, compare
+/// and hash.
+#[derive(Serialize, Deserialize, Clone)]
 pub struct AbstractDomain {
     // todo: make this private
     // This is not a domain element, but a representation of how this instance has been constructed.
     // It is used to refine the instance with respect to path conditions and actual arguments.
     // It is also used to construct corresponding elements from other domains, when needed.
-    pub expression: Expression,
-    /// Cached interval computed on demand by get_as_interval.
+    // The expression is interned and is only valid until the expression scope of the analysis
+    // (see expression_arena::enter_scope) is dropped, which the element must not outlive.
+    #[serde(with = "expression_arena")]
+    pub expression: &'static Expression,
+    /// Cached interval computed on demand by get_as_interval. It depends only on the expression,
+    /// since equality and hashing ignore it.
     #[serde(skip)]
     interval: Option<IntervalDomain>,
 }
@@ -37,43 +56,100 @@ impl Debug for AbstractDomain {
     }
 }
 
+impl Eq for AbstractDomain {}
+
+// Interned expressions are equal if and only if they are the same node, except for the
+// expressions of the constant elements below, which are not interned.
 impl Hash for AbstractDomain {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.expression.hash(state);
+        if is_constant_element(self.expression) {
+            self.expression.hash(state);
+        } else {
+            (self.expression as *const Expression).hash(state);
+        }
     }
 }
 
 impl PartialEq for AbstractDomain {
     fn eq(&self, other: &Self) -> bool {
-        self.expression.eq(&other.expression)
+        std::ptr::eq(self.expression, other.expression)
+            || (is_constant_element(self.expression) && self.expression == other.expression)
+    }
+}
+
+// The order is structural, rather than based on the addresses of the nodes, so that
+// sorted collections of elements, such as the preconditions of summaries, are deterministic.
+impl Ord for AbstractDomain {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if std::ptr::eq(self.expression, other.expression) {
+            Ordering::Equal
+        } else {
+            self.expression.cmp(other.expression)
+        }
+    }
+}
+
+impl PartialOrd for AbstractDomain {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
 /// An abstract domain element that all represent the impossible concrete value.
 /// I.e. the corresponding set of possible concrete values is empty.
 pub const BOTTOM: AbstractDomain = AbstractDomain {
-    expression: Expression::Bottom,
+    expression: &Expression::Bottom,
     interval: None,
 };
 
 /// An abstract domain element that all represent the single concrete value, false.
 pub const FALSE: AbstractDomain = AbstractDomain {
-    expression: Expression::CompileTimeConstant(ConstantDomain::False),
+    expression: &Expression::CompileTimeConstant(ConstantDomain::False),
     interval: None,
 };
 
 /// An abstract domain element that all represents all possible concrete values.
 pub const TOP: AbstractDomain = AbstractDomain {
-    expression: Expression::Top,
+    expression: &Expression::Top,
     interval: None,
 };
 
 /// An abstract domain element that all represent the single concrete value, true.
 pub const TRUE: AbstractDomain = AbstractDomain {
-    expression: Expression::CompileTimeConstant(ConstantDomain::True),
+    expression: &Expression::CompileTimeConstant(ConstantDomain::True),
     interval: None,
 };
 
+/// True if expression is the expression of one of the constant elements above.
+fn is_constant_element(expression: &Expression) -> bool {
+    match expression {
+        Expression::Bottom
+        | Expression::CompileTimeConstant(ConstantDomain::False)
+        | Expression::CompileTimeConstant(ConstantDomain::True)
+        | Expression::Top => true,
+        _ => false,
+    }
+}
+
+/// Returns the value of the i32 local variable with the given ordinal. The unit tests of the
+/// modules that decide conditions use these values as the unknowns of their conditions.
+#[cfg(test)]
+pub fn local_variable(ordinal: usize) -> AbstractDomain {
+    Expression::Variable {
+        path: box Path::LocalVariable { ordinal },
+        var_type: ExpressionType::I32,
+    }
+    .into()
+}
+
+thread_local! {
+    /// The path condition whose interval bounds were computed last, along with the bounds.
+    /// This refers to interned expressions, so it is reset when the expression scope is dropped.
+    static PATH_CONDITION_BOUNDS: RefCell<
+        Option<(AbstractDomain, Rc<HashMap<AbstractDomain, IntervalDomain>>)>,
+    > = RefCell::new(None);
+}
+
 impl<'a> From<&TyKind<'a>> for ExpressionType {
     fn from(ty_kind: &TyKind<'a>) -> ExpressionType {
         match ty_kind {
@@ -101,32 +177,24 @@ impl<'a> From<&TyKind<'a>> for ExpressionType {
 impl From<bool> for AbstractDomain {
     fn from(b: bool) -> AbstractDomain {
         if b {
-            AbstractDomain {
-                expression: Expression::CompileTimeConstant(ConstantDomain::True),
-                interval: None,
-            }
+            TRUE
         } else {
-            AbstractDomain {
-                expression: Expression::CompileTimeConstant(ConstantDomain::False),
-                interval: None,
-            }
+            FALSE
         }
     }
 }
 
 impl From<ConstantDomain> for AbstractDomain {
     fn from(cv: ConstantDomain) -> AbstractDomain {
-        AbstractDomain {
-            expression: Expression::CompileTimeConstant(cv),
-            interval: None,
-        }
+        Expression::CompileTimeConstant(cv).into()
     }
 }
 
 impl From<Expression> for AbstractDomain {
     fn from(expr: Expression) -> AbstractDomain {
         AbstractDomain {
-            expression: expr,
+            // The node is stored in the element, which does not outlive the expression scope.
+            expression: unsafe { expression_arena::intern(expr) },
             interval: None,
         }
     }
@@ -195,8 +263,9 @@ impl AbstractDomain {
             self.clone()
         } else if other.is_top() {
             other.clone()
+        } else if let Some(conjunction) = bdd::and(self, other) {
+            conjunction
         } else {
-            // todo: #32 more simplifications
             Expression::And {
                 left: box self.clone(),
                 right: box other.clone(),
@@ -268,6 +337,25 @@ impl AbstractDomain {
         .into()
     }
 
+    /// Returns an element that is "self as target_type".
+    pub fn cast(&self, target_type: ExpressionType) -> Self {
+        if self.get_as_interval().is_contained_in(&target_type) {
+            // The conversion does not change the value.
+            return self.clone();
+        }
+        if let Expression::CompileTimeConstant(v1) = &self.expression {
+            let result = v1.cast(&target_type);
+            if result != ConstantDomain::Bottom {
+                return result.into();
+            }
+        };
+        Expression::Cast {
+            operand: box self.clone(),
+            target_type,
+        }
+        .into()
+    }
+
     /// Returns an element that is "self / other".
     pub fn div(&self, other: &Self) -> Self {
         if let (Expression::CompileTimeConstant(v1), Expression::CompileTimeConstant(v2)) =
@@ -339,7 +427,7 @@ impl AbstractDomain {
             _ => {
                 // If self and other are the same expression and the expression could not result in NaN
                 // and the expression represents exactly one value, we can simplify this to true.
-                if self.expression == other.expression {
+                if self == other {
                     match self.expression {
                         Expression::Top
                         | Expression::Bottom
@@ -352,6 +440,10 @@ impl AbstractDomain {
                             // Could be NaN, because we don't know the type.
                             // todo: infer it from the operands
                         }
+                        Expression::Widen { .. } => {
+                            // Stands for the values of a place at a join point, which need not
+                            // be the same value every time the node is used.
+                        }
                         Expression::CompileTimeConstant(..) | Expression::Variable { .. } => {
                             unreachable!()
                         } // handled above
@@ -363,12 +455,24 @@ impl AbstractDomain {
                 }
             }
         }
-        // Return an equals expression rather than a constant expression.
-        Expression::Equals {
+        // If self and other are integers whose ranges do not overlap, they are never equal.
+        if let Some(result) = self.get_as_interval().equals(&other.get_as_interval()) {
+            return result.into();
+        }
+        // If self or other is NaN, or their floating point ranges do not overlap, they are never
+        // equal.
+        if let Some(result) = self
+            .get_as_float_interval()
+            .equals(&other.get_as_float_interval())
+        {
+            return result.into();
+        }
+        // If self and other are integers whose congruences have no values in common, or that
+        // have a known bit that differs, they are never equal.
+        Self::decide_comparison(Expression::Equals {
             left: box self.clone(),
             right: box other.clone(),
-        }
-        .into()
+        })
     }
 
     /// Returns an element that is "self >= other".
@@ -384,11 +488,16 @@ impl AbstractDomain {
         {
             return result.into();
         }
-        Expression::GreaterOrEqual {
+        if let Some(result) = self
+            .get_as_float_interval()
+            .greater_or_equal(&other.get_as_float_interval())
+        {
+            return result.into();
+        }
+        Self::decide_comparison(Expression::GreaterOrEqual {
             left: box self.clone(),
             right: box other.clone(),
-        }
-        .into()
+        })
     }
 
     /// Returns an element that is "self > other".
@@ -404,11 +513,16 @@ impl AbstractDomain {
         {
             return result.into();
         }
-        Expression::GreaterThan {
+        if let Some(result) = self
+            .get_as_float_interval()
+            .greater_than(&other.get_as_float_interval())
+        {
+            return result.into();
+        }
+        Self::decide_comparison(Expression::GreaterThan {
             left: box self.clone(),
             right: box other.clone(),
-        }
-        .into()
+        })
     }
 
     /// Returns true if "self => other" is known at compile time to be true.
@@ -417,9 +531,18 @@ impl AbstractDomain {
         // x => true, is always true
         // false => x, is always true
         // x => x, is always true
+        // x && y => x, x && y => y, are always true
+        // x && !x => y, x => x || y, are always true
+        // x % 4 == 0 => x % 2 == 0, is always true
         other.as_bool_if_known().unwrap_or(false)
             || !self.as_bool_if_known().unwrap_or(true)
             || self.equals(other).as_bool_if_known().unwrap_or(false)
+            || match &self.expression {
+                Expression::And { left, right } => left.implies(other) || right.implies(other),
+                _ => false,
+            }
+            || bdd::implies(self, other)
+            || reduced_product::decide(other, Some(self)).unwrap_or(false)
     }
 
     /// Returns true if "self => !other" is known at compile time to be true.
@@ -437,7 +560,23 @@ impl AbstractDomain {
                 .as_bool_if_known()
                 .unwrap_or(false);
         }
-        false
+        // x && y => !(!x || !y), x == y => !(x != y)
+        if bdd::implies_not(self, other) {
+            return true;
+        }
+        // x % 2 == 0 => !(x % 4 == 1)
+        // x & 4 != 0 => !(x == 0)
+        !reduced_product::decide(other, Some(self)).unwrap_or(true)
+    }
+
+    /// Returns a constant if one of the components of the reduced product, such as the congruence
+    /// and known bits domains, decides the given comparison, and the comparison otherwise.
+    fn decide_comparison(comparison: Expression) -> Self {
+        let comparison: Self = comparison.into();
+        match reduced_product::decide(&comparison, None) {
+            Some(result) => result.into(),
+            None => comparison,
+        }
     }
 
     /// True if the set of concrete values that correspond to this domain is empty.
@@ -466,9 +605,7 @@ impl AbstractDomain {
             Expression::Bottom.into()
         }
         // c ? x : x is just x, as is true ? x : y
-        else if (*self).expression == (*other).expression
-            || join_condition.as_bool_if_known().unwrap_or(false)
-        {
+        else if *self == *other || join_condition.as_bool_if_known().unwrap_or(false) {
             self.clone()
         }
         // false ? x : y is just y
@@ -488,6 +625,45 @@ impl AbstractDomain {
         }
     }
 
+    /// Returns self if its expression is no deeper than max_depth and has no more than
+    /// max_node_count nodes. Otherwise returns an unknown value for the given path and join block
+    /// that keeps the interval and congruence of self, so that values that are joined over and
+    /// over again, for example by the loops of state machines, stop growing.
+    pub fn limit_size(
+        &self,
+        path: &Path,
+        block: usize,
+        max_depth: usize,
+        max_node_count: usize,
+    ) -> Self {
+        let size = expression_arena::get_size(self.expression);
+        if size.depth <= max_depth && size.node_count <= max_node_count {
+            return self.clone();
+        }
+        statistics::record_abstracted_expression();
+        self.abstract_to_interval(path, block, self.get_as_interval())
+    }
+
+    /// Returns an unknown value for the given path and join block that lies within the given
+    /// interval and within the congruence of self, or Top if nothing is known about it.
+    fn abstract_to_interval(&self, path: &Path, block: usize, interval: IntervalDomain) -> Self {
+        let mut congruence = reduced_product::evaluate::<CongruenceDomain>(self, &HashMap::new());
+        if congruence.is_bottom() {
+            // Not all values are integers that we know something about.
+            congruence = congruence_domain::TOP;
+        }
+        if (interval.is_bottom() || interval.is_top()) && congruence.is_top() {
+            return Expression::Top.into();
+        }
+        Expression::Widen {
+            path: box path.clone(),
+            block,
+            interval,
+            congruence,
+        }
+        .into()
+    }
+
     /// Returns an element that is "self <= other".
     pub fn less_or_equal(&mut self, other: &mut Self) -> Self {
         if let (Expression::CompileTimeConstant(v1), Expression::CompileTimeConstant(v2)) =
@@ -501,11 +677,16 @@ impl AbstractDomain {
         {
             return result.into();
         }
-        Expression::LessOrEqual {
+        if let Some(result) = self
+            .get_as_float_interval()
+            .less_equal(&other.get_as_float_interval())
+        {
+            return result.into();
+        }
+        Self::decide_comparison(Expression::LessOrEqual {
             left: box self.clone(),
             right: box other.clone(),
-        }
-        .into()
+        })
     }
 
     /// Returns an element that is self < other
@@ -521,11 +702,16 @@ impl AbstractDomain {
         {
             return result.into();
         }
-        Expression::LessThan {
+        if let Some(result) = self
+            .get_as_float_interval()
+            .less_than(&other.get_as_float_interval())
+        {
+            return result.into();
+        }
+        Self::decide_comparison(Expression::LessThan {
             left: box self.clone(),
             right: box other.clone(),
-        }
-        .into()
+        })
     }
 
     /// Returns an element that is "self * other".
@@ -588,11 +774,19 @@ impl AbstractDomain {
         {
             return v1.not_equals(v2).into();
         };
-        Expression::Ne {
+        if let Some(result) = self.get_as_interval().equals(&other.get_as_interval()) {
+            return (!result).into();
+        }
+        if let Some(result) = self
+            .get_as_float_interval()
+            .equals(&other.get_as_float_interval())
+        {
+            return (!result).into();
+        }
+        Self::decide_comparison(Expression::Ne {
             left: box self.clone(),
             right: box other.clone(),
-        }
-        .into()
+        })
     }
 
     /// Returns an element that is "!self".
@@ -642,11 +836,14 @@ impl AbstractDomain {
                 {
                     true.into()
                 }
-                _ => Expression::Or {
-                    left: box self.clone(),
-                    right: box other.clone(),
-                }
-                .into(),
+                _ => match bdd::or(self, other) {
+                    Some(disjunction) => disjunction,
+                    None => Expression::Or {
+                        left: box self.clone(),
+                        right: box other.clone(),
+                    }
+                    .into(),
+                },
             }
         }
     }
@@ -669,7 +866,7 @@ impl AbstractDomain {
     }
 
     /// Returns an element that is "self << other".
-    pub fn shl(&self, other: &Self) -> Self {
+    pub fn shl(&self, other: &Self, expression_type: ExpressionType) -> Self {
         if let (Expression::CompileTimeConstant(v1), Expression::CompileTimeConstant(v2)) =
             (&self.expression, &other.expression)
         {
@@ -681,6 +878,7 @@ impl AbstractDomain {
         Expression::Shl {
             left: box self.clone(),
             right: box other.clone(),
+            result_type: expression_type,
         }
         .into()
     }
@@ -747,6 +945,28 @@ impl AbstractDomain {
         .into()
     }
 
+    /// Returns an element that is "&self[start..end]", where self is a string slice.
+    pub fn str_slice(&self, start: &Self, end: &Self) -> Self {
+        if let (
+            Expression::CompileTimeConstant(ConstantDomain::Str(s)),
+            Expression::CompileTimeConstant(ConstantDomain::U128(from)),
+            Expression::CompileTimeConstant(ConstantDomain::U128(to)),
+        ) = (&self.expression, &start.expression, &end.expression)
+        {
+            if *from <= *to && *to <= s.len() as u128 {
+                if let Some(slice) = s.get(*from as usize..*to as usize) {
+                    return ConstantDomain::Str(String::from(slice)).into();
+                }
+            }
+        };
+        Expression::StrSlice {
+            operand: box self.clone(),
+            start: box start.clone(),
+            end: box end.clone(),
+        }
+        .into()
+    }
+
     /// Returns an element that is "self - other".
     pub fn sub(&self, other: &Self) -> Self {
         if let (Expression::CompileTimeConstant(v1), Expression::CompileTimeConstant(v2)) =
@@ -830,6 +1050,21 @@ impl AbstractDomain {
                 cv1 == cv2
             }
             (Expression::Reference(p1), Expression::Reference(p2)) => p1 == p2,
+            // x is a subset of a widened value if all of its possible values are in the interval
+            // and in the congruence.
+            (
+                _,
+                Expression::Widen {
+                    interval,
+                    congruence,
+                    ..
+                },
+            ) => {
+                self.get_as_interval().subset(interval)
+                    && (congruence.is_top()
+                        || reduced_product::evaluate::<CongruenceDomain>(self, &HashMap::new())
+                            .subset(congruence))
+            }
             // in all other cases we conservatively answer false
             _ => false,
         }
@@ -847,9 +1082,41 @@ impl AbstractDomain {
 
     /// Constructs an element of the Interval domain for simple expressions.
     pub fn get_as_interval(&self) -> IntervalDomain {
-        match &self.expression {
+        self.get_as_interval_using(&HashMap::new())
+    }
+
+    /// Constructs an element of the Interval domain for simple expressions, given intervals
+    /// for sub expressions that are known to be bounded, for example by a path condition.
+    pub fn get_as_interval_using(
+        &self,
+        bounds: &HashMap<AbstractDomain, IntervalDomain>,
+    ) -> IntervalDomain {
+        let interval = match &self.expression {
             Expression::Top => interval_domain::TOP,
-            Expression::Add { left, right } => left.get_as_interval().add(&right.get_as_interval()),
+            Expression::Add { left, right } => left
+                .get_as_interval_using(bounds)
+                .add(&right.get_as_interval_using(bounds)),
+            Expression::BitAnd { left, right } => left
+                .get_as_interval_using(bounds)
+                .bit_and(&right.get_as_interval_using(bounds)),
+            Expression::BitOr { left, right } => left
+                .get_as_interval_using(bounds)
+                .bit_or(&right.get_as_interval_using(bounds)),
+            Expression::BitXor { left, right } => left
+                .get_as_interval_using(bounds)
+                .bit_xor(&right.get_as_interval_using(bounds)),
+            Expression::Cast {
+                operand,
+                target_type,
+            } => {
+                // Floating point values saturate when they are cast to integers.
+                let float_interval = operand.get_as_float_interval();
+                if float_interval.is_bottom() {
+                    operand.get_as_interval_using(bounds).cast(target_type)
+                } else {
+                    float_interval.as_integer_interval(target_type)
+                }
+            }
             Expression::CompileTimeConstant(ConstantDomain::I128(val)) => (*val).into(),
             Expression::CompileTimeConstant(ConstantDomain::U128(val)) => (*val).into(),
             Expression::ConditionalExpression {
@@ -857,13 +1124,271 @@ impl AbstractDomain {
                 alternate,
                 ..
             } => consequent
-                .get_as_interval()
-                .widen(&alternate.get_as_interval()),
-            Expression::Mul { left, right } => left.get_as_interval().mul(&right.get_as_interval()),
-            Expression::Neg { operand } => operand.get_as_interval().neg(),
-            Expression::Sub { left, right } => left.get_as_interval().sub(&right.get_as_interval()),
-            Expression::Variable { .. } => interval_domain::TOP,
+                .get_as_interval_using(bounds)
+                .join(&alternate.get_as_interval_using(bounds)),
+            Expression::Div { left, right } => left
+                .get_as_interval_using(bounds)
+                .div(&right.get_as_interval_using(bounds)),
+            Expression::Mul { left, right } => left
+                .get_as_interval_using(bounds)
+                .mul(&right.get_as_interval_using(bounds)),
+            Expression::Neg { operand } => operand.get_as_interval_using(bounds).neg(),
+            Expression::Rem { left, right } => left
+                .get_as_interval_using(bounds)
+                .rem(&right.get_as_interval_using(bounds)),
+            Expression::Shl {
+                left,
+                right,
+                result_type,
+            } => left
+                .get_as_interval_using(bounds)
+                .shl(&right.get_as_interval_using(bounds), result_type),
+            Expression::Shr {
+                left,
+                right,
+                result_type,
+            } => left
+                .get_as_interval_using(bounds)
+                .shr(&right.get_as_interval_using(bounds), result_type),
+            Expression::Sub { left, right } => left
+                .get_as_interval_using(bounds)
+                .sub(&right.get_as_interval_using(bounds)),
+            Expression::Variable { var_type, .. } => {
+                if var_type.is_integer() {
+                    IntervalDomain::for_type(var_type)
+                } else {
+                    interval_domain::TOP
+                }
+            }
+            Expression::Widen { interval, .. } => interval.clone(),
             _ => interval_domain::BOTTOM,
+        };
+        // The bits that bitwise operations are known to set or clear often bound the result
+        // more tightly than the intervals of the operands.
+        let interval = match &self.expression {
+            Expression::BitAnd { .. }
+            | Expression::BitOr { .. }
+            | Expression::BitXor { .. }
+            | Expression::Shl { .. }
+            | Expression::Shr { .. } => interval.intersect(&reduced_product::get_as_interval(self)),
+            _ => interval,
+        };
+        match bounds.get(self) {
+            Some(bound) => interval.intersect(bound),
+            None => interval,
+        }
+    }
+
+    /// Adds to bounds the intervals that the operands of the comparisons in self must lie in
+    /// if self evaluates to is_true. Only conjuncts are considered.
+    fn add_interval_bounds(
+        &self,
+        is_true: bool,
+        bounds: &mut HashMap<AbstractDomain, IntervalDomain>,
+    ) {
+        match &self.expression {
+            Expression::And { left, right } if is_true => {
+                left.add_interval_bounds(true, bounds);
+                right.add_interval_bounds(true, bounds);
+            }
+            Expression::Or { left, right } if !is_true => {
+                left.add_interval_bounds(false, bounds);
+                right.add_interval_bounds(false, bounds);
+            }
+            Expression::Not { operand } => operand.add_interval_bounds(!is_true, bounds),
+            Expression::Equals { left, right } if is_true => {
+                Self::add_comparison_bounds(left, right, true, false, bounds);
+                Self::add_comparison_bounds(left, right, false, false, bounds);
+            }
+            Expression::GreaterOrEqual { left, right } => {
+                Self::add_comparison_bounds(left, right, !is_true, !is_true, bounds)
+            }
+            Expression::GreaterThan { left, right } => {
+                Self::add_comparison_bounds(left, right, !is_true, is_true, bounds)
+            }
+            Expression::LessOrEqual { left, right } => {
+                Self::add_comparison_bounds(left, right, is_true, !is_true, bounds)
+            }
+            Expression::LessThan { left, right } => {
+                Self::add_comparison_bounds(left, right, is_true, is_true, bounds)
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds to bounds the intervals that left and right must lie in if left < right (or
+    /// left > right if !is_less, or the non strict versions if !is_strict).
+    fn add_comparison_bounds(
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        is_less: bool,
+        is_strict: bool,
+        bounds: &mut HashMap<AbstractDomain, IntervalDomain>,
+    ) {
+        let left_interval = left.get_as_interval_using(bounds);
+        let right_interval = right.get_as_interval_using(bounds);
+        // If either operand is not known to be an integer, we know nothing about the other.
+        if left_interval.is_bottom() || right_interval.is_bottom() {
+            return;
+        }
+        let (left_interval, right_interval) = if is_less {
+            (
+                left_interval.refine_less_than(&right_interval, is_strict),
+                right_interval.refine_greater_than(&left_interval, is_strict),
+            )
+        } else {
+            (
+                left_interval.refine_greater_than(&right_interval, is_strict),
+                right_interval.refine_less_than(&left_interval, is_strict),
+            )
+        };
+        bounds.insert(left.clone(), left_interval);
+        bounds.insert(right.clone(), right_interval);
+    }
+
+    /// Constructs an element of the FloatInterval domain for floating point expressions.
+    pub fn get_as_float_interval(&self) -> FloatIntervalDomain {
+        self.get_as_float_interval_using(&HashMap::new())
+    }
+
+    /// Constructs an element of the FloatInterval domain for floating point expressions, given
+    /// ranges for sub expressions that are known to hold, for example because of a path condition.
+    pub fn get_as_float_interval_using(
+        &self,
+        bounds: &HashMap<AbstractDomain, FloatIntervalDomain>,
+    ) -> FloatIntervalDomain {
+        let float_interval = match &self.expression {
+            Expression::Top => float_interval_domain::TOP,
+            Expression::Add { left, right } => left
+                .get_as_float_interval_using(bounds)
+                .add(&right.get_as_float_interval_using(bounds)),
+            Expression::Cast {
+                operand,
+                target_type,
+            } => {
+                let operand_interval = operand.get_as_float_interval_using(bounds);
+                if operand_interval.is_bottom() {
+                    FloatIntervalDomain::from_interval(&operand.get_as_interval(), target_type)
+                } else {
+                    operand_interval.cast(target_type)
+                }
+            }
+            Expression::CompileTimeConstant(ConstantDomain::F32(val)) => {
+                f32::from_bits(*val).into()
+            }
+            Expression::CompileTimeConstant(ConstantDomain::F64(val)) => {
+                f64::from_bits(*val).into()
+            }
+            Expression::ConditionalExpression {
+                consequent,
+                alternate,
+                ..
+            } => consequent
+                .get_as_float_interval_using(bounds)
+                .join(&alternate.get_as_float_interval_using(bounds)),
+            Expression::Div { left, right } => left
+                .get_as_float_interval_using(bounds)
+                .div(&right.get_as_float_interval_using(bounds)),
+            Expression::Mul { left, right } => left
+                .get_as_float_interval_using(bounds)
+                .mul(&right.get_as_float_interval_using(bounds)),
+            Expression::Neg { operand } => operand.get_as_float_interval_using(bounds).neg(),
+            Expression::Rem { left, right } => left
+                .get_as_float_interval_using(bounds)
+                .rem(&right.get_as_float_interval_using(bounds)),
+            Expression::Sub { left, right } => left
+                .get_as_float_interval_using(bounds)
+                .sub(&right.get_as_float_interval_using(bounds)),
+            Expression::Variable { var_type, .. } => FloatIntervalDomain::for_type(var_type),
+            _ => float_interval_domain::BOTTOM,
+        };
+        match bounds.get(self) {
+            Some(bound) => float_interval.meet(bound),
+            None => float_interval,
+        }
+    }
+
+    /// Adds to bounds the ranges that the floating point operands of the comparisons in self
+    /// must lie in if self evaluates to is_true. Only conjuncts are considered.
+    pub fn add_float_interval_bounds(
+        &self,
+        is_true: bool,
+        bounds: &mut HashMap<AbstractDomain, FloatIntervalDomain>,
+    ) {
+        match &self.expression {
+            Expression::And { left, right } if is_true => {
+                left.add_float_interval_bounds(true, bounds);
+                right.add_float_interval_bounds(true, bounds);
+            }
+            Expression::Or { left, right } if !is_true => {
+                left.add_float_interval_bounds(false, bounds);
+                right.add_float_interval_bounds(false, bounds);
+            }
+            Expression::Not { operand } => operand.add_float_interval_bounds(!is_true, bounds),
+            Expression::Equals { left, right } if is_true => {
+                Self::add_float_comparison_bounds(left, right, false, true, bounds);
+                Self::add_float_comparison_bounds(right, left, false, true, bounds);
+            }
+            Expression::GreaterOrEqual { left, right } => {
+                Self::add_float_comparison_bounds(right, left, false, is_true, bounds)
+            }
+            Expression::GreaterThan { left, right } => {
+                Self::add_float_comparison_bounds(right, left, true, is_true, bounds)
+            }
+            Expression::LessOrEqual { left, right } => {
+                Self::add_float_comparison_bounds(left, right, false, is_true, bounds)
+            }
+            Expression::LessThan { left, right } => {
+                Self::add_float_comparison_bounds(left, right, true, is_true, bounds)
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds to bounds the ranges that left and right must lie in if left < right (or the non
+    /// strict version if !is_strict) evaluates to is_true.
+    fn add_float_comparison_bounds(
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        is_strict: bool,
+        is_true: bool,
+        bounds: &mut HashMap<AbstractDomain, FloatIntervalDomain>,
+    ) {
+        let left_interval = left.get_as_float_interval_using(bounds);
+        let right_interval = right.get_as_float_interval_using(bounds);
+        // If either operand is not known to be a floating point value, we know nothing.
+        if left_interval.is_bottom() || right_interval.is_bottom() {
+            return;
+        }
+        bounds.insert(
+            left.clone(),
+            left_interval.refine_less_than(&right_interval, is_strict, is_true),
+        );
+        bounds.insert(
+            right.clone(),
+            right_interval.refine_greater_than(&left_interval, is_strict, is_true),
+        );
+    }
+
+    /// Constructs an element of the String domain for string slice expressions. The contents of
+    /// strings are only known for constants and for slices of constants, while the lengths of
+    /// other slices follow from the intervals of their bounds.
+    pub fn get_as_string(&self) -> StringDomain {
+        match &self.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Str(s)) => s.as_str().into(),
+            Expression::ConditionalExpression {
+                consequent,
+                alternate,
+                ..
+            } => consequent.get_as_string().join(&alternate.get_as_string()),
+            Expression::StrSlice {
+                operand,
+                start,
+                end,
+            } => operand
+                .get_as_string()
+                .slice(&start.get_as_interval(), &end.get_as_interval()),
+            _ => string_domain::TOP,
         }
     }
 
@@ -897,6 +1422,10 @@ impl AbstractDomain {
             Expression::BitXor { left, right } => left
                 .refine_paths(environment)
                 .bit_xor(&right.refine_paths(environment)),
+            Expression::Cast {
+                operand,
+                target_type,
+            } => operand.refine_paths(environment).cast(target_type.clone()),
             Expression::CompileTimeConstant(..) => self.clone(),
             Expression::ConditionalExpression {
                 condition,
@@ -949,9 +1478,13 @@ impl AbstractDomain {
             Expression::Rem { left, right } => left
                 .refine_paths(environment)
                 .rem(&right.refine_paths(environment)),
-            Expression::Shl { left, right } => left
+            Expression::Shl {
+                left,
+                right,
+                result_type,
+            } => left
                 .refine_paths(environment)
-                .shl(&right.refine_paths(environment)),
+                .shl(&right.refine_paths(environment), result_type.clone()),
             Expression::ShlOverflows {
                 left,
                 right,
@@ -973,6 +1506,14 @@ impl AbstractDomain {
             } => left
                 .refine_paths(environment)
                 .shr_overflows(&mut right.refine_paths(environment), result_type.clone()),
+            Expression::StrSlice {
+                operand,
+                start,
+                end,
+            } => operand.refine_paths(environment).str_slice(
+                &start.refine_paths(environment),
+                &end.refine_paths(environment),
+            ),
             Expression::Sub { left, right } => left
                 .refine_paths(environment)
                 .sub(&right.refine_paths(environment)),
@@ -990,6 +1531,7 @@ impl AbstractDomain {
                     self.clone()
                 }
             }
+            Expression::Widen { .. } => self.clone(),
         }
     }
 
@@ -1021,6 +1563,10 @@ impl AbstractDomain {
             Expression::BitXor { left, right } => left
                 .refine_parameters(arguments)
                 .bit_xor(&right.refine_parameters(arguments)),
+            Expression::Cast {
+                operand,
+                target_type,
+            } => operand.refine_parameters(arguments).cast(target_type.clone()),
             Expression::CompileTimeConstant(..) => self.clone(),
             Expression::ConditionalExpression {
                 condition,
@@ -1073,9 +1619,13 @@ impl AbstractDomain {
             Expression::Rem { left, right } => left
                 .refine_parameters(arguments)
                 .rem(&right.refine_parameters(arguments)),
-            Expression::Shl { left, right } => left
+            Expression::Shl {
+                left,
+                right,
+                result_type,
+            } => left
                 .refine_parameters(arguments)
-                .shl(&right.refine_parameters(arguments)),
+                .shl(&right.refine_parameters(arguments), result_type.clone()),
             Expression::ShlOverflows {
                 left,
                 right,
@@ -1097,6 +1647,14 @@ impl AbstractDomain {
             } => left
                 .refine_parameters(arguments)
                 .shr_overflows(&mut right.refine_parameters(arguments), result_type.clone()),
+            Expression::StrSlice {
+                operand,
+                start,
+                end,
+            } => operand.refine_parameters(arguments).str_slice(
+                &start.refine_parameters(arguments),
+                &end.refine_parameters(arguments),
+            ),
             Expression::Sub { left, right } => left
                 .refine_parameters(arguments)
                 .sub(&right.refine_parameters(arguments)),
@@ -1113,6 +1671,7 @@ impl AbstractDomain {
                 }
                 _ => self.clone(),
             },
+            Expression::Widen { .. } => self.clone(),
         }
     }
 
@@ -1131,7 +1690,8 @@ impl AbstractDomain {
                 result_type,
             } => left
                 .refine_with(path_condition)
-                .add_overflows(&mut right.refine_with(path_condition), result_type.clone()),
+                .add_overflows(&mut right.refine_with(path_condition), result_type.clone())
+                .decide_overflow_with(path_condition),
             Expression::And { left, right } => {
                 if path_condition.implies(&**left) && path_condition.implies(&**right) {
                     true.into()
@@ -1153,6 +1713,10 @@ impl AbstractDomain {
             Expression::BitXor { left, right } => left
                 .refine_with(path_condition)
                 .bit_xor(&right.refine_with(path_condition)),
+            Expression::Cast {
+                operand,
+                target_type,
+            } => operand.refine_with(path_condition).cast(target_type.clone()),
             Expression::CompileTimeConstant(..) => self.clone(),
             Expression::ConditionalExpression {
                 condition,
@@ -1180,21 +1744,33 @@ impl AbstractDomain {
             Expression::Div { left, right } => left
                 .refine_with(path_condition)
                 .div(&right.refine_with(path_condition)),
-            Expression::Equals { left, right } => left
-                .refine_with(path_condition)
-                .equals(&right.refine_with(path_condition)),
+            Expression::Equals { left, right } => {
+                if path_condition.implies(&self) {
+                    true.into()
+                } else if path_condition.implies_not(&self) {
+                    false.into()
+                } else {
+                    left.refine_with(path_condition)
+                        .equals(&right.refine_with(path_condition))
+                        .decide_comparison_with(path_condition)
+                }
+            }
             Expression::GreaterOrEqual { left, right } => left
                 .refine_with(path_condition)
-                .greater_or_equal(&mut right.refine_with(path_condition)),
+                .greater_or_equal(&mut right.refine_with(path_condition))
+                .decide_comparison_with(path_condition),
             Expression::GreaterThan { left, right } => left
                 .refine_with(path_condition)
-                .greater_than(&mut right.refine_with(path_condition)),
+                .greater_than(&mut right.refine_with(path_condition))
+                .decide_comparison_with(path_condition),
             Expression::LessOrEqual { left, right } => left
                 .refine_with(path_condition)
-                .less_or_equal(&mut right.refine_with(path_condition)),
+                .less_or_equal(&mut right.refine_with(path_condition))
+                .decide_comparison_with(path_condition),
             Expression::LessThan { left, right } => left
                 .refine_with(path_condition)
-                .less_than(&mut right.refine_with(path_condition)),
+                .less_than(&mut right.refine_with(path_condition))
+                .decide_comparison_with(path_condition),
             Expression::Mul { left, right } => left
                 .refine_with(path_condition)
                 .mul(&right.refine_with(path_condition)),
@@ -1204,10 +1780,19 @@ impl AbstractDomain {
                 result_type,
             } => left
                 .refine_with(path_condition)
-                .mul_overflows(&mut right.refine_with(path_condition), result_type.clone()),
-            Expression::Ne { left, right } => left
-                .refine_with(path_condition)
-                .not_equals(&right.refine_with(path_condition)),
+                .mul_overflows(&mut right.refine_with(path_condition), result_type.clone())
+                .decide_overflow_with(path_condition),
+            Expression::Ne { left, right } => {
+                if path_condition.implies(&self) {
+                    true.into()
+                } else if path_condition.implies_not(&self) {
+                    false.into()
+                } else {
+                    left.refine_with(path_condition)
+                        .not_equals(&right.refine_with(path_condition))
+                        .decide_comparison_with(path_condition)
+                }
+            }
             Expression::Neg { operand } => operand.refine_with(path_condition).neg(),
             Expression::Not { operand } => {
                 if path_condition.implies(&**operand) {
@@ -1237,16 +1822,21 @@ impl AbstractDomain {
             Expression::Rem { left, right } => left
                 .refine_with(path_condition)
                 .rem(&right.refine_with(path_condition)),
-            Expression::Shl { left, right } => left
+            Expression::Shl {
+                left,
+                right,
+                result_type,
+            } => left
                 .refine_with(path_condition)
-                .shl(&right.refine_with(path_condition)),
+                .shl(&right.refine_with(path_condition), result_type.clone()),
             Expression::ShlOverflows {
                 left,
                 right,
                 result_type,
             } => left
                 .refine_with(path_condition)
-                .shl_overflows(&mut right.refine_with(path_condition), result_type.clone()),
+                .shl_overflows(&mut right.refine_with(path_condition), result_type.clone())
+                .decide_overflow_with(path_condition),
             Expression::Shr {
                 left,
                 right,
@@ -1260,7 +1850,16 @@ impl AbstractDomain {
                 result_type,
             } => left
                 .refine_with(path_condition)
-                .shr_overflows(&mut right.refine_with(path_condition), result_type.clone()),
+                .shr_overflows(&mut right.refine_with(path_condition), result_type.clone())
+                .decide_overflow_with(path_condition),
+            Expression::StrSlice {
+                operand,
+                start,
+                end,
+            } => operand.refine_with(path_condition).str_slice(
+                &start.refine_with(path_condition),
+                &end.refine_with(path_condition),
+            ),
             Expression::Sub { left, right } => left
                 .refine_with(path_condition)
                 .sub(&right.refine_with(path_condition)),
@@ -1270,7 +1869,8 @@ impl AbstractDomain {
                 result_type,
             } => left
                 .refine_with(path_condition)
-                .sub_overflows(&mut right.refine_with(path_condition), result_type.clone()),
+                .sub_overflows(&mut right.refine_with(path_condition), result_type.clone())
+                .decide_overflow_with(path_condition),
             Expression::Variable { .. } => {
                 if path_condition.implies(&self) {
                     true.into()
@@ -1280,6 +1880,197 @@ impl AbstractDomain {
                     self.clone()
                 }
             }
+            Expression::Widen { .. } => self.clone(),
+        }
+    }
+
+    /// Returns self restricted to the given interval, which must include all of the values of
+    /// self that are possible at the entry of the given block, for example because of a loop
+    /// invariant. The restriction is part of the expression, so that it is never mistaken for
+    /// the same value in another context: a widened value gets the narrower interval and other
+    /// values that the interval restricts become unknown values for the given path and block.
+    pub fn restrict_to_interval(
+        &self,
+        path: &Path,
+        block: usize,
+        interval: &IntervalDomain,
+    ) -> Self {
+        match self.expression {
+            Expression::Widen {
+                path,
+                block,
+                interval: widened_interval,
+                congruence,
+            } => Expression::Widen {
+                path: path.clone(),
+                block: *block,
+                interval: widened_interval.intersect(interval),
+                congruence: congruence.clone(),
+            }
+            .into(),
+            _ => {
+                let self_interval = self.get_as_interval();
+                if self_interval.is_bottom() || self_interval.subset(interval) {
+                    // Not an integer that we know something about, or already restricted.
+                    return self.clone();
+                }
+                self.abstract_to_interval(path, block, self_interval.intersect(interval))
+            }
+        }
+    }
+
+    /// Returns a constant if the intervals that the path condition implies for the operands of
+    /// this comparison decide it, and self otherwise. This decides comparisons like i < 20 and
+    /// x == 0 if the path condition is i < 10 && x > 0.
+    fn decide_comparison_with(&self, path_condition: &Self) -> Self {
+        let (left, right) = match &self.expression {
+            Expression::Equals { left, right }
+            | Expression::GreaterOrEqual { left, right }
+            | Expression::GreaterThan { left, right }
+            | Expression::LessOrEqual { left, right }
+            | Expression::LessThan { left, right }
+            | Expression::Ne { left, right } => (left, right),
+            _ => return self.clone(),
+        };
+        let bounds = Self::get_path_condition_bounds(path_condition);
+        if bounds.is_empty() {
+            return self.clone();
+        }
+        let left_interval = left.get_as_interval_using(&bounds);
+        let right_interval = right.get_as_interval_using(&bounds);
+        let result = match &self.expression {
+            Expression::Equals { .. } => left_interval.equals(&right_interval),
+            Expression::GreaterOrEqual { .. } => left_interval.greater_or_equal(&right_interval),
+            Expression::GreaterThan { .. } => left_interval.greater_than(&right_interval),
+            Expression::LessOrEqual { .. } => left_interval.less_equal(&right_interval),
+            Expression::LessThan { .. } => left_interval.less_than(&right_interval),
+            Expression::Ne { .. } => left_interval.equals(&right_interval).map(|b| !b),
+            _ => None,
+        };
+        match result {
+            Some(result) => result.into(),
+            None => self.clone(),
+        }
+    }
+
+    /// Returns false if the intervals that the path condition implies for the operands of this
+    /// overflow check show that the operation cannot overflow, and self otherwise. This decides
+    /// the check of i + 1 if the path condition is i < 10.
+    fn decide_overflow_with(&self, path_condition: &Self) -> Self {
+        let (left, right, result_type) = match &self.expression {
+            Expression::AddOverflows {
+                left,
+                right,
+                result_type,
+            }
+            | Expression::MulOverflows {
+                left,
+                right,
+                result_type,
+            }
+            | Expression::ShlOverflows {
+                left,
+                right,
+                result_type,
+            }
+            | Expression::ShrOverflows {
+                left,
+                right,
+                result_type,
+            }
+            | Expression::SubOverflows {
+                left,
+                right,
+                result_type,
+            } => (left, right, result_type),
+            _ => return self.clone(),
+        };
+        let bounds = Self::get_path_condition_bounds(path_condition);
+        if bounds.is_empty() {
+            return self.clone();
+        }
+        let left_interval = left.get_as_interval_using(&bounds);
+        let right_interval = right.get_as_interval_using(&bounds);
+        let cannot_overflow = match &self.expression {
+            Expression::AddOverflows { .. } => left_interval
+                .add(&right_interval)
+                .is_contained_in(result_type),
+            Expression::MulOverflows { .. } => left_interval
+                .mul(&right_interval)
+                .is_contained_in(result_type),
+            Expression::ShlOverflows { .. } | Expression::ShrOverflows { .. } => {
+                right_interval.is_contained_in_width_of(result_type)
+            }
+            Expression::SubOverflows { .. } => left_interval
+                .sub(&right_interval)
+                .is_contained_in(result_type),
+            _ => false,
+        };
+        if cannot_overflow {
+            false.into()
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Forgets the cached bounds of the last path condition.
+    pub fn reset_path_condition_bounds() {
+        PATH_CONDITION_BOUNDS.with(|cache| *cache.borrow_mut() = None);
+    }
+
+    /// Returns the intervals that the comparisons in the conjuncts of the given path condition
+    /// imply for the expressions that they compare. The bounds of the last path condition are
+    /// cached, since refine_with asks for them for every sub expression that it refines.
+    fn get_path_condition_bounds(
+        path_condition: &Self,
+    ) -> Rc<HashMap<AbstractDomain, IntervalDomain>> {
+        let cached_bounds = PATH_CONDITION_BOUNDS.with(|cache| match &*cache.borrow() {
+            Some((condition, bounds)) if condition == path_condition => Some(bounds.clone()),
+            _ => None,
+        });
+        if let Some(bounds) = cached_bounds {
+            return bounds;
+        }
+        let mut bounds = HashMap::new();
+        path_condition.add_interval_bounds(true, &mut bounds);
+        let bounds = Rc::new(bounds);
+        PATH_CONDITION_BOUNDS.with(|cache| {
+            *cache.borrow_mut() = Some((path_condition.clone(), bounds.clone()));
+        });
+        bounds
+    }
+
+    /// Returns a domain whose corresponding set of concrete values is included in the set
+    /// corresponding to self, but includes all of the values corresponding to other.
+    /// Other is expected to be the result of analyzing the code again, starting from a fixed point
+    /// that includes self, so it recovers some of the precision that widening has given up:
+    /// values that were widened to Top become other and widened intervals with infinite bounds
+    /// take the corresponding bounds of other.
+    pub fn narrow(&self, other: &Self) -> Self {
+        if self == other {
+            return self.clone();
+        };
+        match &self.expression {
+            Expression::Top => other.clone(),
+            Expression::Widen {
+                path,
+                block,
+                interval,
+                congruence,
+            } => {
+                let interval = interval.narrow(&other.get_as_interval());
+                if interval.is_top() {
+                    return self.clone();
+                }
+                Expression::Widen {
+                    path: path.clone(),
+                    block: *block,
+                    interval,
+                    congruence: congruence.clone(),
+                }
+                .into()
+            }
+            _ => self.clone(),
         }
     }
 
@@ -1287,11 +2078,48 @@ impl AbstractDomain {
     /// corresponding to self and other.The set of values may be less precise (more inclusive) than
     /// the set returned by join. The chief requirement is that a small number of widen calls
     /// deterministically lead to Top.
-    pub fn widen(&self, other: &Self, _join_condition: &AbstractDomain) -> Self {
+    ///
+    /// If self and other are integers, the result is an unknown value at path, joined at the
+    /// entry of the given block, that lies within the threshold widening of their intervals and
+    /// in the join of their congruences.
+    /// The interval of self is computed assuming join_condition and the interval of other is
+    /// computed assuming other_condition, so that loop conditions such as i < 512 bound the
+    /// values that flow back into the loop header. The congruences need no widening, since a
+    /// sequence of joins can only reduce the modulus a finite number of times.
+    pub fn widen(
+        &self,
+        other: &Self,
+        join_condition: &AbstractDomain,
+        other_condition: &AbstractDomain,
+        path: &Path,
+        block: usize,
+        thresholds: &BTreeSet<i128>,
+    ) -> Self {
         if self == other {
             return self.clone();
         };
-        //todo: #30 don't get to top quite this quickly.
-        Expression::Top.into()
+        let mut bounds = HashMap::new();
+        join_condition.add_interval_bounds(true, &mut bounds);
+        let self_interval = self.get_as_interval_using(&bounds);
+        let mut bounds = HashMap::new();
+        other_condition.add_interval_bounds(true, &mut bounds);
+        let other_interval = other.get_as_interval_using(&bounds);
+        let interval = self_interval.widen(&other_interval, thresholds);
+        let mut congruence = reduced_product::evaluate::<CongruenceDomain>(self, &HashMap::new())
+            .join(&reduced_product::evaluate(other, &HashMap::new()));
+        if congruence.is_bottom() {
+            // Not all values are integers that we know something about.
+            congruence = congruence_domain::TOP;
+        }
+        if (interval.is_bottom() || interval.is_top()) && congruence.is_top() {
+            return Expression::Top.into();
+        }
+        Expression::Widen {
+            path: box path.clone(),
+            block,
+            interval,
+            congruence,
+        }
+        .into()
     }
 }
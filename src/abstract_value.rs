@@ -7,8 +7,11 @@ use crate::abstract_domains::{self, AbstractDomain};
 use crate::constant_domain::ConstantDomain;
 use crate::environment::Environment;
 use crate::expression::{Expression, ExpressionType};
+use crate::interval_domain::IntervalDomain;
+use crate::simplifier;
 
 use rustc::hir::def_id::DefId;
+use std::collections::BTreeSet;
 use std::fmt::{Debug, Formatter, Result};
 use std::hash::{Hash, Hasher};
 use syntax_pos::Span;
@@ -318,6 +321,24 @@ impl AbstractValue {
         }
     }
 
+    /// Returns self if the expression of its domain is within the given limits, and otherwise an
+    /// unknown value for the given path and join block that keeps the interval and congruence
+    /// of self.
+    pub fn limit_size(
+        &self,
+        path: &Path,
+        block: usize,
+        max_depth: usize,
+        max_node_count: usize,
+    ) -> AbstractValue {
+        AbstractValue {
+            provenance: self.provenance.clone(),
+            domain: self
+                .domain
+                .limit_size(path, block, max_depth, max_node_count),
+        }
+    }
+
     /// Returns an abstract value whose corresponding set of concrete values include all of the
     /// values resulting from applying "<=" to each element of the cross product of the concrete
     /// values or self and other.
@@ -387,6 +408,24 @@ impl AbstractValue {
         }
     }
 
+    /// Returns an abstract value whose corresponding set of concrete values include all of the
+    /// values resulting from converting each element of the concrete values of self to target_type.
+    pub fn cast(
+        &self,
+        target_type: ExpressionType,
+        expression_provenance: Option<Span>,
+    ) -> AbstractValue {
+        let mut provenance = Vec::new();
+        if expression_provenance.is_some() {
+            provenance.push(expression_provenance.unwrap())
+        }
+        provenance.extend_from_slice(&self.provenance);
+        AbstractValue {
+            provenance,
+            domain: self.domain.cast(target_type),
+        }
+    }
+
     /// Returns an abstract value whose corresponding set of concrete values include all of the
     /// values resulting from applying "neg" to each element of the concrete values of self.
     pub fn neg(&self, expression_provenance: Option<Span>) -> AbstractValue {
@@ -478,6 +517,21 @@ impl AbstractValue {
         }
     }
 
+    /// Returns a value that is self restricted to the given interval, which must include all of
+    /// the values of self that are possible at the entry of the given block, where self is the
+    /// value at the given path.
+    pub fn restrict_to_interval(
+        &self,
+        path: &Path,
+        block: usize,
+        interval: &IntervalDomain,
+    ) -> AbstractValue {
+        AbstractValue {
+            provenance: self.provenance.clone(),
+            domain: self.domain.restrict_to_interval(path, block, interval),
+        }
+    }
+
     /// Returns a value that is simplified (refined) by replacing values with Variable(path) expressions
     /// with the value at that path (if there is one). If no refinement is possible
     /// the result is simply a clone of this value. This refinement only makes sense
@@ -516,14 +570,19 @@ impl AbstractValue {
     /// Returns an abstract value whose corresponding set of concrete values include all of the
     /// values resulting from applying "<<" to each element of the cross product of the concrete
     /// values or self and other.
-    pub fn shl(&self, other: &AbstractValue, expression_provenance: Option<Span>) -> AbstractValue {
+    pub fn shl(
+        &self,
+        other: &AbstractValue,
+        expression_type: ExpressionType,
+        expression_provenance: Option<Span>,
+    ) -> AbstractValue {
         AbstractValue {
             provenance: Self::binary_provenance(
                 expression_provenance,
                 &self.provenance,
                 &other.provenance,
             ),
-            domain: self.domain.shl(&other.domain),
+            domain: self.domain.shl(&other.domain, expression_type),
         }
     }
 
@@ -584,6 +643,33 @@ impl AbstractValue {
         }
     }
 
+    /// Returns an abstract value with the same concrete values as self, whose expression has been
+    /// rewritten into a normal form that is smaller and easier to refine.
+    pub fn simplify(&self) -> AbstractValue {
+        AbstractValue {
+            provenance: self.provenance.clone(),
+            domain: simplifier::simplify(&self.domain),
+        }
+    }
+
+    /// Returns an abstract value whose corresponding set of concrete values include all of the
+    /// string slices resulting from slicing each element of the concrete values of self with
+    /// each range of the cross product of the concrete values of start and end.
+    pub fn str_slice(
+        &self,
+        start: &AbstractValue,
+        end: &AbstractValue,
+        expression_provenance: Option<Span>,
+    ) -> AbstractValue {
+        let mut provenance =
+            Self::binary_provenance(expression_provenance, &self.provenance, &start.provenance);
+        provenance.extend_from_slice(&end.provenance);
+        AbstractValue {
+            provenance,
+            domain: self.domain.str_slice(&start.domain, &end.domain),
+        }
+    }
+
     /// Returns an abstract value whose corresponding set of concrete values include all of the
     /// values resulting from applying "-" to each element of the cross product of the concrete
     /// values or self and other.
@@ -622,14 +708,43 @@ impl AbstractValue {
         self.domain.subset(&other.domain)
     }
 
+    /// Returns an abstract value whose corresponding set of concrete values is included in the set
+    /// corresponding to self, but includes all of the values corresponding to other.
+    /// Used to recover precision after widening, where other is the result of analyzing the
+    /// code again, starting from a fixed point that includes self.
+    pub fn narrow(&self, other: &AbstractValue) -> AbstractValue {
+        AbstractValue {
+            provenance: other.provenance.clone(),
+            domain: self.domain.narrow(&other.domain),
+        }
+    }
+
     /// Returns an abstract value whose corresponding set of concrete values include all of the values
     /// corresponding to self and other. The set of values may be less precise (more inclusive) than
     /// the set returned by join. The chief requirement is that a small number of widen calls
     /// deterministically lead to Top.
-    pub fn widen(&self, other: &AbstractValue, join_condition: &AbstractValue) -> AbstractValue {
+    /// The value of self is known to be the value at path if join_condition is true and
+    /// the value of other is known to be the value at path if other_condition is true.
+    /// The values are joined at the entry of the given block.
+    pub fn widen(
+        &self,
+        other: &AbstractValue,
+        join_condition: &AbstractValue,
+        other_condition: &AbstractValue,
+        path: &Path,
+        block: usize,
+        thresholds: &BTreeSet<i128>,
+    ) -> AbstractValue {
         AbstractValue {
             provenance: other.provenance.clone(),
-            domain: self.domain.widen(&other.domain, &join_condition.domain),
+            domain: self.domain.widen(
+                &other.domain,
+                &join_condition.domain,
+                &other_condition.domain,
+                path,
+                block,
+                thresholds,
+            ),
         }
     }
 
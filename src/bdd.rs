@@ -0,0 +1,402 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::abstract_domains::{self, AbstractDomain};
+use crate::constant_domain::ConstantDomain;
+use crate::expression::Expression;
+use crate::expression_arena;
+use crate::k_limits;
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Path conditions are boolean combinations of atoms, such as comparisons and boolean variables,
+// whose boolean skeleton is represented here as a reduced ordered binary decision diagram.
+// The diagrams are hash-consed in a table, so that there is only one diagram for all equivalent
+// boolean combinations of the same atoms. This makes equivalence, implication and
+// simplification canonical: two conditions are equivalent if they have the same diagram,
+// "x => y" holds if the diagram of "x && !y" is the false diagram, and the condition of a
+// diagram is its canonical (and often smaller) form.
+//
+// The atoms are ordered by the structural order of their expressions, so that the diagrams do
+// not depend on the order in which conditions are constructed. A condition "x != y" is
+// represented as the negation of the atom "x == y" and "x > y" as the atom "y < x", so that
+// these pairs of atoms are recognized as related.
+
+/// A diagram, identified by its index in the table. The index of a decision node is its index
+/// in the nodes of the table plus two.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Bdd(usize);
+
+/// The diagram of conditions that are always false.
+const FALSE: Bdd = Bdd(0);
+
+/// The diagram of conditions that are always true.
+const TRUE: Bdd = Bdd(1);
+
+/// A decision node: the value of the diagram is that of high if the atom is true and that
+/// of low otherwise. The atoms of high and low come after this atom in the order of atoms.
+struct Node {
+    atom: AbstractDomain,
+    high: Bdd,
+    low: Bdd,
+}
+
+/// The decision nodes, along with the results of earlier operations on them.
+#[derive(Default)]
+struct Table {
+    nodes: Vec<Node>,
+    /// Maps the atom and the branches of every decision node to the node.
+    unique_nodes: HashMap<(AbstractDomain, Bdd, Bdd), Bdd>,
+    /// The results of earlier conjunctions (true) and disjunctions (false).
+    junctions: HashMap<(bool, Bdd, Bdd), Bdd>,
+    /// The results of earlier negations.
+    negations: HashMap<Bdd, Bdd>,
+    /// The diagrams of the conditions that have been converted to diagrams.
+    diagrams: HashMap<AbstractDomain, Bdd>,
+    /// The conditions of the diagrams that have been converted to conditions.
+    conditions: HashMap<Bdd, AbstractDomain>,
+}
+
+impl Table {
+    fn get_node(&self, bdd: Bdd) -> &Node {
+        &self.nodes[bdd.0 - 2]
+    }
+
+    /// Returns the diagram that is high if atom is true and low otherwise, which is high itself
+    /// if high and low are the same diagram.
+    fn make_node(&mut self, atom: &AbstractDomain, high: Bdd, low: Bdd) -> Bdd {
+        if high == low {
+            return high;
+        }
+        let key = (atom.clone(), high, low);
+        if let Some(bdd) = self.unique_nodes.get(&key) {
+            return *bdd;
+        }
+        let bdd = Bdd(self.nodes.len() + 2);
+        self.nodes.push(Node {
+            atom: atom.clone(),
+            high,
+            low,
+        });
+        self.unique_nodes.insert(key, bdd);
+        bdd
+    }
+
+    /// Returns the diagram of "left && right", or of "left || right" if !is_and.
+    /// Returns None if this takes more steps than are left in the budget.
+    fn combine(&mut self, left: Bdd, right: Bdd, is_and: bool, budget: &mut usize) -> Option<Bdd> {
+        let (absorbing, neutral) = if is_and { (FALSE, TRUE) } else { (TRUE, FALSE) };
+        if left == absorbing || right == absorbing {
+            return Some(absorbing);
+        }
+        if left == neutral || left == right {
+            return Some(right);
+        }
+        if right == neutral {
+            return Some(left);
+        }
+        let key = if left.0 < right.0 {
+            (is_and, left, right)
+        } else {
+            (is_and, right, left)
+        };
+        if let Some(bdd) = self.junctions.get(&key) {
+            return Some(*bdd);
+        }
+        *budget = budget.checked_sub(1)?;
+        let left_node = self.get_node(left);
+        let right_node = self.get_node(right);
+        let (atom, (left_high, left_low), (right_high, right_low)) =
+            match left_node.atom.cmp(&right_node.atom) {
+                Ordering::Less => (
+                    left_node.atom.clone(),
+                    (left_node.high, left_node.low),
+                    (right, right),
+                ),
+                Ordering::Greater => (
+                    right_node.atom.clone(),
+                    (left, left),
+                    (right_node.high, right_node.low),
+                ),
+                Ordering::Equal => (
+                    left_node.atom.clone(),
+                    (left_node.high, left_node.low),
+                    (right_node.high, right_node.low),
+                ),
+            };
+        let high = self.combine(left_high, right_high, is_and, budget)?;
+        let low = self.combine(left_low, right_low, is_and, budget)?;
+        let bdd = self.make_node(&atom, high, low);
+        self.junctions.insert(key, bdd);
+        Some(bdd)
+    }
+
+    /// Returns the diagram of "!bdd". This takes time that is linear in the size of bdd.
+    fn not(&mut self, bdd: Bdd) -> Bdd {
+        if bdd == TRUE {
+            return FALSE;
+        }
+        if bdd == FALSE {
+            return TRUE;
+        }
+        if let Some(negation) = self.negations.get(&bdd) {
+            return *negation;
+        }
+        let node = self.get_node(bdd);
+        let (atom, high, low) = (node.atom.clone(), node.high, node.low);
+        let high = self.not(high);
+        let low = self.not(low);
+        let negation = self.make_node(&atom, high, low);
+        self.negations.insert(bdd, negation);
+        self.negations.insert(negation, bdd);
+        negation
+    }
+
+    /// Returns the diagram of an atomic condition.
+    fn get_atom(&mut self, atom: AbstractDomain) -> Bdd {
+        self.make_node(&atom, TRUE, FALSE)
+    }
+
+    /// Returns the diagram of the given condition, or None if the condition is not known
+    /// or its diagram takes more steps to construct than are left in the budget.
+    fn get_bdd(&mut self, condition: &AbstractDomain, budget: &mut usize) -> Option<Bdd> {
+        if let Some(bdd) = self.diagrams.get(condition) {
+            return Some(*bdd);
+        }
+        let bdd = match &condition.expression {
+            Expression::Top | Expression::Bottom => return None,
+            Expression::CompileTimeConstant(ConstantDomain::True) => TRUE,
+            Expression::CompileTimeConstant(ConstantDomain::False) => FALSE,
+            Expression::And { left, right } => {
+                let left = self.get_bdd(left, budget)?;
+                let right = self.get_bdd(right, budget)?;
+                self.combine(left, right, true, budget)?
+            }
+            Expression::Or { left, right } => {
+                let left = self.get_bdd(left, budget)?;
+                let right = self.get_bdd(right, budget)?;
+                self.combine(left, right, false, budget)?
+            }
+            Expression::Not { operand } => {
+                let operand = self.get_bdd(operand, budget)?;
+                self.not(operand)
+            }
+            Expression::ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                let condition = self.get_bdd(condition, budget)?;
+                let consequent = self.get_bdd(consequent, budget)?;
+                let alternate = self.get_bdd(alternate, budget)?;
+                let not_condition = self.not(condition);
+                let when_true = self.combine(condition, consequent, true, budget)?;
+                let when_false = self.combine(not_condition, alternate, true, budget)?;
+                self.combine(when_true, when_false, false, budget)?
+            }
+            Expression::Ne { left, right } => {
+                let equals = self.get_atom(
+                    Expression::Equals {
+                        left: left.clone(),
+                        right: right.clone(),
+                    }
+                    .into(),
+                );
+                self.not(equals)
+            }
+            Expression::GreaterOrEqual { left, right } => self.get_atom(
+                Expression::LessOrEqual {
+                    left: right.clone(),
+                    right: left.clone(),
+                }
+                .into(),
+            ),
+            Expression::GreaterThan { left, right } => self.get_atom(
+                Expression::LessThan {
+                    left: right.clone(),
+                    right: left.clone(),
+                }
+                .into(),
+            ),
+            _ => self.get_atom(condition.clone()),
+        };
+        self.diagrams.insert(condition.clone(), bdd);
+        Some(bdd)
+    }
+
+    /// Returns the canonical condition of the given diagram. The condition is constructed
+    /// directly, rather than with AbstractDomain::and and friends, since those simplify their
+    /// results with diagrams.
+    fn get_condition(&mut self, bdd: Bdd) -> AbstractDomain {
+        if bdd == TRUE {
+            return abstract_domains::TRUE;
+        }
+        if bdd == FALSE {
+            return abstract_domains::FALSE;
+        }
+        if let Some(condition) = self.conditions.get(&bdd) {
+            return condition.clone();
+        }
+        let node = self.get_node(bdd);
+        let (atom, high, low) = (node.atom.clone(), node.high, node.low);
+        let condition: AbstractDomain = if high == TRUE && low == FALSE {
+            atom
+        } else if high == FALSE && low == TRUE {
+            negate_atom(&atom)
+        } else if low == FALSE {
+            Expression::And {
+                left: box atom,
+                right: box self.get_condition(high),
+            }
+            .into()
+        } else if high == FALSE {
+            Expression::And {
+                left: box negate_atom(&atom),
+                right: box self.get_condition(low),
+            }
+            .into()
+        } else if high == TRUE {
+            Expression::Or {
+                left: box atom,
+                right: box self.get_condition(low),
+            }
+            .into()
+        } else if low == TRUE {
+            Expression::Or {
+                left: box negate_atom(&atom),
+                right: box self.get_condition(high),
+            }
+            .into()
+        } else {
+            Expression::ConditionalExpression {
+                condition: box atom,
+                consequent: box self.get_condition(high),
+                alternate: box self.get_condition(low),
+            }
+            .into()
+        };
+        self.conditions.insert(bdd, condition.clone());
+        self.diagrams.insert(condition.clone(), bdd);
+        condition
+    }
+
+    /// Returns the diagram of "left && right", or of "left || right" if !is_and, where right
+    /// is negated first if negate_right.
+    fn get_junction(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        is_and: bool,
+        negate_right: bool,
+        budget: &mut usize,
+    ) -> Option<Bdd> {
+        let left = self.get_bdd(left, budget)?;
+        let mut right = self.get_bdd(right, budget)?;
+        if negate_right {
+            right = self.not(right);
+        }
+        self.combine(left, right, is_and, budget)
+    }
+}
+
+/// Returns "x != y" if atom is "x == y" and "!atom" otherwise.
+fn negate_atom(atom: &AbstractDomain) -> AbstractDomain {
+    match &atom.expression {
+        Expression::Equals { left, right } => Expression::Ne {
+            left: left.clone(),
+            right: right.clone(),
+        }
+        .into(),
+        _ => Expression::Not {
+            operand: box atom.clone(),
+        }
+        .into(),
+    }
+}
+
+thread_local! {
+    /// The diagrams that have been constructed by the current thread. The diagrams refer to
+    /// interned expressions, so the table is reset when the expression scope is dropped.
+    static TABLE: RefCell<Table> = RefCell::new(Table::default());
+}
+
+/// Forgets all of the diagrams that have been constructed by the current thread. This is done
+/// for every body that is analyzed, so that the table does not keep growing.
+pub fn reset() {
+    TABLE.with(|table| *table.borrow_mut() = Table::default());
+}
+
+/// Returns the diagram of "left && right", or of "left || right" if !is_and, where right is
+/// negated first if negate_right. Returns None if the diagram is not known or too costly.
+fn get_junction(
+    left: &AbstractDomain,
+    right: &AbstractDomain,
+    is_and: bool,
+    negate_right: bool,
+) -> Option<Bdd> {
+    let mut budget = k_limits::MAX_BDD_STEPS;
+    TABLE.with(|table| {
+        table
+            .borrow_mut()
+            .get_junction(left, right, is_and, negate_right, &mut budget)
+    })
+}
+
+/// Returns true if "condition => other" follows from the boolean skeletons of the conditions.
+/// Returning false does not imply the implication is false, just that we do not know.
+pub fn implies(condition: &AbstractDomain, other: &AbstractDomain) -> bool {
+    get_junction(condition, other, true, true) == Some(FALSE)
+}
+
+/// Returns true if "condition => !other" follows from the boolean skeletons of the conditions.
+/// Returning false does not imply the implication is false, just that we do not know.
+pub fn implies_not(condition: &AbstractDomain, other: &AbstractDomain) -> bool {
+    get_junction(condition, other, true, false) == Some(FALSE)
+}
+
+/// Returns true if the boolean skeletons of the given conditions are equivalent.
+pub fn are_equivalent(left: &AbstractDomain, right: &AbstractDomain) -> bool {
+    let mut budget = k_limits::MAX_BDD_STEPS;
+    TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        let left = table.get_bdd(left, &mut budget);
+        left.is_some() && left == table.get_bdd(right, &mut budget)
+    })
+}
+
+/// Returns the canonical form of "left && right", if it is known and not larger than the
+/// plain conjunction.
+pub fn and(left: &AbstractDomain, right: &AbstractDomain) -> Option<AbstractDomain> {
+    get_canonical_junction(left, right, true)
+}
+
+/// Returns the canonical form of "left || right", if it is known and not larger than the
+/// plain disjunction.
+pub fn or(left: &AbstractDomain, right: &AbstractDomain) -> Option<AbstractDomain> {
+    get_canonical_junction(left, right, false)
+}
+
+/// Returns the canonical form of "left && right", or of "left || right" if !is_and, if it is
+/// known and not larger than the plain conjunction or disjunction.
+fn get_canonical_junction(
+    left: &AbstractDomain,
+    right: &AbstractDomain,
+    is_and: bool,
+) -> Option<AbstractDomain> {
+    let bdd = get_junction(left, right, is_and, false)?;
+    let condition = TABLE.with(|table| table.borrow_mut().get_condition(bdd));
+    let size = expression_arena::get_size(condition.expression).node_count;
+    let plain_size = expression_arena::get_size(left.expression)
+        .node_count
+        .saturating_add(expression_arena::get_size(right.expression).node_count)
+        .saturating_add(1);
+    if size <= plain_size {
+        Some(condition)
+    } else {
+        None
+    }
+}
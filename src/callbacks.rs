@@ -5,14 +5,22 @@
 #![allow(clippy::borrowed_box)]
 
 use crate::constant_domain::ConstantValueCache;
-use crate::k_limits;
-use crate::smt_solver::SolverStub;
+use crate::expression_arena;
+use crate::linear_solver::LinearSolver;
+use crate::options::{Options, SolverChoice};
+use crate::smt_lib_solver::SmtLibSolver;
+use crate::smt_solver::{SmtSolver, SolverStub};
+use crate::solver_cache::CachingSolver;
+use crate::statistics;
 use crate::summaries;
 use crate::visitors::{MirVisitor, MirVisitorCrateContext};
+#[cfg(feature = "z3")]
+use crate::z3_solver::Z3Solver;
 
 use rustc::hir::def_id::DefId;
 use rustc::session::config::{self, ErrorOutputType, Input};
 use rustc::session::Session;
+use rustc::ty::TyCtxt;
 use rustc_codegen_utils::codegen_backend::CodegenBackend;
 use rustc_driver::{driver, Compilation, CompilerCalls, RustcDefaultCalls};
 use rustc_metadata::cstore::CStore;
@@ -33,6 +41,8 @@ pub struct MiraiCallbacks {
     /// By default, this just emits the message. When overridden it can
     /// intercept and buffer the diagnostics, which is used by the test harness.
     emit_diagnostic: fn(&mut DiagnosticBuilder<'_>, &mut Vec<Diagnostic>) -> (),
+    /// The options that were extracted from the command line arguments.
+    options: Options,
     /// A path to the directory where analysis output, such as the summary cache, should be stored.
     output_directory: PathBuf,
     /// True if this run is done via cargo test
@@ -42,16 +52,22 @@ pub struct MiraiCallbacks {
 /// Constructors
 impl MiraiCallbacks {
     pub fn new() -> MiraiCallbacks {
+        Self::with_options(Options::default())
+    }
+
+    pub fn with_options(options: Options) -> MiraiCallbacks {
         MiraiCallbacks {
             consume_buffered_diagnostics: box |_bd: &Vec<Diagnostic>| {},
             default_calls: box RustcDefaultCalls,
             emit_diagnostic: |db: &mut DiagnosticBuilder<'_>, _buf: &mut Vec<Diagnostic>| db.emit(),
+            options,
             output_directory: PathBuf::default(),
             test_run: false,
         }
     }
 
     pub fn with_buffered_diagnostics(
+        options: Options,
         consume_buffered_diagnostics: Box<dyn Fn(&Vec<Diagnostic>) -> ()>,
         emit_diagnostic: fn(&mut DiagnosticBuilder<'_>, &mut Vec<Diagnostic>) -> (),
     ) -> MiraiCallbacks {
@@ -59,6 +75,7 @@ impl MiraiCallbacks {
             consume_buffered_diagnostics,
             default_calls: box RustcDefaultCalls,
             emit_diagnostic,
+            options,
             output_directory: PathBuf::default(),
             test_run: true,
         }
@@ -84,9 +101,9 @@ impl<'a> CompilerCalls<'a> for MiraiCallbacks {
         descriptions: &errors::registry::Registry,
         output: ErrorOutputType,
     ) -> Compilation {
-        // todo: #3 extract options that are relevant to Mirai and store them in self
-        // also remove these arguments so that Rustc does not try to process them
-        // and possibly also add arguments here to make the compilation process more
+        // Options that are relevant to Mirai have already been removed from the command line
+        // arguments (see Options::parse_from_args), since Rustc would reject them.
+        // todo: possibly add arguments here to make the compilation process more
         // friendly to static analysis.
         self.default_calls
             .early_callback(matches, options, config, descriptions, output)
@@ -142,6 +159,7 @@ impl<'a> CompilerCalls<'a> for MiraiCallbacks {
                 state,
                 &self.consume_buffered_diagnostics,
                 self.emit_diagnostic,
+                &self.options,
                 &mut self.output_directory.clone(),
             )
         });
@@ -162,24 +180,101 @@ fn after_analysis(
     state: &mut driver::CompileState<'_, '_>,
     consume_buffered_diagnostics: &Box<dyn Fn(&Vec<Diagnostic>) -> ()>,
     emit_diagnostic: fn(&mut DiagnosticBuilder<'_>, &mut Vec<Diagnostic>) -> (),
+    options: &Options,
     output_directory: &mut PathBuf,
 ) {
     let session = state.session;
     let tcx = state.tcx.unwrap();
-    output_directory.set_file_name(".summary_store");
-    output_directory.set_extension("sled");
-    let summary_store_path = String::from(output_directory.to_str().unwrap());
-    info!("storing summaries at {}", summary_store_path);
-    let mut persistent_summary_cache =
-        summaries::PersistentSummaryCache::new(&tcx, summary_store_path);
+    // Declared first, so that it is dropped last, after everything that refers to expressions.
+    // The abstract values of the analysis live in the summary cache, the solver and the
+    // diagnostics, which are all dropped before the scope.
+    let _expression_scope = unsafe { expression_arena::enter_scope() };
+    let mut options = options.clone();
+    if options.summary_store_path.is_none() {
+        output_directory.set_file_name(".summary_store");
+        output_directory.set_extension("sled");
+        options.summary_store_path = Some(output_directory.clone());
+    }
+    let mut persistent_summary_cache = summaries::PersistentSummaryCache::new(&tcx, &options);
+    let all_diagnostics = match &options.solver {
+        SolverChoice::Stub => analyze_bodies(
+            session,
+            tcx,
+            &options,
+            emit_diagnostic,
+            &mut persistent_summary_cache,
+            &mut SolverStub::default(),
+        ),
+        SolverChoice::Linear => analyze_bodies(
+            session,
+            tcx,
+            &options,
+            emit_diagnostic,
+            &mut persistent_summary_cache,
+            &mut LinearSolver::default(),
+        ),
+        #[cfg(feature = "z3")]
+        SolverChoice::Z3 => analyze_bodies(
+            session,
+            tcx,
+            &options,
+            emit_diagnostic,
+            &mut persistent_summary_cache,
+            &mut Z3Solver::default(),
+        ),
+        #[cfg(not(feature = "z3"))]
+        SolverChoice::Z3 => unreachable!("the z3 option is rejected when Z3 is not available"),
+        SolverChoice::SmtLib(solver_path) => {
+            let transcript_path = options.solver_transcript_path.as_ref().map(|p| p.as_path());
+            match SmtLibSolver::new(solver_path, transcript_path) {
+                Ok(mut smt_solver) => analyze_bodies(
+                    session,
+                    tcx,
+                    &options,
+                    emit_diagnostic,
+                    &mut persistent_summary_cache,
+                    &mut smt_solver,
+                ),
+                Err(err) => session.fatal(&format!(
+                    "could not start the SMT solver {}: {}",
+                    solver_path.display(),
+                    err
+                )),
+            }
+        }
+    };
+    consume_buffered_diagnostics(&all_diagnostics);
+    statistics::report();
+    info!("done with analysis");
+}
+
+/// Analyzes all of the bodies in the crate, repeating the analysis of bodies that depend on
+/// summaries that have changed, until the summaries reach a fixed point or the maximum number of
+/// iterations specified by the options has been reached.
+/// The results of solver queries are cached next to the summaries, so that each distinct
+/// query is decided by smt_solver only once.
+/// Returns the diagnostics reported during the final analysis of each body.
+fn analyze_bodies<'a, 'tcx, E>(
+    session: &'tcx Session,
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    options: &Options,
+    emit_diagnostic: fn(&mut DiagnosticBuilder<'_>, &mut Vec<Diagnostic>) -> (),
+    persistent_summary_cache: &mut summaries::PersistentSummaryCache<'a, 'tcx>,
+    smt_solver: &mut dyn SmtSolver<E>,
+) -> Vec<Diagnostic> {
     let mut constant_value_cache = ConstantValueCache::default();
+    let mut caching_solver = CachingSolver::new(
+        smt_solver,
+        &options.solver,
+        persistent_summary_cache.get_db(),
+    );
     let mut defs_to_analyze: HashSet<DefId> = HashSet::from_iter(tcx.body_owners());
     let mut defs_to_reanalyze: HashSet<DefId> = HashSet::new();
     let mut defs_to_check: HashSet<DefId> = HashSet::new();
     let mut diagnostics_for: HashMap<DefId, Vec<Diagnostic>> = HashMap::new();
     let mut not_done = true;
     let mut iteration_count = 0;
-    while not_done && iteration_count < k_limits::MAX_OUTER_FIXPOINT_ITERATIONS {
+    while not_done && iteration_count < options.max_fixpoint_iterations {
         for def_id in tcx.body_owners() {
             let analyze_it = defs_to_analyze.contains(&def_id);
             let check_it = !analyze_it && defs_to_check.contains(&def_id);
@@ -201,18 +296,17 @@ fn after_analysis(
             let mut buffered_diagnostics: Vec<Diagnostic> = vec![];
             let old_summary_if_changed = {
                 let mir = tcx.optimized_mir(def_id);
-                // todo: #3 provide a helper that returns the solver as specified by a compiler switch.
-                let mut smt_solver = SolverStub::default();
                 let mut mir_visitor = MirVisitor::new(MirVisitorCrateContext {
+                    options,
                     buffered_diagnostics: &mut buffered_diagnostics,
                     emit_diagnostic,
                     session,
                     tcx,
                     def_id,
                     mir,
-                    summary_cache: &mut persistent_summary_cache,
+                    summary_cache: &mut *persistent_summary_cache,
                     constant_value_cache: &mut constant_value_cache,
-                    smt_solver: &mut smt_solver,
+                    smt_solver: &mut caching_solver,
                 });
                 mir_visitor.visit_body()
             };
@@ -245,6 +339,5 @@ fn after_analysis(
     for (_, mut diagnostics) in diagnostics_for.drain() {
         all_diagnostics.append(&mut diagnostics);
     }
-    consume_buffered_diagnostics(&all_diagnostics);
-    info!("done with analysis");
+    all_diagnostics
 }
@@ -0,0 +1,302 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+use crate::expression::ExpressionType;
+use crate::interval_domain::IntervalDomain;
+
+use std::cmp;
+use std::convert::TryFrom;
+
+/// An element of the Congruence domain is the set of integers m*k + r, where k is any integer,
+/// denoted by a modulus m and a residue r, with 0 <= r < m. A modulus of 0 denotes the singleton
+/// set {r}, in which case r can be any i128 value. A modulus of 1 denotes all integers.
+/// Congruence domain elements are constructed on demand from AbstractDomain expressions.
+/// They are most useful for checking alignment and stride facts, for example that an index
+/// that is stepped by 2 stays even.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq, Hash, Ord)]
+pub struct CongruenceDomain {
+    modulus: i128,
+    residue: i128,
+}
+
+// A residue that is not less than the modulus does not correspond to any integer.
+pub const BOTTOM: CongruenceDomain = CongruenceDomain {
+    modulus: 1,
+    residue: 1,
+};
+
+pub const TOP: CongruenceDomain = CongruenceDomain {
+    modulus: 1,
+    residue: 0,
+};
+
+// The largest number of low order bits that are tracked by bitwise operations, so that 2^n
+// remains an i128.
+const MAX_LOW_BITS: u32 = 126;
+
+impl From<i128> for CongruenceDomain {
+    fn from(i: i128) -> CongruenceDomain {
+        CongruenceDomain {
+            modulus: 0,
+            residue: i,
+        }
+    }
+}
+
+impl From<u128> for CongruenceDomain {
+    fn from(u: u128) -> CongruenceDomain {
+        match i128::try_from(u) {
+            Ok(i) => i.into(),
+            Err(..) => TOP.clone(),
+        }
+    }
+}
+
+impl CongruenceDomain {
+    // Returns |m|*Z + r.
+    pub fn new(modulus: i128, residue: i128) -> Self {
+        Self::from_parts(modulus.checked_abs(), Some(residue))
+    }
+
+    // Returns m*Z + r, or top if m or r could not be computed without overflowing.
+    fn from_parts(modulus: Option<i128>, residue: Option<i128>) -> Self {
+        match (modulus, residue) {
+            (Some(0), Some(residue)) => residue.into(),
+            (Some(modulus), Some(residue)) => CongruenceDomain {
+                modulus,
+                residue: residue.rem_euclid(modulus),
+            },
+            _ => TOP.clone(),
+        }
+    }
+
+    // Returns the number of low order bits that are the same for all elements of self.
+    fn known_low_bits(&self) -> u32 {
+        if self.modulus == 0 {
+            MAX_LOW_BITS
+        } else {
+            cmp::min(self.modulus.trailing_zeros(), MAX_LOW_BITS)
+        }
+    }
+
+    // Returns the number of low order bits that are zero for all elements of self.
+    fn known_zero_low_bits(&self) -> u32 {
+        cmp::min(self.residue.trailing_zeros(), self.known_low_bits())
+    }
+
+    // (m1*Z + r1) + (m2*Z + r2) = gcd(m1, m2)*Z + (r1 + r2)
+    pub fn add(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        Self::from_parts(
+            Some(gcd(self.modulus, other.modulus)),
+            self.residue.checked_add(other.residue),
+        )
+    }
+
+    // Returns the value of self, if it is a singleton.
+    pub fn as_constant(&self) -> Option<i128> {
+        if self.modulus == 0 {
+            Some(self.residue)
+        } else {
+            None
+        }
+    }
+
+    // (m1*Z + r1) & (m2*Z + r2): the low order bits that are known for both operands are known
+    // for the result, and so are the low order bits that are known to be zero for either operand.
+    // So if k is the larger of these numbers, the result is in 2^k*Z + ((r1 & r2) mod 2^k).
+    pub fn bit_and(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let known_bits = cmp::min(self.known_low_bits(), other.known_low_bits());
+        let zero_bits = cmp::max(self.known_zero_low_bits(), other.known_zero_low_bits());
+        let low_bits = cmp::max(known_bits, zero_bits);
+        Self::from_parts(Some(1 << low_bits), Some(self.residue & other.residue))
+    }
+
+    // (m*Z + r) as target_type = gcd(m, 2^n)*Z + r, where n is the bit length of target_type,
+    // since the cast adds or subtracts a multiple of 2^n if the value does not fit.
+    pub fn cast(&self, target_type: &ExpressionType) -> Self {
+        if self.is_bottom() || !target_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        let bit_length = cmp::min(u32::from(target_type.bit_length()), MAX_LOW_BITS);
+        Self::from_parts(Some(gcd(self.modulus, 1 << bit_length)), Some(self.residue))
+    }
+
+    // Returns Some(true) if self and other are the same singleton, Some(false) if they have no
+    // elements in common, and None otherwise.
+    // m1*Z + r1 and m2*Z + r2 have no elements in common iff r1 - r2 is not a multiple of
+    // gcd(m1, m2).
+    pub fn equals(&self, other: &Self) -> Option<bool> {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        if self.modulus == 0 && other.modulus == 0 {
+            return Some(self.residue == other.residue);
+        }
+        let difference = self.residue.checked_sub(other.residue)?;
+        if difference % gcd(self.modulus, other.modulus) != 0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // The expression that corresponds to this element is not known to result in an integer value.
+    pub fn is_bottom(&self) -> bool {
+        self.modulus != 0 && (self.residue < 0 || self.residue >= self.modulus)
+    }
+
+    // All concrete integer values belong to this element, so we know nothing.
+    pub fn is_top(&self) -> bool {
+        self.modulus == 1 && self.residue == 0
+    }
+
+    // (m1*Z + r1) join (m2*Z + r2) = gcd(m1, m2, r1 - r2)*Z + r1
+    pub fn join(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let difference = match self.residue.checked_sub(other.residue) {
+            Some(difference) => difference,
+            None => return TOP.clone(),
+        };
+        Self::from_parts(
+            Some(gcd(gcd(self.modulus, other.modulus), difference)),
+            Some(self.residue),
+        )
+    }
+
+    // Returns the elements of self that are also in other, if other is a subset of self or the
+    // other way around. Otherwise returns self, since the intersection is not always a single
+    // element of the domain.
+    pub fn meet(&self, other: &Self) -> Self {
+        if other.subset(self) {
+            other.clone()
+        } else {
+            self.clone()
+        }
+    }
+
+    // (m1*Z + r1) * (m2*Z + r2) = gcd(m1*m2, m1*r2, m2*r1)*Z + r1*r2
+    pub fn mul(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let modulus = self
+            .modulus
+            .checked_mul(other.modulus)
+            .and_then(|m1m2| {
+                let m1r2 = self.modulus.checked_mul(other.residue)?;
+                Some(gcd(m1m2, m1r2))
+            })
+            .and_then(|m| {
+                let m2r1 = other.modulus.checked_mul(self.residue)?;
+                Some(gcd(m, m2r1))
+            });
+        Self::from_parts(modulus, self.residue.checked_mul(other.residue))
+    }
+
+    // -(m*Z + r) = m*Z + -r
+    pub fn neg(&self) -> Self {
+        if self.is_bottom() {
+            return BOTTOM.clone();
+        }
+        Self::from_parts(Some(self.modulus), self.residue.checked_neg())
+    }
+
+    // Returns the largest subset of self that is a singleton, if the only element of self that is
+    // in the given interval is the singleton. Otherwise returns self.
+    pub fn reduce(&self, interval: &IntervalDomain) -> Self {
+        if self.modulus <= 1 {
+            return self.clone();
+        }
+        if let (Some(lower_bound), Some(upper_bound)) =
+            (interval.lower_bound(), interval.upper_bound())
+        {
+            // The smallest element of self that is not less than lower_bound.
+            let first = self
+                .residue
+                .checked_sub(lower_bound)
+                .and_then(|difference| {
+                    lower_bound.checked_add(difference.rem_euclid(self.modulus))
+                });
+            if let Some(first) = first {
+                if first <= upper_bound && first.saturating_add(self.modulus) > upper_bound {
+                    return first.into();
+                }
+            }
+        }
+        self.clone()
+    }
+
+    // (m1*Z + r1) % (m2*Z + r2) = x - y*q for some x, y and q, where y*q is a multiple of
+    // g = gcd(m2, r2), so the result is in gcd(m1, g)*Z + r1.
+    pub fn rem(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() || other.as_constant() == Some(0) {
+            return BOTTOM.clone();
+        }
+        let divisor_gcd = gcd(other.modulus, other.residue);
+        Self::from_parts(Some(gcd(self.modulus, divisor_gcd)), Some(self.residue))
+    }
+
+    // (m*Z + r) << k = ((m*Z + r) * 2^k) as result_type, if k is known. Shift amounts that
+    // overflow are left out, since they cause a panic.
+    pub fn shl(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        if self.is_bottom() || other.is_bottom() || !result_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        match other.as_constant() {
+            Some(shift)
+                if shift >= 0
+                    && shift < i128::from(result_type.bit_length())
+                    && shift < i128::from(MAX_LOW_BITS) =>
+            {
+                self.mul(&(1i128 << shift).into()).cast(result_type)
+            }
+            _ => TOP.clone(),
+        }
+    }
+
+    // (m1*Z + r1) - (m2*Z + r2) = gcd(m1, m2)*Z + (r1 - r2)
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    // Returns true if every value in self is also in other.
+    // m1*Z + r1 subset m2*Z + r2 iff m1 is a multiple of m2 and r1 - r2 is a multiple of m2.
+    pub fn subset(&self, other: &Self) -> bool {
+        if self.is_bottom() || other.is_bottom() {
+            return false;
+        }
+        if other.modulus == 0 {
+            return self.modulus == 0 && self.residue == other.residue;
+        }
+        match self.residue.checked_sub(other.residue) {
+            Some(difference) => {
+                self.modulus % other.modulus == 0 && difference % other.modulus == 0
+            }
+            None => false,
+        }
+    }
+}
+
+// Returns the greatest common divisor of the magnitudes of a and b, where gcd(0, b) = |b|.
+// The only result that does not fit into an i128 is 2^127, which is replaced with 2^126, since
+// that still divides both operands.
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.wrapping_abs() as u128, b.wrapping_abs() as u128);
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    i128::try_from(a).unwrap_or(1 << 126)
+}
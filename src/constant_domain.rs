@@ -180,6 +180,58 @@ impl ConstantDomain {
         }
     }
 
+    /// Returns a constant that is "self as target_type". Integers keep the low order bits that
+    /// fit into target_type, while floating point values saturate when converted to integers.
+    pub fn cast(&self, target_type: &ExpressionType) -> Self {
+        match self {
+            ConstantDomain::F32(val) => {
+                Self::cast_float(f64::from(f32::from_bits(*val)), target_type)
+            }
+            ConstantDomain::F64(val) => Self::cast_float(f64::from_bits(*val), target_type),
+            ConstantDomain::I128(val) => match target_type {
+                ExpressionType::F32 => ConstantDomain::F32((*val as f32).to_bits()),
+                ExpressionType::F64 => ConstantDomain::F64((*val as f64).to_bits()),
+                _ => Self::cast_integer(*val as u128, target_type),
+            },
+            ConstantDomain::U128(val) => match target_type {
+                ExpressionType::F32 => ConstantDomain::F32((*val as f32).to_bits()),
+                ExpressionType::F64 => ConstantDomain::F64((*val as f64).to_bits()),
+                _ => Self::cast_integer(*val, target_type),
+            },
+            _ => ConstantDomain::Bottom,
+        }
+    }
+
+    /// Returns the value of target_type whose representation is the low order bits of bits.
+    fn cast_integer(bits: u128, target_type: &ExpressionType) -> Self {
+        if !target_type.is_integer() {
+            return ConstantDomain::Bottom;
+        }
+        let unused_bits = 128 - u32::from(target_type.bit_length());
+        if target_type.is_signed_integer() {
+            ConstantDomain::I128(((bits << unused_bits) as i128) >> unused_bits)
+        } else {
+            ConstantDomain::U128((bits << unused_bits) >> unused_bits)
+        }
+    }
+
+    /// Returns the value of target_type that val converts to. Conversions to integer types
+    /// round towards zero and saturate at the smallest and largest values of the type, while
+    /// NaN becomes zero.
+    fn cast_float(val: f64, target_type: &ExpressionType) -> Self {
+        let (min, max) = (target_type.min_value(), target_type.max_value());
+        match target_type {
+            ExpressionType::F32 => ConstantDomain::F32((val as f32).to_bits()),
+            ExpressionType::F64 => ConstantDomain::F64(val.to_bits()),
+            _ if !target_type.is_integer() => ConstantDomain::Bottom,
+            _ if val.is_nan() => Self::cast_integer(0, target_type),
+            _ if val <= min as f64 => Self::cast_integer(min as u128, target_type),
+            _ if val >= max as f64 => Self::cast_integer(max, target_type),
+            _ if val < 0.0 => ConstantDomain::I128(val as i128),
+            _ => Self::cast_integer(val as u128, target_type),
+        }
+    }
+
     /// Returns a constant that is "self / other".
     pub fn div(&self, other: &Self) -> Self {
         match (&self, &other) {
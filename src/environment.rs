@@ -5,11 +5,16 @@
 
 use crate::abstract_domains::AbstractDomain;
 use crate::abstract_value::{self, AbstractValue, Path};
+use crate::constant_domain::ConstantDomain;
 use crate::expression::Expression;
+use crate::interval_domain::IntervalDomain;
+use crate::octagon_domain::{OctagonDomain, OctagonTerm};
+use crate::variant_set_domain::VariantSetDomain;
 
 use rpds::HashTrieMap;
 use rustc::mir::BasicBlock;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
 use std::fmt::{Debug, Formatter, Result};
 
 #[derive(Clone, Eq, PartialEq)]
@@ -20,6 +25,15 @@ pub struct Environment {
     pub exit_conditions: HashMap<BasicBlock, AbstractValue>,
     /// Does not include any entries where the value is abstract_value::Bottom
     pub value_map: HashTrieMap<Path, AbstractValue>,
+    /// Relations between the integer values at the paths of value_map, and the values of
+    /// unknown variables, such as the lengths of arrays that are parameters.
+    pub octagon: OctagonDomain,
+    /// The variants that the enums at the paths of value_map may have. Enums that have no entry
+    /// may have any of the variants of their types.
+    pub variants: HashTrieMap<Path, VariantSetDomain>,
+    /// The variants that the enum that is switched on by this block may have when control exits
+    /// from this block to the successor block.
+    pub exit_variants: HashMap<BasicBlock, (Path, VariantSetDomain)>,
 }
 
 /// Default
@@ -29,6 +43,9 @@ impl Environment {
             entry_condition: abstract_value::TRUE,
             exit_conditions: HashMap::default(),
             value_map: HashTrieMap::default(),
+            octagon: OctagonDomain::default(),
+            variants: HashTrieMap::default(),
+            exit_variants: HashMap::default(),
         }
     }
 }
@@ -49,6 +66,8 @@ impl Environment {
     /// Updates the path to value map so that the given path now points to the given value.
     pub fn update_value_at(&mut self, path: Path, value: AbstractValue) {
         debug!("updating value of {:?} to {:?}", path, value);
+        self.update_octagon_at(&path, &value);
+        self.forget_variants_at(&path);
         if value.is_bottom() {
             self.value_map = self.value_map.remove(&path);
             return;
@@ -67,6 +86,17 @@ impl Environment {
         self.value_map = self.value_map.insert(path, value);
     }
 
+    /// Restricts the value at the given path to the given interval, which must include all of
+    /// the values that the path can have at the entry of the given block, for example because of
+    /// a loop invariant. Unlike update_value_at, this keeps the relations of the octagon and the
+    /// variants of the path.
+    pub fn restrict_value_at(&mut self, path: &Path, block: BasicBlock, interval: &IntervalDomain) {
+        if let Some(value) = self.value_at(path) {
+            let value = value.restrict_to_interval(path, block.index(), interval);
+            self.value_map = self.value_map.insert(path.clone(), value);
+        }
+    }
+
     /// If the path contains an abstract value that was constructed with a join, the path is
     /// concretized into two paths where the abstract value is replaced by the consequent
     /// and alternate, respectively. These paths can then be weakly updated to reflect the
@@ -145,26 +175,377 @@ impl Environment {
         }
     }
 
+    /// Updates the octagon so that it relates the value at path to the values at other paths,
+    /// given that value is about to be assigned to path.
+    fn update_octagon_at(&mut self, path: &Path, value: &AbstractValue) {
+        match self.get_as_octagon_sum(&value.domain) {
+            Some((Some(source), offset)) => self.octagon.assign(path, &source, offset),
+            Some((None, constant)) => self.octagon.assign_constant(path, constant),
+            None => self.octagon.forget(path),
+        }
+    }
+
+    /// Adds to the octagon the relations between values that follow from the given condition
+    /// being true. If the condition cannot be true, given the relations that are already known,
+    /// the octagon becomes bottom.
+    pub fn refine_octagon_with(&mut self, path_condition: &AbstractValue) {
+        let mut constraints = Vec::new();
+        self.get_as_octagon_constraints(&path_condition.domain, true, &mut constraints);
+        if !constraints.is_empty() {
+            self.octagon.add_constraints(constraints);
+        }
+    }
+
+    /// Returns the value of the given condition, if the relations in the octagon decide it.
+    pub fn decide_using_octagon(&self, condition: &AbstractValue) -> Option<bool> {
+        if self.octagon.is_top() || self.octagon.is_bottom() {
+            return None;
+        }
+        let implies_all = |is_true: bool| {
+            let mut constraints = Vec::new();
+            self.get_as_octagon_constraints(&condition.domain, is_true, &mut constraints)
+                && !constraints.is_empty()
+                && constraints
+                    .iter()
+                    .all(|(a, b, c)| self.octagon.implies(a, b, *c))
+        };
+        if implies_all(true) {
+            Some(true)
+        } else if implies_all(false) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Gives target_path, and the paths rooted in it, the variant sets of source_path and of the
+    /// corresponding paths rooted in it.
+    pub fn copy_variants(&mut self, source_path: &Path, target_path: &Path) {
+        let copies: Vec<(Path, VariantSetDomain)> = self
+            .variants
+            .iter()
+            .filter_map(|(path, variants)| {
+                if path == source_path {
+                    Some((target_path.clone(), *variants))
+                } else if path.is_rooted_by(source_path) {
+                    Some((
+                        path.replace_root(source_path, target_path.clone()),
+                        *variants,
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        self.forget_variants_at(target_path);
+        for (path, variants) in copies {
+            self.variants = self.variants.insert(path, variants);
+        }
+    }
+
+    /// Forgets the variant sets of path and of the paths rooted in it, given that a new value is
+    /// about to be assigned to path.
+    fn forget_variants_at(&mut self, path: &Path) {
+        let stale_paths: Vec<Path> = self
+            .variants
+            .keys()
+            .filter(|p| *p == path || p.is_rooted_by(path))
+            .cloned()
+            .collect();
+        for stale_path in stale_paths {
+            self.variants = self.variants.remove(&stale_path);
+        }
+    }
+
+    /// Refines the variant set of the enum that is switched on by this block with the variants
+    /// that it may have when control goes to the given successor block. The refinements for the
+    /// other successors only apply to the edges from this block, so they are dropped.
+    pub fn refine_variants_for(&mut self, successor: BasicBlock) {
+        if let Some((path, variants)) = self.exit_variants.remove(&successor) {
+            self.variants = self.variants.insert(path, variants);
+        }
+        self.exit_variants.clear();
+    }
+
+    /// Records that the enum at path has one of the given variants.
+    pub fn set_variants_at(&mut self, path: Path, variants: VariantSetDomain) {
+        self.variants = self.variants.insert(path, variants);
+    }
+
+    /// Adds to constraints the octagonal constraints that follow from condition being equal to
+    /// is_true. Returns true if the added constraints also imply that condition is equal to
+    /// is_true, which is not the case if condition has parts that cannot be expressed by
+    /// octagonal constraints.
+    fn get_as_octagon_constraints(
+        &self,
+        condition: &AbstractDomain,
+        is_true: bool,
+        constraints: &mut Vec<(OctagonTerm, OctagonTerm, i128)>,
+    ) -> bool {
+        match &condition.expression {
+            Expression::And { left, right } if is_true => {
+                let left_is_exact = self.get_as_octagon_constraints(left, true, constraints);
+                let right_is_exact = self.get_as_octagon_constraints(right, true, constraints);
+                left_is_exact && right_is_exact
+            }
+            Expression::Or { left, right } if !is_true => {
+                let left_is_exact = self.get_as_octagon_constraints(left, false, constraints);
+                let right_is_exact = self.get_as_octagon_constraints(right, false, constraints);
+                left_is_exact && right_is_exact
+            }
+            Expression::Not { operand } => {
+                self.get_as_octagon_constraints(operand, !is_true, constraints)
+            }
+            Expression::Equals { left, right } if is_true => {
+                let left_is_exact = self.add_less_or_equal_constraint(left, right, 0, constraints);
+                let right_is_exact = self.add_less_or_equal_constraint(right, left, 0, constraints);
+                left_is_exact && right_is_exact
+            }
+            Expression::Ne { left, right } if !is_true => {
+                let left_is_exact = self.add_less_or_equal_constraint(left, right, 0, constraints);
+                let right_is_exact = self.add_less_or_equal_constraint(right, left, 0, constraints);
+                left_is_exact && right_is_exact
+            }
+            // !(x < y) is y <= x, and !(x <= y) is y < x, since the values are integers.
+            Expression::GreaterOrEqual { left, right } if is_true => {
+                self.add_less_or_equal_constraint(right, left, 0, constraints)
+            }
+            Expression::GreaterOrEqual { left, right } => {
+                self.add_less_or_equal_constraint(left, right, -1, constraints)
+            }
+            Expression::GreaterThan { left, right } if is_true => {
+                self.add_less_or_equal_constraint(right, left, -1, constraints)
+            }
+            Expression::GreaterThan { left, right } => {
+                self.add_less_or_equal_constraint(left, right, 0, constraints)
+            }
+            Expression::LessOrEqual { left, right } if is_true => {
+                self.add_less_or_equal_constraint(left, right, 0, constraints)
+            }
+            Expression::LessOrEqual { left, right } => {
+                self.add_less_or_equal_constraint(right, left, -1, constraints)
+            }
+            Expression::LessThan { left, right } if is_true => {
+                self.add_less_or_equal_constraint(left, right, -1, constraints)
+            }
+            Expression::LessThan { left, right } => {
+                self.add_less_or_equal_constraint(right, left, 0, constraints)
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds the octagonal constraint left - right <= c to constraints, if left and right are
+    /// integer values whose difference can be expressed in terms of at most two paths.
+    /// Returns true if a constraint was added.
+    fn add_less_or_equal_constraint(
+        &self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        c: i128,
+        constraints: &mut Vec<(OctagonTerm, OctagonTerm, i128)>,
+    ) -> bool {
+        let (left_path, left_offset) = match self.get_as_octagon_sum(left) {
+            Some(sum) => sum,
+            None => return false,
+        };
+        let (right_path, right_offset) = match self.get_as_octagon_sum(right) {
+            Some(sum) => sum,
+            None => return false,
+        };
+        // left_path + left_offset - (right_path + right_offset) <= c
+        let c = match c
+            .checked_add(right_offset)
+            .and_then(|c| c.checked_sub(left_offset))
+        {
+            Some(c) => c,
+            None => return false,
+        };
+        match (left_path, right_path) {
+            (Some(left_path), Some(right_path)) => {
+                constraints.push((
+                    OctagonTerm::Positive(left_path),
+                    OctagonTerm::Negative(right_path),
+                    c,
+                ));
+            }
+            (Some(left_path), None) => {
+                let term = OctagonTerm::Positive(left_path);
+                match c.checked_mul(2) {
+                    Some(c) => constraints.push((term.clone(), term, c)),
+                    None => return false,
+                }
+            }
+            (None, Some(right_path)) => {
+                let term = OctagonTerm::Negative(right_path);
+                match c.checked_mul(2) {
+                    Some(c) => constraints.push((term.clone(), term, c)),
+                    None => return false,
+                }
+            }
+            // Comparisons of constants are folded by the other domains.
+            (None, None) => return false,
+        }
+        true
+    }
+
+    /// If the value is an integer that is equal to the value at some path plus a constant offset,
+    /// returns the path and the offset. If the value is an integer constant, returns the constant.
+    fn get_as_octagon_sum(&self, value: &AbstractDomain) -> Option<(Option<Path>, i128)> {
+        match &value.expression {
+            Expression::CompileTimeConstant(ConstantDomain::I128(val)) => Some((None, *val)),
+            Expression::CompileTimeConstant(ConstantDomain::U128(val)) => {
+                Some((None, i128::try_from(*val).ok()?))
+            }
+            Expression::Add { left, right } => {
+                let left_sum = self.get_as_octagon_sum(left);
+                let right_sum = self.get_as_octagon_sum(right);
+                match (left_sum, right_sum) {
+                    (Some((left_path, left_offset)), Some((None, right_offset)))
+                    | (Some((None, right_offset)), Some((left_path, left_offset))) => {
+                        Some((left_path, left_offset.checked_add(right_offset)?))
+                    }
+                    _ => Some((Some(self.get_path_holding(value)?), 0)),
+                }
+            }
+            Expression::Sub { left, right } => {
+                let left_sum = self.get_as_octagon_sum(left);
+                let right_sum = self.get_as_octagon_sum(right);
+                match (left_sum, right_sum) {
+                    (Some((left_path, left_offset)), Some((None, right_offset))) => {
+                        Some((left_path, left_offset.checked_sub(right_offset)?))
+                    }
+                    _ => Some((Some(self.get_path_holding(value)?), 0)),
+                }
+            }
+            _ => Some((Some(self.get_path_holding(value)?), 0)),
+        }
+    }
+
+    /// Returns a path whose current value is the given integer value, if there is one.
+    /// An unknown variable is the current value of its path if the path has not been updated.
+    fn get_path_holding(&self, value: &AbstractDomain) -> Option<Path> {
+        match &value.expression {
+            Expression::Variable { path, var_type } if var_type.is_integer() => {
+                if !self.value_map.contains_key(path) {
+                    return Some((**path).clone());
+                }
+            }
+            Expression::Widen { path, .. } => {
+                if let Some(val) = self.value_map.get(path) {
+                    if val.domain == *value {
+                        return Some((**path).clone());
+                    }
+                }
+            }
+            Expression::AbstractHeapAddress(..)
+            | Expression::Bottom
+            | Expression::CompileTimeConstant(..)
+            | Expression::Reference(..)
+            | Expression::Top => {
+                return None;
+            }
+            _ => {}
+        }
+        let interval = value.get_as_interval();
+        if interval.is_bottom() || interval.is_top() {
+            // Not an integer value that we know something about.
+            return None;
+        }
+        self.value_map
+            .iter()
+            .find(|(_, val)| val.domain == *value)
+            .map(|(path, _)| path.clone())
+    }
+
     /// Returns an environment with a path for every entry in self and other and an associated
-    /// value that is the join of self.value_at(path) and other.value_at(path)
-    pub fn join(&self, other: &Environment, join_condition: &AbstractValue) -> Environment {
-        self.join_or_widen(other, join_condition, |x, y, c| x.join(y, c))
+    /// value that is the join of self.value_at(path) and other.value_at(path).
+    /// Joined values whose expressions are deeper than max_expression_depth or have more than
+    /// max_expression_node_count nodes are replaced with unknown values that keep their intervals
+    /// and that belong to the given block, which is where the environments are joined.
+    pub fn join(
+        &self,
+        other: &Environment,
+        join_condition: &AbstractValue,
+        block: BasicBlock,
+        max_expression_depth: usize,
+        max_expression_node_count: usize,
+    ) -> Environment {
+        let octagon = self.octagon.join(&other.octagon);
+        self.join_or_widen(other, join_condition, octagon, |x, y, c, p| {
+            x.join(y, c).limit_size(
+                p,
+                block.index(),
+                max_expression_depth,
+                max_expression_node_count,
+            )
+        })
     }
 
     /// Returns an environment with a path for every entry in self and other and an associated
-    /// value that is the widen of self.value_at(path) and other.value_at(path)
-    pub fn widen(&self, other: &Environment, join_condition: &AbstractValue) -> Environment {
-        self.join_or_widen(other, join_condition, |x, y, c| x.widen(y, c))
+    /// value that is the widen of self.value_at(path) and other.value_at(path).
+    /// The bounds of widened integer values are taken from the given thresholds and the widened
+    /// values belong to the given block, which is where the environments are joined.
+    pub fn widen(
+        &self,
+        other: &Environment,
+        join_condition: &AbstractValue,
+        block: BasicBlock,
+        thresholds: &BTreeSet<i128>,
+    ) -> Environment {
+        let other_condition = &other.entry_condition;
+        let octagon = self.octagon.widen(&other.octagon);
+        self.join_or_widen(other, join_condition, octagon, |x, y, c, p| {
+            x.widen(y, c, other_condition, p, block.index(), thresholds)
+        })
+    }
+
+    /// Returns an environment with a path for every entry in self and other and an associated
+    /// value that is the narrowing of self.value_at(path) with other.value_at(path).
+    /// Paths that have no value in other keep their value in self, and vice versa.
+    /// The octagon of self is refined with the relations of other that it does not have, and
+    /// likewise the variant sets.
+    pub fn narrow(&self, other: &Environment) -> Environment {
+        let mut value_map = other.value_map.clone();
+        for (path, val1) in self.value_map.iter() {
+            let val = match other.value_map.get(path) {
+                Some(val2) => val1.narrow(val2),
+                None => val1.clone(),
+            };
+            value_map = value_map.insert(path.clone(), val);
+        }
+        let mut variants = other.variants.clone();
+        for (path, variants1) in self.variants.iter() {
+            let narrowed_variants = match other.variants.get(path) {
+                Some(variants2) => variants1.intersect(variants2),
+                None => *variants1,
+            };
+            variants = variants.insert(path.clone(), narrowed_variants);
+        }
+        Environment {
+            value_map,
+            entry_condition: abstract_value::TRUE,
+            exit_conditions: HashMap::default(),
+            octagon: self.octagon.narrow(&other.octagon),
+            variants,
+            exit_variants: HashMap::default(),
+        }
     }
 
     /// Returns an environment with a path for every entry in self and other and an associated
     /// value that is the join or widen of self.value_at(path) and other.value_at(path).
-    fn join_or_widen(
+    /// The octagon of the result is the given octagon, which relates the joined values.
+    /// Since variant sets are finite, they are joined even when widening.
+    fn join_or_widen<F>(
         &self,
         other: &Environment,
         join_condition: &AbstractValue,
-        join_or_widen: fn(&AbstractValue, &AbstractValue, &AbstractValue) -> AbstractValue,
-    ) -> Environment {
+        octagon: OctagonDomain,
+        join_or_widen: F,
+    ) -> Environment
+    where
+        F: Fn(&AbstractValue, &AbstractValue, &AbstractValue, &Path) -> AbstractValue,
+    {
         let value_map1 = &self.value_map;
         let value_map2 = &other.value_map;
         let mut value_map: HashTrieMap<Path, AbstractValue> = HashTrieMap::default();
@@ -172,11 +553,12 @@ impl Environment {
             let p = path.clone();
             match value_map2.get(path) {
                 Some(val2) => {
-                    value_map = value_map.insert(p, join_or_widen(&val1, &val2, &join_condition));
+                    let val = join_or_widen(&val1, &val2, &join_condition, path);
+                    value_map = value_map.insert(p, val);
                 }
                 None => {
                     assert!(!val1.is_bottom());
-                    let val = join_or_widen(&val1, &abstract_value::BOTTOM, &join_condition);
+                    let val = join_or_widen(&val1, &abstract_value::BOTTOM, &join_condition, path);
                     if !val.is_bottom() {
                         value_map = value_map.insert(p, val);
                     }
@@ -187,20 +569,31 @@ impl Environment {
             if !value_map1.contains_key(path) {
                 assert!(!val2.is_bottom());
                 let p = path.clone();
-                let val = join_or_widen(&abstract_value::BOTTOM, &val2, &join_condition);
+                let val = join_or_widen(&abstract_value::BOTTOM, &val2, &join_condition, path);
                 if !val.is_bottom() {
                     value_map = value_map.insert(p, val);
                 }
             }
         }
+        let mut variants: HashTrieMap<Path, VariantSetDomain> = HashTrieMap::default();
+        for (path, variants1) in self.variants.iter() {
+            if let Some(variants2) = other.variants.get(path) {
+                variants = variants.insert(path.clone(), variants1.join(variants2));
+            }
+        }
         Environment {
             value_map,
             entry_condition: abstract_value::TRUE,
             exit_conditions: HashMap::default(),
+            octagon,
+            variants,
+            exit_variants: HashMap::default(),
         }
     }
 
     /// Returns true if for every path, self.value_at(path).subset(other.value_at(path))
+    /// and if every relation in the octagon of other is implied by the octagon of self,
+    /// and if every variant set of self is a subset of the corresponding one in other.
     pub fn subset(&self, other: &Environment) -> bool {
         let value_map1 = &self.value_map;
         let value_map2 = &other.value_map;
@@ -223,6 +616,11 @@ impl Environment {
                 }
             }
         }
-        true
+        let variants_are_subsets = other.variants.iter().all(|(path, variants2)| {
+            self.variants
+                .get(path)
+                .map_or(false, |variants1| variants1.subset(variants2))
+        });
+        variants_are_subsets && self.octagon.subset(&other.octagon)
     }
 }
@@ -5,7 +5,9 @@
 
 use crate::abstract_domains::AbstractDomain;
 use crate::abstract_value::Path;
+use crate::congruence_domain::CongruenceDomain;
 use crate::constant_domain::ConstantDomain;
+use crate::interval_domain::IntervalDomain;
 
 /// Closely based on the expressions found in MIR.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -73,6 +75,14 @@ pub enum Expression {
         right: Box<AbstractDomain>,
     },
 
+    /// An expression that is the operand converted to target_type by an "as" conversion.
+    Cast {
+        // The value of the operand.
+        operand: Box<AbstractDomain>,
+        // The type that the operand is converted to.
+        target_type: ExpressionType,
+    },
+
     /// An expression that is a compile time constant value, such as a numeric literal or a function.
     CompileTimeConstant(ConstantDomain),
 
@@ -199,6 +209,8 @@ pub enum Expression {
         left: Box<AbstractDomain>,
         // The value of the right operand.
         right: Box<AbstractDomain>,
+        // The type of the left argument and the result
+        result_type: ExpressionType,
     },
 
     /// An expression that is false if left shifted left by right bits would shift way all bits. <<
@@ -231,6 +243,16 @@ pub enum Expression {
         result_type: ExpressionType,
     },
 
+    /// An expression that is the string slice &operand[start..end].
+    StrSlice {
+        // The value of the string slice that is sliced.
+        operand: Box<AbstractDomain>,
+        // The byte offset of the first byte of the slice.
+        start: Box<AbstractDomain>,
+        // The byte offset of the first byte after the slice.
+        end: Box<AbstractDomain>,
+    },
+
     /// An expression that is the right subtracted from left. -
     Sub {
         // The value of the left operand.
@@ -258,6 +280,69 @@ pub enum Expression {
         path: Box<Path>,
         var_type: ExpressionType,
     },
+
+    /// The unknown integer value of a place in memory that was widened while computing the
+    /// fixed point of a loop, or whose expression became too large. All that is known is that
+    /// the value lies within the interval and the congruence. The path and the block keep the
+    /// widened values of different places and of different join points distinct.
+    Widen {
+        path: Box<Path>,
+        /// The index of the basic block at whose entry the values of the path were joined.
+        block: usize,
+        interval: IntervalDomain,
+        congruence: CongruenceDomain,
+    },
+}
+
+impl Expression {
+    /// Returns the operands of this expression, in the order in which they are declared.
+    pub fn operands(&self) -> Vec<&AbstractDomain> {
+        use self::Expression::*;
+        match self {
+            Top
+            | Bottom
+            | AbstractHeapAddress(..)
+            | CompileTimeConstant(..)
+            | Reference(..)
+            | Variable { .. }
+            | Widen { .. } => vec![],
+            Cast { operand, .. } | Neg { operand } | Not { operand } => vec![operand],
+            Add { left, right }
+            | AddOverflows { left, right, .. }
+            | And { left, right }
+            | BitAnd { left, right }
+            | BitOr { left, right }
+            | BitXor { left, right }
+            | Div { left, right }
+            | Equals { left, right }
+            | GreaterOrEqual { left, right }
+            | GreaterThan { left, right }
+            | LessOrEqual { left, right }
+            | LessThan { left, right }
+            | Mul { left, right }
+            | MulOverflows { left, right, .. }
+            | Ne { left, right }
+            | Or { left, right }
+            | Offset { left, right }
+            | Rem { left, right }
+            | Shl { left, right, .. }
+            | ShlOverflows { left, right, .. }
+            | Shr { left, right, .. }
+            | ShrOverflows { left, right, .. }
+            | Sub { left, right }
+            | SubOverflows { left, right, .. } => vec![left, right],
+            ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => vec![condition, consequent, alternate],
+            StrSlice {
+                operand,
+                start,
+                end,
+            } => vec![operand, start, end],
+        }
+    }
 }
 
 /// The type of a place in memory, as understood by MIR.
@@ -286,6 +371,15 @@ pub enum ExpressionType {
 }
 
 impl ExpressionType {
+    /// Returns true if this type is one of the integer types.
+    pub fn is_integer(&self) -> bool {
+        use self::ExpressionType::*;
+        match self {
+            I8 | I16 | I32 | I64 | I128 | Isize | U8 | U16 | U32 | U64 | U128 | Usize => true,
+            _ => false,
+        }
+    }
+
     /// Returns true if this type is one of the signed integer types.
     pub fn is_signed_integer(&self) -> bool {
         use self::ExpressionType::*;
@@ -319,4 +413,40 @@ impl ExpressionType {
             Usize => 64,
         }
     }
+
+    /// Returns the largest value that can be represented by this type, if it is an integer type.
+    /// For other types the result is just 0.
+    pub fn max_value(&self) -> u128 {
+        use self::ExpressionType::*;
+        match self {
+            I8 => std::i8::MAX as u128,
+            I16 => std::i16::MAX as u128,
+            I32 => std::i32::MAX as u128,
+            I64 => std::i64::MAX as u128,
+            I128 => std::i128::MAX as u128,
+            Isize => std::isize::MAX as u128,
+            U8 => std::u8::MAX as u128,
+            U16 => std::u16::MAX as u128,
+            U32 => std::u32::MAX as u128,
+            U64 => std::u64::MAX as u128,
+            U128 => std::u128::MAX,
+            Usize => std::usize::MAX as u128,
+            _ => 0,
+        }
+    }
+
+    /// Returns the smallest value that can be represented by this type, if it is an integer type.
+    /// For other types the result is just 0.
+    pub fn min_value(&self) -> i128 {
+        use self::ExpressionType::*;
+        match self {
+            I8 => i128::from(std::i8::MIN),
+            I16 => i128::from(std::i16::MIN),
+            I32 => i128::from(std::i32::MIN),
+            I64 => i128::from(std::i64::MIN),
+            I128 => std::i128::MIN,
+            Isize => std::isize::MIN as i128,
+            _ => 0,
+        }
+    }
 }
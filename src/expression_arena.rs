@@ -0,0 +1,193 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::abstract_domains::AbstractDomain;
+use crate::bdd;
+use crate::expression::Expression;
+use crate::statistics;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+// Expressions are hash-consed: every expression node that is part of an abstract domain element
+// is interned in an arena, so that there is only one node for all structurally equal expressions.
+// Since the operands of an interned node are themselves interned, two interned nodes are equal
+// if and only if they are the same node, so that abstract domain elements can be cloned, compared
+// and hashed in constant time, and sub expressions are shared rather than copied.
+//
+// The nodes of the arena live until the analysis of the current crate ends, which is fine because
+// an expression that has been constructed once tends to be constructed again and again by the
+// fixed point computations that analyze a function, its callers and its loops. The analysis of a
+// crate enters a scope (see enter_scope) and the nodes are freed when the scope is dropped.
+// Although intern hands out &'static references, they are only valid until then. This is why
+// both intern and enter_scope are unsafe: the code that enters a scope promises that nothing that
+// refers to an interned node outlives the scope, and the thread local caches that hold on to
+// nodes, such as the diagrams of the bdd module, are cleared before the nodes are freed.
+// Nodes that are interned outside of a scope are never freed.
+
+/// The depth of an expression tree and the number of nodes in it, where shared sub trees are
+/// counted as many times as they occur.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ExpressionSize {
+    pub depth: usize,
+    pub node_count: usize,
+}
+
+/// The interned nodes, along with the sizes of the trees that they are the roots of.
+#[derive(Default)]
+struct Arena {
+    nodes: HashSet<&'static Expression>,
+    sizes: HashMap<*const Expression, ExpressionSize>,
+    /// The largest depth and the largest node count of the interned nodes.
+    largest_size: ExpressionSize,
+    /// True if a scope has been entered and not yet dropped.
+    in_scope: bool,
+}
+
+impl Arena {
+    /// Frees all of the interned nodes.
+    fn free_nodes(&mut self) {
+        self.sizes.clear();
+        self.largest_size = ExpressionSize::default();
+        for node in self.nodes.drain() {
+            // Every node has been leaked from a box by intern and is only freed here, after
+            // it has been removed from the arena.
+            unsafe { drop(Box::from_raw(node as *const Expression as *mut Expression)) };
+        }
+    }
+
+    /// Returns the size of the given expression, which must be interned unless it has no operands.
+    fn get_size(&self, expression: &Expression) -> ExpressionSize {
+        match self.sizes.get(&(expression as *const Expression)) {
+            Some(size) => *size,
+            None => self.get_size_of_new_node(expression),
+        }
+    }
+
+    /// Returns the size of the given expression, computed from the sizes of its operands.
+    fn get_size_of_new_node(&self, expression: &Expression) -> ExpressionSize {
+        let mut size = ExpressionSize {
+            depth: 1,
+            node_count: 1,
+        };
+        for operand in expression.operands() {
+            let operand_size = self.get_size(operand.expression);
+            size.depth = size.depth.max(operand_size.depth + 1);
+            size.node_count = size.node_count.saturating_add(operand_size.node_count);
+        }
+        size
+    }
+}
+
+thread_local! {
+    /// The nodes that have been interned by the current thread.
+    static ARENA: RefCell<Arena> = RefCell::new(Arena::default());
+}
+
+/// Returns the interned node that is structurally equal to the given expression, adding the
+/// expression to the arena if there is no such node yet. The operands of the expression must
+/// already be interned.
+///
+/// # Safety
+///
+/// The returned node is freed when the current scope is dropped, despite its lifetime. The caller
+/// must not use it, or store it where it can be used, after that. Nodes are only stored in
+/// abstract domain elements, whose lifetime enter_scope places under the same obligation, and in
+/// the thread local caches that are cleared when the scope is dropped.
+pub(crate) unsafe fn intern(expression: Expression) -> &'static Expression {
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        if let Some(node) = arena.nodes.get(&expression) {
+            return *node;
+        }
+        let size = arena.get_size_of_new_node(&expression);
+        let node: &'static Expression = Box::leak(Box::new(expression));
+        arena.nodes.insert(node);
+        arena.sizes.insert(node, size);
+        arena.largest_size.depth = arena.largest_size.depth.max(size.depth);
+        arena.largest_size.node_count = arena.largest_size.node_count.max(size.node_count);
+        node
+    })
+}
+
+/// Frees the nodes interned by the current thread when it is dropped.
+pub struct Scope {
+    // Scopes are not Send, since the arena is local to a thread.
+    _arena: std::marker::PhantomData<*const Arena>,
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        // The caches of the other modules refer to the nodes, so they have to go first.
+        bdd::reset();
+        AbstractDomain::reset_path_condition_bounds();
+        ARENA.with(|arena| {
+            let mut arena = arena.borrow_mut();
+            arena.free_nodes();
+            arena.in_scope = false;
+        })
+    }
+}
+
+/// Enters the scope of the analysis of a crate. The expressions interned by the current thread
+/// are freed when the returned scope is dropped. Scopes do not nest.
+///
+/// # Safety
+///
+/// The caller must make sure that no abstract domain element, and no other value that refers to
+/// an interned expression, outlives the returned scope. This includes the expressions that were
+/// interned before the scope was entered.
+pub unsafe fn enter_scope() -> Scope {
+    ARENA.with(|arena| {
+        let mut arena = arena.borrow_mut();
+        assert!(
+            !arena.in_scope,
+            "the analysis of a crate is already in progress"
+        );
+        arena.in_scope = true;
+    });
+    statistics::reset();
+    Scope {
+        _arena: std::marker::PhantomData,
+    }
+}
+
+/// Returns the size of the tree that the given expression is the root of. This takes constant
+/// time, since the sizes of the interned nodes are computed when they are interned.
+pub fn get_size(expression: &Expression) -> ExpressionSize {
+    ARENA.with(|arena| arena.borrow().get_size(expression))
+}
+
+/// Returns the largest depth and the largest node count of the nodes interned by the current
+/// thread.
+pub fn get_largest_size() -> ExpressionSize {
+    ARENA.with(|arena| arena.borrow().largest_size)
+}
+
+/// Returns the number of nodes that have been interned by the current thread.
+pub fn number_of_nodes() -> usize {
+    ARENA.with(|arena| arena.borrow().nodes.len())
+}
+
+/// Serializes an interned node as the expression tree that it is the root of, so that the
+/// persistent summary store does not depend on the addresses of the nodes.
+pub fn serialize<S>(node: &&'static Expression, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    node.serialize(serializer)
+}
+
+/// Deserializes an expression tree and interns it. Since the operands of the expression are
+/// deserialized (and thus interned) first, the whole tree ends up in the arena.
+pub fn deserialize<'de, D>(deserializer: D) -> Result<&'static Expression, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // The deserialized node is stored in an abstract domain element (see the serde attribute of
+    // AbstractDomain::expression), so it does not outlive the scope.
+    Expression::deserialize(deserializer).map(|expression| unsafe { intern(expression) })
+}
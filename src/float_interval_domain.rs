@@ -0,0 +1,496 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+#![allow(clippy::float_cmp)]
+
+use crate::expression::ExpressionType;
+use crate::interval_domain::IntervalDomain;
+
+use std::f64::{INFINITY, NEG_INFINITY};
+
+/// An element of the FloatInterval domain is a range of floating point values, denoted by a
+/// lower bound and an upper bound, which may be infinite, along with a flag that tells if the
+/// value could also be NaN. An empty range with the flag set denotes NaN itself.
+/// Values of type f32 are represented as f64 values, but bounds computed for them are rounded
+/// to single precision, just like the results of the operations on them.
+/// FloatInterval domain elements are constructed on demand from AbstractDomain expressions.
+/// They are most useful for checking casts to integers, which saturate, and for comparisons that
+/// are always false because an operand is NaN.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FloatIntervalDomain {
+    lower_bound: f64,
+    upper_bound: f64,
+    may_be_nan: bool,
+    is_single_precision: bool,
+}
+
+// An empty range that is not NaN does not correspond to any floating point value.
+pub const BOTTOM: FloatIntervalDomain = FloatIntervalDomain {
+    lower_bound: INFINITY,
+    upper_bound: NEG_INFINITY,
+    may_be_nan: false,
+    is_single_precision: false,
+};
+
+pub const NAN: FloatIntervalDomain = FloatIntervalDomain {
+    lower_bound: INFINITY,
+    upper_bound: NEG_INFINITY,
+    may_be_nan: true,
+    is_single_precision: false,
+};
+
+pub const TOP: FloatIntervalDomain = FloatIntervalDomain {
+    lower_bound: NEG_INFINITY,
+    upper_bound: INFINITY,
+    may_be_nan: true,
+    is_single_precision: false,
+};
+
+impl From<f32> for FloatIntervalDomain {
+    fn from(f: f32) -> FloatIntervalDomain {
+        FloatIntervalDomain {
+            is_single_precision: true,
+            ..f64::from(f).into()
+        }
+    }
+}
+
+impl From<f64> for FloatIntervalDomain {
+    fn from(f: f64) -> FloatIntervalDomain {
+        if f.is_nan() {
+            NAN.clone()
+        } else {
+            FloatIntervalDomain::new(f, f, false, false)
+        }
+    }
+}
+
+impl FloatIntervalDomain {
+    // Returns [lower_bound...upper_bound], which includes NaN if may_be_nan. A bound that is NaN
+    // is replaced with an infinity, which gives up on the range but not on the NaN flag.
+    // Since rounding is monotonic, the bounds of single precision values can just be rounded.
+    fn new(
+        lower_bound: f64,
+        upper_bound: f64,
+        may_be_nan: bool,
+        is_single_precision: bool,
+    ) -> Self {
+        let lower_bound = if lower_bound.is_nan() {
+            NEG_INFINITY
+        } else {
+            lower_bound
+        };
+        let upper_bound = if upper_bound.is_nan() {
+            INFINITY
+        } else {
+            upper_bound
+        };
+        if is_single_precision && lower_bound <= upper_bound {
+            FloatIntervalDomain {
+                lower_bound: f64::from(lower_bound as f32),
+                upper_bound: f64::from(upper_bound as f32),
+                may_be_nan,
+                is_single_precision,
+            }
+        } else {
+            FloatIntervalDomain {
+                lower_bound,
+                upper_bound,
+                may_be_nan,
+                is_single_precision,
+            }
+        }
+    }
+
+    // Returns the floating point values that the integers in the interval convert to.
+    pub fn from_interval(interval: &IntervalDomain, target_type: &ExpressionType) -> Self {
+        if interval.is_bottom() || Self::for_type(target_type).is_bottom() {
+            return Self::for_type(target_type);
+        }
+        let lower_bound = interval.lower_bound().map_or(NEG_INFINITY, |b| b as f64);
+        let upper_bound = interval.upper_bound().map_or(INFINITY, |b| b as f64);
+        Self::new(
+            lower_bound,
+            upper_bound,
+            false,
+            *target_type == ExpressionType::F32,
+        )
+    }
+
+    // Returns all values of target_type, or bottom if target_type is not a floating point type.
+    pub fn for_type(target_type: &ExpressionType) -> Self {
+        match target_type {
+            ExpressionType::F32 => Self::new(NEG_INFINITY, INFINITY, true, true),
+            ExpressionType::F64 => TOP.clone(),
+            _ => BOTTOM.clone(),
+        }
+    }
+
+    // Returns the result of applying an operation that is monotonic in both operands to self
+    // and other, given the values of the operation at the corners of the ranges. The result
+    // is NaN if an operand is NaN or if may_be_nan.
+    fn from_corners(&self, other: &Self, corners: &[f64], may_be_nan: bool) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let may_be_nan = may_be_nan || self.may_be_nan || other.may_be_nan;
+        let is_single_precision = self.is_single_precision || other.is_single_precision;
+        if self.is_nan() || other.is_nan() {
+            return Self::new(INFINITY, NEG_INFINITY, true, is_single_precision);
+        }
+        // A NaN corner, such as 0 * infinity, is approached by values on either side of 0,
+        // which the other corners already account for.
+        let corners = corners.iter().map(|c| if c.is_nan() { 0.0 } else { *c });
+        let lower_bound = corners.clone().fold(INFINITY, f64::min);
+        let upper_bound = corners.fold(NEG_INFINITY, f64::max);
+        Self::new(lower_bound, upper_bound, may_be_nan, is_single_precision)
+    }
+
+    // [x...y] + [a...b] = [x+a...y+b], which is NaN if one operand could be +infinity while the
+    // other could be -infinity.
+    pub fn add(&self, other: &Self) -> Self {
+        let may_be_nan = (self.contains(INFINITY) && other.contains(NEG_INFINITY))
+            || (self.contains(NEG_INFINITY) && other.contains(INFINITY));
+        self.from_corners(
+            other,
+            &[
+                self.lower_bound + other.lower_bound,
+                self.upper_bound + other.upper_bound,
+            ],
+            may_be_nan,
+        )
+    }
+
+    // Returns the integer interval that a cast of self to target_type results in. Casts saturate,
+    // so values that are out of range become the smallest or largest value of target_type,
+    // while NaN becomes zero.
+    pub fn as_integer_interval(&self, target_type: &ExpressionType) -> IntervalDomain {
+        if self.is_bottom() || !target_type.is_integer() {
+            return IntervalDomain::for_type(target_type);
+        }
+        let zero = IntervalDomain::from(0i128);
+        if self.is_nan() {
+            return zero;
+        }
+        let result =
+            saturate(self.lower_bound, target_type).join(&saturate(self.upper_bound, target_type));
+        if self.may_be_nan {
+            result.join(&zero)
+        } else {
+            result
+        }
+    }
+
+    // self as target_type, where target_type is a floating point type. Conversions to f32 round
+    // the bounds.
+    pub fn cast(&self, target_type: &ExpressionType) -> Self {
+        if self.is_bottom() {
+            return Self::for_type(target_type);
+        }
+        match target_type {
+            ExpressionType::F32 | ExpressionType::F64 => Self::new(
+                self.lower_bound,
+                self.upper_bound,
+                self.may_be_nan,
+                *target_type == ExpressionType::F32,
+            ),
+            _ => BOTTOM.clone(),
+        }
+    }
+
+    // Returns true if the range of self includes value.
+    fn contains(&self, value: f64) -> bool {
+        self.lower_bound <= value && value <= self.upper_bound
+    }
+
+    // [x...y] / [a...b] = [min(corners)...max(corners)], if 0 is not in [a...b]. Otherwise the
+    // result could be any value. It is NaN for 0/0 and infinity/infinity.
+    pub fn div(&self, other: &Self) -> Self {
+        let may_be_nan = (self.contains(0.0) && other.contains(0.0))
+            || (self.is_unbounded() && other.is_unbounded());
+        if other.contains(0.0) {
+            return self.from_corners(other, &[NEG_INFINITY, INFINITY], may_be_nan);
+        }
+        self.from_corners(
+            other,
+            &[
+                self.lower_bound / other.lower_bound,
+                self.lower_bound / other.upper_bound,
+                self.upper_bound / other.lower_bound,
+                self.upper_bound / other.upper_bound,
+            ],
+            may_be_nan,
+        )
+    }
+
+    // Returns true if all values of self are equal to all values of other, false if no value of
+    // self is equal to a value of other, and None otherwise. NaN is not equal to anything.
+    pub fn equals(&self, other: &Self) -> Option<bool> {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        if self.is_nan()
+            || other.is_nan()
+            || self.upper_bound < other.lower_bound
+            || other.upper_bound < self.lower_bound
+        {
+            return Some(false);
+        }
+        if !self.may_be_nan
+            && !other.may_be_nan
+            && self.lower_bound == self.upper_bound
+            && other.lower_bound == other.upper_bound
+        {
+            return Some(true);
+        }
+        None
+    }
+
+    // Returns Some(true) if every value of self is a value of target_type after truncation,
+    // Some(false) if no value of self is, and None otherwise. NaN is not a value of any integer
+    // type.
+    pub fn fits_in(&self, target_type: &ExpressionType) -> Option<bool> {
+        if self.is_bottom() || !target_type.is_integer() {
+            return None;
+        }
+        // The smallest value of an integer type is 0 or a power of 2 and the largest value is one
+        // less than a power of 2, which max_value() as f64 is rounded up to if needed, so both
+        // min and max_plus_one are exact.
+        let min = target_type.min_value() as f64;
+        let max_plus_one = target_type.max_value() as f64 + 1.0;
+        if self.is_nan() || self.upper_bound.trunc() < min || self.lower_bound >= max_plus_one {
+            Some(false)
+        } else if !self.may_be_nan
+            && self.lower_bound.trunc() >= min
+            && self.upper_bound < max_plus_one
+        {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    // self >= other is other <= self
+    pub fn greater_or_equal(&self, other: &Self) -> Option<bool> {
+        other.less_equal(self)
+    }
+
+    // self > other is other < self
+    pub fn greater_than(&self, other: &Self) -> Option<bool> {
+        other.less_than(self)
+    }
+
+    // The expression that corresponds to this element is not known to result in a floating
+    // point value.
+    pub fn is_bottom(&self) -> bool {
+        !self.may_be_nan && self.upper_bound < self.lower_bound
+    }
+
+    // True if the value is always NaN.
+    pub fn is_nan(&self) -> bool {
+        self.may_be_nan && self.upper_bound < self.lower_bound
+    }
+
+    // All floating point values belong to this element, so we know nothing.
+    pub fn is_top(&self) -> bool {
+        self.may_be_nan && self.lower_bound == NEG_INFINITY && self.upper_bound == INFINITY
+    }
+
+    // True if the value could be an infinity.
+    fn is_unbounded(&self) -> bool {
+        self.contains(NEG_INFINITY) || self.contains(INFINITY)
+    }
+
+    // [x...y] join [a...b] = [min(x,a)...max(y,b)]
+    pub fn join(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        Self::new(
+            self.lower_bound.min(other.lower_bound),
+            self.upper_bound.max(other.upper_bound),
+            self.may_be_nan || other.may_be_nan,
+            self.is_single_precision || other.is_single_precision,
+        )
+    }
+
+    // [x...y] <= [a...b] = y <= a
+    // !([x...y] <= [a...b]) = [a...b] < [x...y] = a < x, or either is NaN
+    pub fn less_equal(&self, other: &Self) -> Option<bool> {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        if self.is_nan() || other.is_nan() || other.upper_bound < self.lower_bound {
+            return Some(false);
+        }
+        if !self.may_be_nan && !other.may_be_nan && self.upper_bound <= other.lower_bound {
+            return Some(true);
+        }
+        None
+    }
+
+    // [x...y] < [a...b] = y < a
+    // !([x...y] < [a...b]) = [a...b] <= [x...y] = b <= x, or either is NaN
+    pub fn less_than(&self, other: &Self) -> Option<bool> {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        if self.is_nan() || other.is_nan() || other.upper_bound <= self.lower_bound {
+            return Some(false);
+        }
+        if !self.may_be_nan && !other.may_be_nan && self.upper_bound < other.lower_bound {
+            return Some(true);
+        }
+        None
+    }
+
+    // Returns the values that are in both self and other, unless there are none, in which case
+    // other must be inconsistent with self and self is returned.
+    pub fn meet(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return self.clone();
+        }
+        let result = Self::new(
+            self.lower_bound.max(other.lower_bound),
+            self.upper_bound.min(other.upper_bound),
+            self.may_be_nan && other.may_be_nan,
+            self.is_single_precision,
+        );
+        if result.is_bottom() {
+            self.clone()
+        } else {
+            result
+        }
+    }
+
+    // [x...y] * [a...b] = [min(corners)...max(corners)], where the corners are x*a, x*b, y*a and
+    // y*b. It is NaN for 0 * infinity.
+    pub fn mul(&self, other: &Self) -> Self {
+        let may_be_nan = (self.contains(0.0) && other.is_unbounded())
+            || (self.is_unbounded() && other.contains(0.0));
+        self.from_corners(
+            other,
+            &[
+                self.lower_bound * other.lower_bound,
+                self.lower_bound * other.upper_bound,
+                self.upper_bound * other.lower_bound,
+                self.upper_bound * other.upper_bound,
+            ],
+            may_be_nan,
+        )
+    }
+
+    // -[x...y] = [-y...-x]
+    pub fn neg(&self) -> Self {
+        if self.is_bottom() {
+            return BOTTOM.clone();
+        }
+        Self::new(
+            -self.upper_bound,
+            -self.lower_bound,
+            self.may_be_nan,
+            self.is_single_precision,
+        )
+    }
+
+    // Returns the values of self that can be less than a value of other, or equal to it if
+    // !is_strict, if the comparison is_true, which excludes NaN. Otherwise returns the values of
+    // self that can be greater than or equal to a value of other, or greater if !is_strict, along
+    // with NaN. If other could be NaN, the comparison could be false for any value of self.
+    pub fn refine_less_than(&self, other: &Self, is_strict: bool, is_true: bool) -> Self {
+        if is_true {
+            let upper_bound = if is_strict {
+                self.next_below(other.upper_bound)
+            } else {
+                other.upper_bound
+            };
+            Self::new(
+                self.lower_bound,
+                self.upper_bound.min(upper_bound),
+                false,
+                self.is_single_precision,
+            )
+        } else if other.may_be_nan {
+            self.clone()
+        } else {
+            let lower_bound = if is_strict {
+                other.lower_bound
+            } else {
+                -self.next_below(-other.lower_bound)
+            };
+            Self::new(
+                self.lower_bound.max(lower_bound),
+                self.upper_bound,
+                self.may_be_nan,
+                self.is_single_precision,
+            )
+        }
+    }
+
+    // Returns the values of self that can be greater than a value of other, or equal to it if
+    // !is_strict, if the comparison is_true, like refine_less_than.
+    pub fn refine_greater_than(&self, other: &Self, is_strict: bool, is_true: bool) -> Self {
+        self.neg()
+            .refine_less_than(&other.neg(), is_strict, is_true)
+            .neg()
+    }
+
+    // Returns the largest value with the precision of self that is less than value.
+    fn next_below(&self, value: f64) -> f64 {
+        if value.is_nan() || value == NEG_INFINITY {
+            value
+        } else if self.is_single_precision {
+            let value = value as f32;
+            f64::from(if value == 0.0 {
+                -f32::from_bits(1)
+            } else if value > 0.0 {
+                f32::from_bits(value.to_bits() - 1)
+            } else {
+                f32::from_bits(value.to_bits() + 1)
+            })
+        } else if value == 0.0 {
+            -f64::from_bits(1)
+        } else if value > 0.0 {
+            f64::from_bits(value.to_bits() - 1)
+        } else {
+            f64::from_bits(value.to_bits() + 1)
+        }
+    }
+
+    // [x...y] % [a...b] has the sign of the dividend and a magnitude that is less than that of
+    // both operands. It is NaN if the divisor could be 0 or the dividend could be infinite.
+    pub fn rem(&self, other: &Self) -> Self {
+        let may_be_nan = other.contains(0.0) || self.is_unbounded();
+        let divisor_magnitude = other.lower_bound.abs().max(other.upper_bound.abs());
+        self.from_corners(
+            other,
+            &[
+                self.lower_bound.max(-divisor_magnitude).min(0.0),
+                self.upper_bound.min(divisor_magnitude).max(0.0),
+            ],
+            may_be_nan,
+        )
+    }
+
+    // [x...y] - [a...b] = [x-b...y-a]
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+}
+
+// Returns the singleton interval with the value of target_type that value saturates to.
+fn saturate(value: f64, target_type: &ExpressionType) -> IntervalDomain {
+    let min = target_type.min_value();
+    let max = target_type.max_value();
+    if value <= min as f64 {
+        min.into()
+    } else if value >= max as f64 {
+        max.into()
+    } else if value < 0.0 {
+        (value as i128).into()
+    } else {
+        (value as u128).into()
+    }
+}
@@ -4,77 +4,253 @@
 // LICENSE file in the root directory of this source tree.
 //
 
-use crate::expression::ExpressionType::{self, *};
+use crate::expression::ExpressionType;
 
+use self::Bound::*;
 use std::cmp;
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
 
-/// An element of the Interval domain is a range of i128 numbers denoted by a lower bound and
-/// upper bound. A lower bound of std::i128::MIN denotes -infinity and an upper bound of
-/// std::i128::MAX denotes +infinity.
+/// An element of the Interval domain is a range of integers denoted by a lower bound and
+/// upper bound. The bounds are exact for all values of all integer types, including values of
+/// type u128 that do not fit into an i128. A missing bound is denoted by -infinity or +infinity.
+/// An interval can also wrap around the end of the range of an integer type, in which case it
+/// contains the values of the type from its lower bound up to the largest value of the type,
+/// along with the values from the smallest value of the type up to its upper bound. Such
+/// intervals are the result of operations that reduce their results modulo 2^n, such as "as"
+/// casts and the wrapping_* operations that are used in hashing and checksum code.
 /// Interval domain elements are constructed on demand from AbstractDomain expressions.
 /// They are most useful for checking if an array index is within bounds.
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq, Hash, Ord)]
 pub struct IntervalDomain {
-    lower_bound: i128,
-    upper_bound: i128,
+    lower_bound: Bound,
+    upper_bound: Bound,
+    // If not None, the interval wraps around the end of the range of this integer type.
+    // In that case, both bounds are in the range of the type and upper_bound < lower_bound.
+    wrapped_type: Option<ExpressionType>,
 }
 
 pub const BOTTOM: IntervalDomain = IntervalDomain {
-    lower_bound: 1,
-    upper_bound: 0,
+    lower_bound: Small(1),
+    upper_bound: Small(0),
+    wrapped_type: None,
 };
 
 pub const TOP: IntervalDomain = IntervalDomain {
-    lower_bound: std::i128::MIN,
-    upper_bound: std::i128::MAX,
+    lower_bound: NegativeInfinity,
+    upper_bound: PositiveInfinity,
+    wrapped_type: None,
 };
 
 impl From<i128> for IntervalDomain {
     fn from(i: i128) -> IntervalDomain {
-        IntervalDomain {
-            lower_bound: i,
-            upper_bound: i,
-        }
+        IntervalDomain::from_bounds(i.into(), i.into())
     }
 }
 
 impl From<u128> for IntervalDomain {
     fn from(u: u128) -> IntervalDomain {
-        if let Result::Ok(i) = i128::try_from(u) {
-            i.into()
-        } else {
-            IntervalDomain {
-                lower_bound: std::i128::MAX,
-                upper_bound: std::i128::MAX,
-            }
-        }
+        IntervalDomain::from_bounds(u.into(), u.into())
     }
 }
 
 impl IntervalDomain {
+    // Returns the interval [lower_bound...upper_bound].
+    fn from_bounds(lower_bound: Bound, upper_bound: Bound) -> Self {
+        IntervalDomain {
+            lower_bound,
+            upper_bound,
+            wrapped_type: None,
+        }
+    }
+
+    // Returns the smallest interval that contains self and does not wrap around. For a wrapped
+    // interval this is the range of its type.
+    fn hull(&self) -> Self {
+        match &self.wrapped_type {
+            Some(wrapped_type) => Self::for_type(wrapped_type),
+            None => self.clone(),
+        }
+    }
+
     //[x...y] + [a...b] = [x+a...y+b]
     pub fn add(&self, other: &Self) -> Self {
         if self.is_bottom() || other.is_bottom() {
             return BOTTOM.clone();
         }
-        if self.is_top() || other.is_top() {
+        let (left, right) = (self.hull(), other.hull());
+        Self::from_bounds(
+            left.lower_bound.add(right.lower_bound),
+            left.upper_bound.add(right.upper_bound),
+        )
+    }
+
+    // [x...y] & [a...b] = [0...min(y,b)] if x >= 0 and a >= 0.
+    // If only one of the intervals is non negative, the result is between 0 and its upper bound,
+    // since the and operation only clears bits.
+    pub fn bit_and(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let (left, right) = (self.hull(), other.hull());
+        let upper_bound = match (left.lower_bound >= ZERO, right.lower_bound >= ZERO) {
+            (true, true) => cmp::min(left.upper_bound, right.upper_bound),
+            (true, false) => left.upper_bound,
+            (false, true) => right.upper_bound,
+            (false, false) => return TOP.clone(),
+        };
+        Self::from_bounds(ZERO, upper_bound)
+    }
+
+    // [x...y] | [a...b] = [max(x,a)...2^n-1] if x >= 0 and a >= 0, where n is the number of bits
+    // needed to represent max(y,b), since the or operation only sets bits.
+    pub fn bit_or(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.lower_bound < ZERO || right.lower_bound < ZERO {
+            return TOP.clone();
+        }
+        Self::from_bounds(
+            cmp::max(left.lower_bound, right.lower_bound),
+            cmp::max(left.upper_bound, right.upper_bound).all_ones_up_to(),
+        )
+    }
+
+    // [x...y] ^ [a...b] = [0...2^n-1] if x >= 0 and a >= 0, where n is the number of bits
+    // needed to represent max(y,b).
+    pub fn bit_xor(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.lower_bound < ZERO || right.lower_bound < ZERO {
             return TOP.clone();
         }
+        Self::from_bounds(
+            ZERO,
+            cmp::max(left.upper_bound, right.upper_bound).all_ones_up_to(),
+        )
+    }
+
+    // [x...y] as target_type = the values of target_type that are congruent to a value in [x...y]
+    // modulo 2^n, where n is the bit length of target_type. If there are fewer than 2^n values
+    // in [x...y], these form a single interval, which may wrap around the end of the range of
+    // target_type. If self is not known to be an integer, for instance because it is a floating
+    // point value, which saturates, the result could be anywhere in the range of target_type.
+    pub fn cast(&self, target_type: &ExpressionType) -> Self {
+        if self.is_bottom() || !target_type.is_integer() {
+            return Self::for_type(target_type);
+        }
+        if self.is_contained_in(target_type) {
+            return self.clone();
+        }
+        let type_range = Self::for_type(target_type);
+        let value = self.hull();
+        let span = value.upper_bound.sub(value.lower_bound);
+        if span >= type_range.upper_bound.sub(type_range.lower_bound) {
+            // This includes the case where a bound is infinite.
+            return type_range;
+        }
+        let lower_bound = value.lower_bound.wrap_into(target_type);
+        let upper_bound = value.upper_bound.wrap_into(target_type);
         IntervalDomain {
-            lower_bound: self.lower_bound.saturating_add(other.lower_bound),
-            upper_bound: self.upper_bound.saturating_add(other.upper_bound),
+            lower_bound,
+            upper_bound,
+            wrapped_type: if upper_bound < lower_bound {
+                Some(target_type.clone())
+            } else {
+                None
+            },
+        }
+    }
+
+    // [x...y] / [a...b] = [min(corners)...max(corners)], where the corners are x/a, x/b, y/a and
+    // y/b, computed separately for the negative and the positive parts of [a...b], since
+    // division by zero panics.
+    pub fn div(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let (left, right) = (self.hull(), other.hull());
+        let negative_part = if right.lower_bound < ZERO {
+            Some(left.div_bounds(right.lower_bound, cmp::min(right.upper_bound, Small(-1))))
+        } else {
+            None
+        };
+        let positive_part = if right.upper_bound > ZERO {
+            Some(left.div_bounds(cmp::max(right.lower_bound, ONE), right.upper_bound))
+        } else {
+            None
+        };
+        match (negative_part, positive_part) {
+            (Some(negative_part), Some(positive_part)) => negative_part.join(&positive_part),
+            (Some(part), None) | (None, Some(part)) => part,
+            // The divisor is always zero.
+            (None, None) => BOTTOM.clone(),
+        }
+    }
+
+    // [x...y] / [a...b] where a and b have the same sign.
+    // Since truncating division is monotone in each operand as long as the divisor does not
+    // change sign, the bounds of the quotient are quotients of the bounds of the operands.
+    // An infinite dividend results in an infinite quotient and an infinite divisor results in a
+    // quotient of 0, which are also the limits.
+    fn div_bounds(&self, lower_divisor: Bound, upper_divisor: Bound) -> Self {
+        let quotients = [
+            self.lower_bound.div(lower_divisor),
+            self.lower_bound.div(upper_divisor),
+            self.upper_bound.div(lower_divisor),
+            self.upper_bound.div(upper_divisor),
+        ];
+        Self::from_bounds(
+            *quotients.iter().min().unwrap(),
+            *quotients.iter().max().unwrap(),
+        )
+    }
+
+    // [x...y] == [a...b] = x == y == a == b
+    // !([x...y] == [a...b]) = y < a || b < x
+    pub fn equals(&self, other: &Self) -> Option<bool> {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.upper_bound < right.lower_bound || right.upper_bound < left.lower_bound {
+            Some(false)
+        } else if left.lower_bound == left.upper_bound
+            && right.lower_bound == right.upper_bound
+            && left.lower_bound == right.lower_bound
+        {
+            Some(true)
+        } else {
+            None
         }
     }
 
+    // Returns the interval [target_type::MIN...target_type::MAX], or bottom if target_type is
+    // not an integer type.
+    pub fn for_type(target_type: &ExpressionType) -> Self {
+        if !target_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        Self::from_bounds(
+            target_type.min_value().into(),
+            target_type.max_value().into(),
+        )
+    }
+
     // [x...y] >= [a...b] = x >= b
     // !([x...y] >= [a...b]) = [a...b] > [x...y] = a > y
     pub fn greater_or_equal(&self, other: &Self) -> Option<bool> {
-        if self.is_bottom() || self.is_top() || other.is_bottom() || other.is_top() {
-            None
-        } else if self.lower_bound >= other.upper_bound {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.lower_bound >= right.upper_bound {
             Some(true)
-        } else if other.lower_bound > self.upper_bound {
+        } else if right.lower_bound > left.upper_bound {
             Some(false)
         } else {
             None
@@ -84,112 +260,106 @@ impl IntervalDomain {
     // [x...y] > [a...b] = x > b
     // !([x...y] > [a...b]) = [a...b] >= [x...y] = a >= y
     pub fn greater_than(&self, other: &Self) -> Option<bool> {
-        if self.is_bottom() || self.is_top() || other.is_bottom() || other.is_top() {
-            None
-        } else if self.lower_bound > other.upper_bound {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.lower_bound > right.upper_bound {
             Some(true)
-        } else if other.lower_bound >= self.upper_bound {
+        } else if right.lower_bound >= left.upper_bound {
             Some(false)
         } else {
             None
         }
     }
 
+    // [x...y] intersect [a...b] = [max(x,a)...min(y,b)]
+    // Since bottom denotes an interval that is not known, intersecting with bottom just returns
+    // the other interval. If the intervals do not overlap, the result is self, since the empty
+    // interval cannot be represented.
+    pub fn intersect(&self, other: &Self) -> Self {
+        if self.is_bottom() || self.is_top() {
+            return other.clone();
+        }
+        if other.is_bottom() || other.is_top() {
+            return self.clone();
+        }
+        let (left, right) = (self.hull(), other.hull());
+        let result = Self::from_bounds(
+            cmp::max(left.lower_bound, right.lower_bound),
+            cmp::min(left.upper_bound, right.upper_bound),
+        );
+        if result.is_bottom() {
+            self.clone()
+        } else {
+            result
+        }
+    }
+
     // The expression that corresponds to this interval is not known to result in a integer value.
     // This is either because we just don't know, or because the necessary transfer function was
     // not implemented. The expectation is that bottom values will not often be encountered.
     // We don't need this domain to implement transfer functions for all operations that might
     // result in integer values since other domains will be preferred in those cases.
     pub fn is_bottom(&self) -> bool {
-        self.upper_bound < self.lower_bound
+        self.wrapped_type.is_none() && self.upper_bound < self.lower_bound
     }
 
     // Returns true if this interval is known to be contained in the interval [target_type::MIN ... target_type::MAX].
     // A false result just means that we don't know, it never means that we know it does not.
-    // Note that i128::MIN and i128::MAX are reserved to indicate missing (unbounded) lower and upper
-    // bounds, respectively.
     pub fn is_contained_in(&self, target_type: &ExpressionType) -> bool {
-        if self.is_bottom() || self.is_top() {
+        if self.is_bottom() || !target_type.is_integer() {
             return false;
         };
-        match target_type {
-            I8 => {
-                self.lower_bound >= i128::from(std::i8::MIN)
-                    && self.upper_bound <= i128::from(std::i8::MAX)
-            }
-            I16 => {
-                self.lower_bound >= i128::from(std::i16::MIN)
-                    && self.upper_bound <= i128::from(std::i16::MAX)
-            }
-            I32 => {
-                self.lower_bound >= i128::from(std::i32::MIN)
-                    && self.upper_bound <= i128::from(std::i32::MAX)
-            }
-            I64 => {
-                self.lower_bound >= i128::from(std::i64::MIN)
-                    && self.upper_bound <= i128::from(std::i64::MAX)
-            }
-            I128 => self.lower_bound > std::i128::MIN && self.upper_bound < std::i128::MAX,
-            Isize => {
-                self.lower_bound >= (std::isize::MIN as i128)
-                    && self.upper_bound <= (std::isize::MAX as i128)
-            }
-            U8 => self.lower_bound >= 0 && self.upper_bound <= i128::from(std::u8::MAX),
-            U16 => self.lower_bound >= 0 && self.upper_bound <= i128::from(std::u16::MAX),
-            U32 => self.lower_bound >= 0 && self.upper_bound <= i128::from(std::u32::MAX),
-            U64 => self.lower_bound >= 0 && self.upper_bound <= i128::from(std::u64::MAX),
-            U128 => self.lower_bound >= 0 && self.upper_bound < std::i128::MAX,
-            Usize => self.lower_bound >= 0 && self.upper_bound <= (std::usize::MAX as i128),
-            _ => false,
-        }
+        let value = self.hull();
+        let type_range = Self::for_type(target_type);
+        type_range.lower_bound <= value.lower_bound && value.upper_bound <= type_range.upper_bound
     }
 
     // Returns true if this interval is known to be contained in the interval [0 ... bit size of target_type).
     // A false result just means that we don't know, it never means that we know it does not.
     pub fn is_contained_in_width_of(&self, target_type: &ExpressionType) -> bool {
-        if self.is_bottom() || self.is_top() {
+        if self.is_bottom() || !target_type.is_integer() {
             return false;
         };
-        match target_type {
-            I8 | U8 => self.lower_bound >= 0 && self.upper_bound < 8,
-            I16 | U16 => self.lower_bound >= 0 && self.upper_bound < 16,
-            I32 | U32 => self.lower_bound >= 0 && self.upper_bound < 32,
-            I64 | U64 => self.lower_bound >= 0 && self.upper_bound < 64,
-            I128 | U128 => self.lower_bound >= 0 && self.upper_bound < 128,
-            Isize | Usize => {
-                self.lower_bound >= 0 && self.upper_bound < i128::from(std::usize::MAX.count_ones())
-            }
-            _ => false,
-        }
+        let value = self.hull();
+        value.lower_bound >= ZERO && value.upper_bound < Small(i128::from(target_type.bit_length()))
     }
 
     // All concrete integer values belong to this interval, so we know nothing.
     pub fn is_top(&self) -> bool {
-        self.lower_bound == std::i128::MIN && self.upper_bound == std::i128::MAX
+        self.lower_bound == NegativeInfinity && self.upper_bound == PositiveInfinity
     }
 
-    // [x...y] * [a...b] = [x*a...y*b]
+    // [x...y] * [a...b] = [min(corners)...max(corners)], where the corners are x*a, x*b, y*a and
+    // y*b. 0 times an infinite bound is 0, since the bound itself is never a value.
     pub fn mul(&self, other: &Self) -> Self {
         if self.is_bottom() || other.is_bottom() {
             return BOTTOM.clone();
         }
-        if self.is_top() || other.is_top() {
-            return TOP.clone();
-        }
-        IntervalDomain {
-            lower_bound: self.lower_bound.saturating_mul(other.lower_bound),
-            upper_bound: self.upper_bound.saturating_mul(other.upper_bound),
-        }
+        let (left, right) = (self.hull(), other.hull());
+        let products = [
+            left.lower_bound.mul(right.lower_bound),
+            left.lower_bound.mul(right.upper_bound),
+            left.upper_bound.mul(right.lower_bound),
+            left.upper_bound.mul(right.upper_bound),
+        ];
+        Self::from_bounds(
+            *products.iter().min().unwrap(),
+            *products.iter().max().unwrap(),
+        )
     }
 
     // [x...y] <= [a...b] = y <= a
     // !([x...y] <= [a...b]) = [a...b] < [x...y] = b < x
     pub fn less_equal(&self, other: &Self) -> Option<bool> {
-        if self.is_bottom() || self.is_top() || other.is_bottom() || other.is_top() {
-            None
-        } else if self.upper_bound <= other.lower_bound {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.upper_bound <= right.lower_bound {
             Some(true)
-        } else if other.upper_bound < self.lower_bound {
+        } else if right.upper_bound < left.lower_bound {
             Some(false)
         } else {
             None
@@ -199,29 +369,117 @@ impl IntervalDomain {
     // [x...y] < [a...b] = y < a
     // !([x...y] < [a...b]) = [a...b] <= [x...y] = b <= x
     pub fn less_than(&self, other: &Self) -> Option<bool> {
-        if self.is_bottom() || self.is_top() || other.is_bottom() || other.is_top() {
-            None
-        } else if self.upper_bound < other.lower_bound {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        let (left, right) = (self.hull(), other.hull());
+        if left.upper_bound < right.lower_bound {
             Some(true)
-        } else if other.upper_bound <= self.lower_bound {
+        } else if right.upper_bound <= left.lower_bound {
             Some(false)
         } else {
             None
         }
     }
 
-    // -[x...y] = [-y...-x]
-    pub fn neg(&self) -> Self {
+    // Returns the lower bound of the interval, or None if it is -infinity, unknown or too large
+    // to fit into an i128.
+    pub fn lower_bound(&self) -> Option<i128> {
         if self.is_bottom() {
+            None
+        } else {
+            self.hull().lower_bound.to_i128()
+        }
+    }
+
+    // Returns the upper bound of the interval, or None if it is +infinity, unknown or too large
+    // to fit into an i128.
+    pub fn upper_bound(&self) -> Option<i128> {
+        if self.is_bottom() {
+            None
+        } else {
+            self.hull().upper_bound.to_i128()
+        }
+    }
+
+    // [x...y] join [a...b] = [min(x,a)...max(y,b)]
+    pub fn join(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
             return BOTTOM.clone();
         }
-        if self.is_top() {
-            return TOP.clone();
+        if self == other {
+            return self.clone();
         }
-        IntervalDomain {
-            lower_bound: self.upper_bound.checked_neg().unwrap_or(std::i128::MAX),
-            upper_bound: self.lower_bound.checked_neg().unwrap_or(std::i128::MAX),
+        let (left, right) = (self.hull(), other.hull());
+        Self::from_bounds(
+            cmp::min(left.lower_bound, right.lower_bound),
+            cmp::max(left.upper_bound, right.upper_bound),
+        )
+    }
+
+    // The values of [x...y] that are less than (or equal to, if !is_strict) a value in [a...b].
+    // [x...y] refined by < [a...b] = [x...min(y, b-1)]
+    pub fn refine_less_than(&self, other: &Self, is_strict: bool) -> Self {
+        let other = other.hull();
+        if other.is_bottom() || other.upper_bound == PositiveInfinity {
+            return self.clone();
         }
+        let upper_bound = if is_strict {
+            other.upper_bound.sub(ONE)
+        } else {
+            other.upper_bound
+        };
+        self.intersect(&Self::from_bounds(NegativeInfinity, upper_bound))
+    }
+
+    // The values of [x...y] that are greater than (or equal to, if !is_strict) a value in [a...b].
+    // [x...y] refined by > [a...b] = [max(x, a+1)...y]
+    pub fn refine_greater_than(&self, other: &Self, is_strict: bool) -> Self {
+        let other = other.hull();
+        if other.is_bottom() || other.lower_bound == NegativeInfinity {
+            return self.clone();
+        }
+        let lower_bound = if is_strict {
+            other.lower_bound.add(ONE)
+        } else {
+            other.lower_bound
+        };
+        self.intersect(&Self::from_bounds(lower_bound, PositiveInfinity))
+    }
+
+    // [x...y] narrow [a...b] = [(x == -infinity ? a : x)...(y == +infinity ? b : y)]
+    // Only infinite bounds are replaced, so a sequence of narrowings stops changing quickly.
+    // Since bottom denotes an interval that is not known, narrowing bottom just gives other.
+    // The bounds of a wrapped interval are always finite, so it does not narrow.
+    pub fn narrow(&self, other: &Self) -> Self {
+        if self.is_bottom() {
+            return other.clone();
+        }
+        if other.is_bottom() || self.wrapped_type.is_some() {
+            return self.clone();
+        }
+        let other = other.hull();
+        Self::from_bounds(
+            if self.lower_bound == NegativeInfinity {
+                other.lower_bound
+            } else {
+                self.lower_bound
+            },
+            if self.upper_bound == PositiveInfinity {
+                other.upper_bound
+            } else {
+                self.upper_bound
+            },
+        )
+    }
+
+    // -[x...y] = [-y...-x]
+    pub fn neg(&self) -> Self {
+        if self.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let value = self.hull();
+        Self::from_bounds(value.upper_bound.neg(), value.lower_bound.neg())
     }
 
     // [x...y] - [a...b] = [x-b...y-a]
@@ -229,26 +487,341 @@ impl IntervalDomain {
         if self.is_bottom() || other.is_bottom() {
             return BOTTOM.clone();
         }
-        if self.is_top() || other.is_top() {
-            return TOP.clone();
+        let (left, right) = (self.hull(), other.hull());
+        Self::from_bounds(
+            left.lower_bound.sub(right.upper_bound),
+            left.upper_bound.sub(right.lower_bound),
+        )
+    }
+
+    // Returns true if every value in self is also in other.
+    // [x...y] subset [a...b] = a <= x && y <= b
+    // A wrapped interval [a...b] of type t is the union of [a...t::MAX] and [t::MIN...b], so
+    // self is a subset of it if self is a subset of either part, or if self is a wrapped
+    // interval of the same type that wraps around less.
+    pub fn subset(&self, other: &Self) -> bool {
+        if self.is_bottom() || other.is_bottom() {
+            return false;
         }
-        IntervalDomain {
-            lower_bound: self.lower_bound.saturating_sub(other.upper_bound),
-            upper_bound: self.upper_bound.saturating_sub(other.lower_bound),
+        match &other.wrapped_type {
+            None => {
+                let value = self.hull();
+                other.lower_bound <= value.lower_bound && value.upper_bound <= other.upper_bound
+            }
+            Some(wrapped_type) => {
+                let type_range = Self::for_type(wrapped_type);
+                self.subset(&Self::from_bounds(
+                    other.lower_bound,
+                    type_range.upper_bound,
+                )) || self.subset(&Self::from_bounds(
+                    type_range.lower_bound,
+                    other.upper_bound,
+                )) || (self.wrapped_type == other.wrapped_type
+                    && other.lower_bound <= self.lower_bound
+                    && self.upper_bound <= other.upper_bound)
+            }
         }
     }
 
-    // [x...y] widen [a...b] = [min(x,a)...max(y,b)],
-    pub fn widen(&self, other: &Self) -> Self {
+    // [x...y] % [a...b] has the sign of the dividend, a magnitude that is less than that of the
+    // divisor and no larger than that of the dividend. So it is contained in
+    // [max(x, 1-m)...min(y, m-1)], where m = max(|a|, |b|), and in [0...] if x >= 0 and in
+    // [...0] if y <= 0.
+    pub fn rem(&self, other: &Self) -> Self {
         if self.is_bottom() || other.is_bottom() {
             return BOTTOM.clone();
         }
-        if self.is_top() || other.is_top() {
-            return TOP.clone();
+        let (left, right) = (self.hull(), other.hull());
+        if right.lower_bound == ZERO && right.upper_bound == ZERO {
+            // The divisor is always zero.
+            return BOTTOM.clone();
         }
-        IntervalDomain {
-            lower_bound: cmp::min(self.lower_bound, other.lower_bound),
-            upper_bound: cmp::max(self.upper_bound, other.upper_bound),
+        let max_magnitude = cmp::max(right.lower_bound.neg(), right.upper_bound).sub(ONE);
+        let lower_bound = if left.lower_bound >= ZERO {
+            ZERO
+        } else {
+            cmp::max(left.lower_bound, max_magnitude.neg())
+        };
+        let upper_bound = if left.upper_bound <= ZERO {
+            ZERO
+        } else {
+            cmp::min(left.upper_bound, max_magnitude)
+        };
+        Self::from_bounds(lower_bound, upper_bound)
+    }
+
+    // [x...y] << [a...b] = [x << a...y << b] if x >= 0 and y << b is in the range of the result
+    // type, since no bits are then shifted out. Otherwise the result could be any value of the
+    // result type. Shift amounts that overflow are left out, since they cause a panic.
+    pub fn shl(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        if self.is_bottom() || other.is_bottom() || !result_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        let (min_shift, max_shift) = match other.get_shift_amounts(result_type) {
+            Some(shift_amounts) => shift_amounts,
+            None => return BOTTOM.clone(),
+        };
+        let type_range = Self::for_type(result_type);
+        let value = self.hull();
+        if value.lower_bound >= ZERO {
+            let upper_bound = value.upper_bound.shl(max_shift);
+            if upper_bound <= type_range.upper_bound {
+                return Self::from_bounds(value.lower_bound.shl(min_shift), upper_bound);
+            }
+        }
+        type_range
+    }
+
+    // [x...y] >> [a...b] = [min(x >> a, x >> b)...max(y >> a, y >> b)], where [x...y] is first
+    // restricted to the range of the result type. The shift is arithmetic, i.e. it rounds towards
+    // negative infinity, which also works for unsigned values since they are never negative.
+    // Shift amounts that overflow are left out, since they cause a panic.
+    pub fn shr(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        if self.is_bottom() || other.is_bottom() || !result_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        let (min_shift, max_shift) = match other.get_shift_amounts(result_type) {
+            Some(shift_amounts) => shift_amounts,
+            None => return BOTTOM.clone(),
+        };
+        let value = self.hull().intersect(&Self::for_type(result_type));
+        Self::from_bounds(
+            cmp::min(
+                value.lower_bound.shr(min_shift),
+                value.lower_bound.shr(max_shift),
+            ),
+            cmp::max(
+                value.upper_bound.shr(min_shift),
+                value.upper_bound.shr(max_shift),
+            ),
+        )
+    }
+
+    // Returns the smallest and largest values in self that are valid amounts for shifting a value
+    // of the given type, or None if there are no such values.
+    fn get_shift_amounts(&self, result_type: &ExpressionType) -> Option<(u32, u32)> {
+        let value = self.hull();
+        let bit_length = i128::from(result_type.bit_length());
+        let min_shift = cmp::max(value.lower_bound, ZERO);
+        let max_shift = cmp::min(value.upper_bound, Small(bit_length - 1));
+        if min_shift > max_shift {
+            None
+        } else {
+            Some((min_shift.to_i128()? as u32, max_shift.to_i128()? as u32))
+        }
+    }
+
+    // [x...y] widen [a...b] = [min(x,a)...max(y,b)], except that a bound that differs between the
+    // two intervals moves out to the nearest threshold, or to infinity if there is none.
+    // Since there are finitely many thresholds, a sequence of widenings reaches a fixed point
+    // quickly, while bounds that coincide with thresholds, such as the length of an array that
+    // is being iterated over, survive the widening.
+    pub fn widen(&self, other: &Self, thresholds: &BTreeSet<i128>) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let (left, right) = (self.hull(), other.hull());
+        let lower_bound = if left.lower_bound == right.lower_bound {
+            left.lower_bound
+        } else {
+            let lower_bound = cmp::min(left.lower_bound, right.lower_bound);
+            thresholds
+                .iter()
+                .rev()
+                .map(|threshold| Small(*threshold))
+                .find(|threshold| *threshold <= lower_bound)
+                .unwrap_or(NegativeInfinity)
+        };
+        let upper_bound = if left.upper_bound == right.upper_bound {
+            left.upper_bound
+        } else {
+            let upper_bound = cmp::max(left.upper_bound, right.upper_bound);
+            thresholds
+                .iter()
+                .map(|threshold| Small(*threshold))
+                .find(|threshold| *threshold >= upper_bound)
+                .unwrap_or(PositiveInfinity)
+        };
+        Self::from_bounds(lower_bound, upper_bound)
+    }
+}
+
+/// A bound of an interval. The values of all integer types lie in [i128::MIN...u128::MAX],
+/// so a finite bound is either an i128 or a u128 that is too large to be an i128.
+/// The variants are listed in increasing order, so the derived ordering is the numeric one.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialOrd, PartialEq, Hash, Ord)]
+enum Bound {
+    NegativeInfinity,
+    Small(i128),
+    // Always larger than std::i128::MAX.
+    Large(u128),
+    PositiveInfinity,
+}
+
+const ZERO: Bound = Small(0);
+const ONE: Bound = Small(1);
+
+impl From<i128> for Bound {
+    fn from(i: i128) -> Bound {
+        Small(i)
+    }
+}
+
+impl From<u128> for Bound {
+    fn from(u: u128) -> Bound {
+        match i128::try_from(u) {
+            Ok(i) => Small(i),
+            Err(..) => Large(u),
+        }
+    }
+}
+
+impl Bound {
+    // Returns the finite bound with the given sign and magnitude, or an infinite bound if the
+    // value is smaller than i128::MIN.
+    fn from_sign_and_magnitude(is_negative: bool, magnitude: u128) -> Bound {
+        if !is_negative {
+            magnitude.into()
+        } else if magnitude <= 1 << 127 {
+            Small((magnitude as i128).wrapping_neg())
+        } else {
+            NegativeInfinity
+        }
+    }
+
+    // Returns the sign and magnitude of a finite bound.
+    fn sign_and_magnitude(self) -> Option<(bool, u128)> {
+        match self {
+            Small(i) => Some((i < 0, i.wrapping_abs() as u128)),
+            Large(u) => Some((false, u)),
+            _ => None,
+        }
+    }
+
+    // Returns the infinite bound with the given sign.
+    fn infinity(is_negative: bool) -> Bound {
+        if is_negative {
+            NegativeInfinity
+        } else {
+            PositiveInfinity
+        }
+    }
+
+    // Returns true if self is less than zero.
+    fn is_negative(self) -> bool {
+        self < ZERO
+    }
+
+    // Returns self + other. If one of the operands is infinite, so is the result.
+    // Results that are too large to be finite bounds become infinite.
+    fn add(self, other: Bound) -> Bound {
+        match (self.sign_and_magnitude(), other.sign_and_magnitude()) {
+            (Some((is_negative, m1)), Some((other_is_negative, m2))) => {
+                if is_negative == other_is_negative {
+                    match m1.checked_add(m2) {
+                        Some(magnitude) => Self::from_sign_and_magnitude(is_negative, magnitude),
+                        None => Self::infinity(is_negative),
+                    }
+                } else if m1 >= m2 {
+                    Self::from_sign_and_magnitude(is_negative, m1 - m2)
+                } else {
+                    Self::from_sign_and_magnitude(other_is_negative, m2 - m1)
+                }
+            }
+            (None, _) => self,
+            (_, None) => other,
+        }
+    }
+
+    // Returns the smallest number of the form 2^n-1 that is not less than self, which must not be
+    // negative.
+    fn all_ones_up_to(self) -> Bound {
+        match self.sign_and_magnitude() {
+            Some((_, 0)) => ZERO,
+            Some((_, magnitude)) => (std::u128::MAX >> magnitude.leading_zeros()).into(),
+            None => self,
+        }
+    }
+
+    // Returns self / other, truncated towards zero. Other must not be zero.
+    fn div(self, other: Bound) -> Bound {
+        let is_negative = self.is_negative() != other.is_negative();
+        match (self.sign_and_magnitude(), other.sign_and_magnitude()) {
+            (Some((_, m1)), Some((_, m2))) => Self::from_sign_and_magnitude(is_negative, m1 / m2),
+            (None, _) => Self::infinity(is_negative),
+            (_, None) => ZERO,
+        }
+    }
+
+    // Returns self * other. Zero times an infinite bound is zero.
+    fn mul(self, other: Bound) -> Bound {
+        if self == ZERO || other == ZERO {
+            return ZERO;
+        }
+        let is_negative = self.is_negative() != other.is_negative();
+        match (self.sign_and_magnitude(), other.sign_and_magnitude()) {
+            (Some((_, m1)), Some((_, m2))) => match m1.checked_mul(m2) {
+                Some(magnitude) => Self::from_sign_and_magnitude(is_negative, magnitude),
+                None => Self::infinity(is_negative),
+            },
+            _ => Self::infinity(is_negative),
+        }
+    }
+
+    // Returns -self.
+    fn neg(self) -> Bound {
+        match self.sign_and_magnitude() {
+            Some((is_negative, magnitude)) => {
+                Self::from_sign_and_magnitude(!is_negative, magnitude)
+            }
+            None => Self::infinity(self == PositiveInfinity),
+        }
+    }
+
+    // Returns self << amount, where amount < 128.
+    fn shl(self, amount: u32) -> Bound {
+        self.mul(Self::from_sign_and_magnitude(false, 1 << amount))
+    }
+
+    // Returns self >> amount, where amount < 128, rounding towards -infinity.
+    fn shr(self, amount: u32) -> Bound {
+        match self.sign_and_magnitude() {
+            Some((false, magnitude)) => Self::from_sign_and_magnitude(false, magnitude >> amount),
+            Some((true, magnitude)) => {
+                Self::from_sign_and_magnitude(true, ((magnitude - 1) >> amount) + 1)
+            }
+            None => self,
+        }
+    }
+
+    // Returns self - other.
+    fn sub(self, other: Bound) -> Bound {
+        self.add(other.neg())
+    }
+
+    // Returns the value of self, if it is finite and fits into an i128.
+    fn to_i128(self) -> Option<i128> {
+        match self {
+            Small(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    // Returns the value of target_type that is congruent to self modulo 2^n, where n is the bit
+    // length of target_type. Self must be finite.
+    fn wrap_into(self, target_type: &ExpressionType) -> Bound {
+        let (is_negative, magnitude) = self.sign_and_magnitude().unwrap();
+        let mask = std::u128::MAX >> (128 - u32::from(target_type.bit_length()));
+        let value = if is_negative {
+            magnitude.wrapping_neg()
+        } else {
+            magnitude
+        };
+        let residue = value & mask;
+        if target_type.is_signed_integer() && residue > (mask >> 1) {
+            Self::from_sign_and_magnitude(true, residue.wrapping_neg() & mask)
+        } else {
+            residue.into()
         }
     }
 }
@@ -9,5 +9,50 @@
 /// Prevents the fixed point loop from creating ever more new abstract values of type Expression::Variable.
 pub const MAX_PATH_LENGTH: usize = 10;
 
+/// The default for the largest depth that the expression of a joined value may have. Deeper
+/// expressions are abstracted, since they make everything that is done with them slow.
+pub const MAX_EXPRESSION_DEPTH: usize = 64;
+
+/// The default for the largest number of nodes that the expression of a joined value may have.
+/// Larger expressions are abstracted, since the joins at the merge points of loops that go
+/// through many branches, such as the loops of state machines, can grow them without limit.
+pub const MAX_EXPRESSION_NODE_COUNT: usize = 1000;
+
+/// The largest number of steps that an operation on the binary decision diagrams of path
+/// conditions may take. Conditions whose diagrams need more steps are not simplified.
+pub const MAX_BDD_STEPS: usize = 10_000;
+
 /// The point at which diverging summaries experience exponential blowup right now.
 pub const MAX_OUTER_FIXPOINT_ITERATIONS: usize = 3;
+
+/// The largest number of times that the fixed point loop of visit_body may visit the blocks of a
+/// body. If the states are still growing after that, their values are all made unknown.
+pub const MAX_FIXPOINT_ITERATIONS: usize = 50;
+
+/// The largest number of constants that visit_body uses as widening thresholds. Every threshold
+/// can cost the fixed point loop another iteration for every loop variable that is widened.
+pub const MAX_WIDENING_THRESHOLDS: usize = 16;
+
+/// The maximum number of times visit_body refines the fixed point of a function body by narrowing.
+pub const MAX_NARROWING_ITERATIONS: usize = 2;
+
+/// The largest number of disjuncts that the built-in linear solver allows in a formula.
+/// Formulas that would become larger are approximated.
+pub const MAX_LINEAR_SOLVER_DISJUNCTS: usize = 64;
+
+/// The largest number of constraints that the built-in linear solver allows while it eliminates
+/// unknowns. Queries that need more constraints are given up on.
+pub const MAX_LINEAR_SOLVER_CONSTRAINTS: usize = 1000;
+
+/// The largest number of paths whose values can be related by the octagon of an environment.
+/// Closing an octagon takes time that is cubic in this number.
+pub const MAX_OCTAGON_PATHS: usize = 24;
+
+/// The number of milliseconds an SMT solver may spend on a single query before giving up.
+pub const SMT_SOLVER_TIMEOUT_MILLISECONDS: u32 = 100;
+
+/// The number of milliseconds to wait for a response from an external solver process before
+/// killing it. This is longer than SMT_SOLVER_TIMEOUT_MILLISECONDS, so that solvers that enforce
+/// that limit themselves have time to report that they gave up, and it also bounds the time
+/// spent on solvers that do not enforce it.
+pub const SMT_SOLVER_PROCESS_TIMEOUT_MILLISECONDS: u64 = 2000;
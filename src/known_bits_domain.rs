@@ -0,0 +1,326 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+use crate::expression::ExpressionType;
+use crate::interval_domain::{self, IntervalDomain};
+
+/// An element of the KnownBits domain is the set of integers whose 128 bit two's complement
+/// representations agree with a partially known bit pattern. It is denoted by a mask, whose set
+/// bits are unknown, and a value, which provides the bits that are not set in the mask.
+/// The values of all integer types have distinct representations, except for the values of type
+/// u128 that are too large to be an i128, which share them with the negative values.
+/// KnownBits domain elements are constructed on demand from AbstractDomain expressions.
+/// They are most useful for checking code that masks, tests and packs bits, for example that
+/// a flag is still set after other flags have been cleared.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq, Hash, Ord)]
+pub struct KnownBitsDomain {
+    value: u128,
+    mask: u128,
+}
+
+// A bit that is known to be one and also unknown does not correspond to any integer.
+pub const BOTTOM: KnownBitsDomain = KnownBitsDomain { value: 1, mask: 1 };
+
+pub const TOP: KnownBitsDomain = KnownBitsDomain {
+    value: 0,
+    mask: std::u128::MAX,
+};
+
+// The bit that is set in the representation of every negative number.
+const SIGN_BIT: u128 = 1 << 127;
+
+impl From<i128> for KnownBitsDomain {
+    fn from(i: i128) -> KnownBitsDomain {
+        KnownBitsDomain {
+            value: i as u128,
+            mask: 0,
+        }
+    }
+}
+
+impl From<u128> for KnownBitsDomain {
+    fn from(u: u128) -> KnownBitsDomain {
+        KnownBitsDomain { value: u, mask: 0 }
+    }
+}
+
+impl KnownBitsDomain {
+    // Returns the integers whose unknown bits are set in mask and whose other bits are the same
+    // as in value.
+    pub fn new(value: u128, mask: u128) -> Self {
+        KnownBitsDomain {
+            value: value & !mask,
+            mask,
+        }
+    }
+
+    // Returns the integers that agree with the bits that are known for all values in the
+    // interval. If both bounds have the same sign, the representations of the values in between
+    // share the leading bits that are the same for both bounds.
+    pub fn from_interval(interval: &IntervalDomain) -> Self {
+        if interval.is_bottom() {
+            return BOTTOM.clone();
+        }
+        match (interval.lower_bound(), interval.upper_bound()) {
+            (Some(lower_bound), Some(upper_bound)) if (lower_bound < 0) == (upper_bound < 0) => {
+                let different_bits = (lower_bound ^ upper_bound) as u128;
+                if different_bits == 0 {
+                    lower_bound.into()
+                } else {
+                    Self::new(
+                        lower_bound as u128,
+                        std::u128::MAX >> different_bits.leading_zeros(),
+                    )
+                }
+            }
+            _ => TOP.clone(),
+        }
+    }
+
+    // Returns the representations of the values of target_type, or bottom if target_type is not
+    // an integer type.
+    pub fn for_type(target_type: &ExpressionType) -> Self {
+        Self::from_interval(&IntervalDomain::for_type(target_type))
+    }
+
+    // Known bits are added with the carries that they are known to produce. A bit of the sum
+    // is unknown if it is unknown in either operand or if it receives an unknown carry, which
+    // shows up as a difference between the smallest and the largest possible sums.
+    pub fn add(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let smallest_sum = self.value.wrapping_add(other.value);
+        let largest_sum = smallest_sum
+            .wrapping_add(self.mask)
+            .wrapping_add(other.mask);
+        Self::new(
+            smallest_sum,
+            (smallest_sum ^ largest_sum) | self.mask | other.mask,
+        )
+    }
+
+    // Returns the representation of the only value in self, if self is a singleton.
+    pub fn as_constant(&self) -> Option<u128> {
+        if self.mask == 0 {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+
+    // Returns the smallest interval that contains all values in self. If the sign bit is not
+    // known to be zero, the representations could belong to negative values as well as to large
+    // u128 values, so nothing is known.
+    pub fn as_interval(&self) -> IntervalDomain {
+        if self.is_bottom() {
+            return interval_domain::BOTTOM;
+        }
+        if (self.value | self.mask) & SIGN_BIT != 0 {
+            return interval_domain::TOP;
+        }
+        IntervalDomain::from(self.value as i128)
+            .join(&IntervalDomain::from((self.value | self.mask) as i128))
+    }
+
+    // A bit of x & y is one if it is known to be one in both operands and it is zero if it is
+    // known to be zero in either operand.
+    pub fn bit_and(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let value = self.value & other.value;
+        let possible_ones = (self.value | self.mask) & (other.value | other.mask);
+        Self::new(value, possible_ones & !value)
+    }
+
+    // A bit of x | y is one if it is known to be one in either operand and it is zero if it is
+    // known to be zero in both operands.
+    pub fn bit_or(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let value = self.value | other.value;
+        Self::new(value, (self.mask | other.mask) & !value)
+    }
+
+    // A bit of x ^ y is known if it is known in both operands.
+    pub fn bit_xor(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        Self::new(self.value ^ other.value, self.mask | other.mask)
+    }
+
+    // x as target_type keeps the low order n bits of x, where n is the bit length of
+    // target_type, and extends them with zeros, or with copies of bit n-1 if target_type is
+    // signed. If self is not known to be an integer, for instance because it is a floating
+    // point value, which saturates, the result could be any value of target_type.
+    pub fn cast(&self, target_type: &ExpressionType) -> Self {
+        if self.is_bottom() || !target_type.is_integer() {
+            return Self::for_type(target_type);
+        }
+        let bit_length = u32::from(target_type.bit_length());
+        if bit_length >= 128 {
+            return self.clone();
+        }
+        let low_bits = (1u128 << bit_length) - 1;
+        let (value, mask) = (self.value & low_bits, self.mask & low_bits);
+        if !target_type.is_signed_integer() {
+            return Self::new(value, mask);
+        }
+        let sign_bit = 1u128 << (bit_length - 1);
+        if mask & sign_bit != 0 {
+            Self::new(value, mask | !low_bits)
+        } else if value & sign_bit != 0 {
+            Self::new(value | !low_bits, mask)
+        } else {
+            Self::new(value, mask)
+        }
+    }
+
+    // Returns Some(true) if self and other are the same singleton, Some(false) if they have no
+    // elements in common, and None otherwise.
+    // Two values are different if a bit that is known in both of them is different.
+    pub fn equals(&self, other: &Self) -> Option<bool> {
+        if self.is_bottom() || other.is_bottom() {
+            return None;
+        }
+        if self.mask == 0 && other.mask == 0 {
+            return Some(self.value == other.value);
+        }
+        if (self.value ^ other.value) & !(self.mask | other.mask) != 0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // The expression that corresponds to this element is not known to result in an integer value.
+    pub fn is_bottom(&self) -> bool {
+        self.value & self.mask != 0
+    }
+
+    // All concrete integer values belong to this element, so we know nothing.
+    pub fn is_top(&self) -> bool {
+        self.mask == std::u128::MAX
+    }
+
+    // A bit of the join is known if it is known and the same in both operands.
+    pub fn join(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        Self::new(
+            self.value & other.value,
+            self.mask | other.mask | (self.value ^ other.value),
+        )
+    }
+
+    // Returns the elements of self that are also in other, which are the values that have the
+    // bits that are known in either of them. If self and other have no elements in common,
+    // which can only happen if a fact is inconsistent with a value, returns self.
+    pub fn meet(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() || self.equals(other) == Some(false) {
+            return self.clone();
+        }
+        Self::new(self.value | other.value, self.mask & other.mask)
+    }
+
+    // x * y is the sum of the values of y shifted left by the positions of the ones in x.
+    // The known part of the product is computed from the known bits, and a sum of the unknown
+    // parts is added to it. A one in x contributes the unknown bits of y, while an unknown bit
+    // in x contributes all bits of y that could be one.
+    pub fn mul(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let (mut left, mut right) = (self.clone(), other.clone());
+        let mut unknown_part: Self = 0u128.into();
+        while left.value != 0 || left.mask != 0 {
+            if left.value & 1 != 0 {
+                unknown_part = unknown_part.add(&Self::new(0, right.mask));
+            } else if left.mask & 1 != 0 {
+                unknown_part = unknown_part.add(&Self::new(0, right.value | right.mask));
+            }
+            left = Self::new(left.value >> 1, left.mask >> 1);
+            right = Self::new(right.value << 1, right.mask << 1);
+        }
+        Self::from(self.value.wrapping_mul(other.value)).add(&unknown_part)
+    }
+
+    // -x = 0 - x
+    pub fn neg(&self) -> Self {
+        Self::from(0u128).sub(self)
+    }
+
+    // x << k = (x * 2^k) as result_type, if k is known. Shift amounts that overflow are left
+    // out, since they cause a panic.
+    pub fn shl(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        if self.is_bottom() || other.is_bottom() || !result_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        match other.get_shift_amount(result_type) {
+            Some(shift) => Self::new(self.value << shift, self.mask << shift).cast(result_type),
+            None => Self::for_type(result_type),
+        }
+    }
+
+    // x >> k shifts the bits of x, as a value of the result type, to the right, if k is known.
+    // Signed values are filled in with copies of the sign bit and unsigned values with zeros.
+    // The representation of a large u128 value has its top bit set, so it must not be copied.
+    pub fn shr(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        if self.is_bottom() || other.is_bottom() || !result_type.is_integer() {
+            return BOTTOM.clone();
+        }
+        match other.get_shift_amount(result_type) {
+            Some(shift) => {
+                let value = self.cast(result_type);
+                if result_type.is_signed_integer() {
+                    Self::new(
+                        ((value.value as i128) >> shift) as u128,
+                        ((value.mask as i128) >> shift) as u128,
+                    )
+                } else {
+                    Self::new(value.value >> shift, value.mask >> shift)
+                }
+            }
+            None => Self::for_type(result_type),
+        }
+    }
+
+    // Returns the value of self, if it is known and is a valid amount for shifting a value of
+    // the given type.
+    fn get_shift_amount(&self, result_type: &ExpressionType) -> Option<u32> {
+        match self.as_constant() {
+            Some(shift) if shift < u128::from(result_type.bit_length()) => Some(shift as u32),
+            _ => None,
+        }
+    }
+
+    // Known bits are subtracted with the borrows that they are known to produce, like add.
+    pub fn sub(&self, other: &Self) -> Self {
+        if self.is_bottom() || other.is_bottom() {
+            return BOTTOM.clone();
+        }
+        let difference = self.value.wrapping_sub(other.value);
+        let largest_difference = difference.wrapping_add(self.mask);
+        let smallest_difference = difference.wrapping_sub(other.mask);
+        Self::new(
+            difference,
+            (largest_difference ^ smallest_difference) | self.mask | other.mask,
+        )
+    }
+
+    // Returns true if every value in self is also in other, which is the case if every bit that
+    // is known in other is known in self and has the same value.
+    pub fn subset(&self, other: &Self) -> bool {
+        if self.is_bottom() || other.is_bottom() {
+            return false;
+        }
+        self.mask & !other.mask == 0 && (self.value ^ other.value) & !other.mask == 0
+    }
+}
@@ -14,6 +14,7 @@
 // While pretty bad, it is a lot less bad than having to write our own compiler, so here goes.
 #![feature(rustc_private)]
 #![feature(box_syntax)]
+#![feature(const_string_new)]
 #![feature(const_vec_new)]
 
 extern crate getopts;
@@ -24,6 +25,8 @@ extern crate rustc_metadata;
 extern crate rustc_target;
 extern crate syntax;
 extern crate syntax_pos;
+#[cfg(feature = "z3")]
+extern crate z3_sys;
 
 #[macro_use]
 extern crate log;
@@ -32,13 +35,31 @@ extern crate serde_derive;
 
 pub mod abstract_domains;
 pub mod abstract_value;
+pub mod bdd;
 pub mod callbacks;
+pub mod congruence_domain;
 pub mod constant_domain;
 pub mod environment;
 pub mod expression;
+pub mod expression_arena;
+pub mod float_interval_domain;
 pub mod interval_domain;
 pub mod k_limits;
+pub mod known_bits_domain;
+pub mod linear_solver;
+pub mod loop_analysis;
+pub mod octagon_domain;
+pub mod options;
+pub mod reduced_product;
+pub mod simplifier;
+pub mod smt_lib_solver;
 pub mod smt_solver;
+pub mod solver_cache;
+pub mod statistics;
+pub mod string_domain;
 pub mod summaries;
 pub mod utils;
+pub mod variant_set_domain;
 pub mod visitors;
+#[cfg(feature = "z3")]
+pub mod z3_solver;
@@ -0,0 +1,840 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::abstract_domains::AbstractDomain;
+use crate::constant_domain::ConstantDomain;
+use crate::expression::{Expression, ExpressionType};
+use crate::interval_domain::IntervalDomain;
+use crate::k_limits;
+use crate::smt_solver::{SmtResult, SmtSolver};
+
+use std::collections::BTreeMap;
+
+/// An implementation of SmtSolver that is written in Rust and needs no external solver.
+/// It decides whether linear constraints over integers can be satisfied, using
+/// Fourier-Motzkin elimination.
+///
+/// Conditions are translated into disjunctive normal form, where each literal is a constraint
+/// of the form c + a1*x1 + ... + an*xn <= 0. The unknowns x1 ... xn are the values of variables,
+/// along with the values of any sub expressions that are not linear, which are treated as opaque.
+/// Strict inequalities are turned into non strict ones by adding one and constraints are divided
+/// by the greatest common divisor of their coefficients, rounding the constant up, which rules out
+/// many (but not all) solutions that are not integers.
+///
+/// An Unsatisfiable result is always correct. A Satisfiable result means that the constraints
+/// have a solution over the rationals. If a condition could not be translated exactly, the
+/// solver uses a weaker condition instead and answers Undefined rather than Satisfiable.
+pub struct LinearSolver {
+    /// The formulas asserted in each of the nested contexts of the solver.
+    /// The last element corresponds to the current context.
+    asserted_formulas: Vec<Vec<LinearFormula>>,
+    /// The number of fresh unknowns that have been created so far.
+    fresh_unknown_count: usize,
+    /// False if the translation of the current expression has had to approximate.
+    is_exact: bool,
+    /// Constraints that limit the variables in the current expression to the ranges of their types.
+    range_constraints: Vec<LinearSum>,
+}
+
+/// The translation of a MIRAI condition into a formula in disjunctive normal form.
+#[derive(Clone, Debug)]
+pub struct LinearFormula {
+    /// Each disjunct is a conjunction of constraints of the form sum <= 0.
+    disjuncts: Vec<Vec<LinearSum>>,
+    /// False if this formula is weaker than the condition it was translated from.
+    is_exact: bool,
+}
+
+/// An unknown value that appears in a linear sum.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Unknown {
+    /// A value that is unrelated to any other value, such as an occurrence of Top.
+    Fresh(usize),
+    /// The value of a variable, or of a sub expression that is not linear.
+    Term(Expression),
+}
+
+/// The linear sum constant + coefficient1 * unknown1 + ... + coefficientN * unknownN.
+/// Coefficients are never zero.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct LinearSum {
+    coefficients: BTreeMap<Unknown, i128>,
+    constant: i128,
+}
+
+impl Default for LinearSolver {
+    fn default() -> Self {
+        LinearSolver::new()
+    }
+}
+
+impl LinearSolver {
+    pub fn new() -> LinearSolver {
+        LinearSolver {
+            asserted_formulas: vec![Vec::new()],
+            fresh_unknown_count: 0,
+            is_exact: true,
+            range_constraints: Vec::new(),
+        }
+    }
+}
+
+impl SmtSolver<LinearFormula> for LinearSolver {
+    fn as_debug_string(&self, expression: &LinearFormula) -> String {
+        format!("{:?}", expression)
+    }
+
+    fn assert(&mut self, expression: &LinearFormula) {
+        if let Some(formulas) = self.asserted_formulas.last_mut() {
+            formulas.push(expression.clone());
+        }
+    }
+
+    fn backtrack(&mut self) {
+        if self.asserted_formulas.len() > 1 {
+            self.asserted_formulas.pop();
+        }
+    }
+
+    fn get_as_smt_predicate(&mut self, mirai_expression: &Expression) -> LinearFormula {
+        self.is_exact = true;
+        let mut disjuncts = self.get_as_disjuncts(mirai_expression, true);
+        let range_constraints = std::mem::replace(&mut self.range_constraints, Vec::new());
+        for disjunct in disjuncts.iter_mut() {
+            disjunct.extend(range_constraints.iter().cloned());
+        }
+        LinearFormula {
+            disjuncts,
+            is_exact: self.is_exact,
+        }
+    }
+
+    fn set_backtrack_position(&mut self) {
+        self.asserted_formulas.push(Vec::new());
+    }
+
+    fn solve(&mut self) -> SmtResult {
+        let mut is_exact = true;
+        let mut disjuncts: Vec<Vec<LinearSum>> = vec![Vec::new()];
+        for formula in self.asserted_formulas.iter().flatten() {
+            is_exact &= formula.is_exact;
+            if disjuncts.len() * formula.disjuncts.len() > k_limits::MAX_LINEAR_SOLVER_DISJUNCTS {
+                // Leaving out a formula weakens the conjunction, so Unsatisfiable is still correct.
+                is_exact = false;
+                continue;
+            }
+            disjuncts = conjoin(&disjuncts, &formula.disjuncts);
+        }
+        let mut result = SmtResult::Unsatisfiable;
+        for constraints in disjuncts {
+            match is_satisfiable(constraints) {
+                Some(true) if is_exact => return SmtResult::Satisfiable,
+                Some(true) => return SmtResult::Undefined,
+                Some(false) => {}
+                None => result = SmtResult::Undefined,
+            }
+        }
+        result
+    }
+}
+
+/// Translation of MIRAI expressions into linear formulas.
+impl LinearSolver {
+    /// Returns true if the given expression results in a Boolean value, rather than a number.
+    fn is_bool_expression(expression: &Expression) -> bool {
+        use self::Expression::*;
+        match expression {
+            AddOverflows { .. }
+            | And { .. }
+            | Equals { .. }
+            | GreaterOrEqual { .. }
+            | GreaterThan { .. }
+            | LessOrEqual { .. }
+            | LessThan { .. }
+            | MulOverflows { .. }
+            | Ne { .. }
+            | Not { .. }
+            | Or { .. }
+            | ShlOverflows { .. }
+            | ShrOverflows { .. }
+            | SubOverflows { .. } => true,
+            CompileTimeConstant(ConstantDomain::True)
+            | CompileTimeConstant(ConstantDomain::False) => true,
+            ConditionalExpression { consequent, .. } => {
+                Self::is_bool_expression(&consequent.expression)
+            }
+            Variable { var_type, .. } => *var_type == ExpressionType::Bool,
+            _ => false,
+        }
+    }
+
+    /// Translates the given Boolean expression (or its negation, if is_positive is false) into
+    /// disjunctive normal form. Conditions that cannot be translated become true, which makes
+    /// the translation weaker than the expression.
+    fn get_as_disjuncts(
+        &mut self,
+        expression: &Expression,
+        is_positive: bool,
+    ) -> Vec<Vec<LinearSum>> {
+        use self::Expression::*;
+        match expression {
+            And { left, right } | Or { left, right } => {
+                let left_disjuncts = self.get_as_disjuncts(&left.expression, is_positive);
+                let right_disjuncts = self.get_as_disjuncts(&right.expression, is_positive);
+                let is_conjunction = match expression {
+                    And { .. } => is_positive,
+                    _ => !is_positive,
+                };
+                if is_conjunction {
+                    self.conjoin(left_disjuncts, right_disjuncts)
+                } else {
+                    self.disjoin(left_disjuncts, right_disjuncts)
+                }
+            }
+            CompileTimeConstant(ConstantDomain::False) if is_positive => vec![],
+            CompileTimeConstant(ConstantDomain::False) => vec![vec![]],
+            CompileTimeConstant(ConstantDomain::True) if is_positive => vec![vec![]],
+            CompileTimeConstant(ConstantDomain::True) => vec![],
+            ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                let condition_holds = self.get_as_disjuncts(&condition.expression, true);
+                let condition_fails = self.get_as_disjuncts(&condition.expression, false);
+                let consequent_disjuncts =
+                    self.get_as_disjuncts(&consequent.expression, is_positive);
+                let alternate_disjuncts = self.get_as_disjuncts(&alternate.expression, is_positive);
+                let consequent_case = self.conjoin(condition_holds, consequent_disjuncts);
+                let alternate_case = self.conjoin(condition_fails, alternate_disjuncts);
+                self.disjoin(consequent_case, alternate_case)
+            }
+            Equals { left, right } => self.get_as_equality(left, right, is_positive),
+            GreaterOrEqual { left, right } => {
+                self.get_as_comparison(right, left, false, is_positive)
+            }
+            GreaterThan { left, right } => self.get_as_comparison(right, left, true, is_positive),
+            LessOrEqual { left, right } => self.get_as_comparison(left, right, false, is_positive),
+            LessThan { left, right } => self.get_as_comparison(left, right, true, is_positive),
+            Ne { left, right } => self.get_as_equality(left, right, !is_positive),
+            Not { operand } => self.get_as_disjuncts(&operand.expression, !is_positive),
+            Variable { var_type, .. } if *var_type == ExpressionType::Bool => {
+                // A Boolean variable is an unknown that is either 0 or 1.
+                let variable = self.get_as_linear_sum(expression);
+                let constraint = if is_positive {
+                    LinearSum::from_constant(1).subtract(&variable)
+                } else {
+                    Some(variable)
+                };
+                self.get_as_literal(constraint)
+            }
+            _ => {
+                self.is_exact = false;
+                vec![vec![]]
+            }
+        }
+    }
+
+    /// Translates left <= right, or left < right if is_strict, (or their negations if is_positive
+    /// is false) into disjunctive normal form.
+    fn get_as_comparison(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        is_strict: bool,
+        is_positive: bool,
+    ) -> Vec<Vec<LinearSum>> {
+        let left_sum = self.get_as_linear_sum(&left.expression);
+        let right_sum = self.get_as_linear_sum(&right.expression);
+        // left < right is left - right + 1 <= 0, !(left <= right) is right - left + 1 <= 0.
+        let constraint = if is_positive {
+            left_sum.subtract(&right_sum)
+        } else {
+            right_sum.subtract(&left_sum)
+        };
+        let constraint = if is_strict == is_positive {
+            constraint.and_then(|c| c.add(&LinearSum::from_constant(1)))
+        } else {
+            constraint
+        };
+        self.get_as_literal(constraint)
+    }
+
+    /// Translates left == right (or left != right if is_positive is false) into disjunctive normal
+    /// form, comparing the operands as Booleans if either of them is Boolean and as numbers otherwise.
+    fn get_as_equality(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        is_positive: bool,
+    ) -> Vec<Vec<LinearSum>> {
+        if Self::is_bool_expression(&left.expression) || Self::is_bool_expression(&right.expression)
+        {
+            let left_holds = self.get_as_disjuncts(&left.expression, true);
+            let left_fails = self.get_as_disjuncts(&left.expression, false);
+            let right_holds = self.get_as_disjuncts(&right.expression, is_positive);
+            let right_fails = self.get_as_disjuncts(&right.expression, !is_positive);
+            let both_hold = self.conjoin(left_holds, right_holds);
+            let both_fail = self.conjoin(left_fails, right_fails);
+            return self.disjoin(both_hold, both_fail);
+        }
+        let left_sum = self.get_as_linear_sum(&left.expression);
+        let right_sum = self.get_as_linear_sum(&right.expression);
+        let difference = left_sum.subtract(&right_sum);
+        let negated_difference = right_sum.subtract(&left_sum);
+        if is_positive {
+            // left - right <= 0 && right - left <= 0
+            let at_most = self.get_as_literal(difference);
+            let at_least = self.get_as_literal(negated_difference);
+            self.conjoin(at_most, at_least)
+        } else {
+            // left - right + 1 <= 0 || right - left + 1 <= 0
+            let one = LinearSum::from_constant(1);
+            let less = self.get_as_literal(difference.and_then(|d| d.add(&one)));
+            let greater = self.get_as_literal(negated_difference.and_then(|d| d.add(&one)));
+            self.disjoin(less, greater)
+        }
+    }
+
+    /// Returns the disjunctive normal form of the single constraint sum <= 0.
+    /// If the constraint could not be computed because of an arithmetic overflow, the result is true.
+    fn get_as_literal(&mut self, constraint: Option<LinearSum>) -> Vec<Vec<LinearSum>> {
+        match constraint {
+            Some(sum) => vec![vec![sum]],
+            None => {
+                self.is_exact = false;
+                vec![vec![]]
+            }
+        }
+    }
+
+    /// Translates the given numeric expression into a linear sum. Sub expressions that are
+    /// not linear become opaque unknowns.
+    fn get_as_linear_sum(&mut self, expression: &Expression) -> LinearSum {
+        use self::Expression::*;
+        let sum = match expression {
+            AbstractHeapAddress(..) | Reference(..) => {
+                Some(LinearSum::from_unknown(Unknown::Term(expression.clone())))
+            }
+            Add { left, right } => {
+                let left_sum = self.get_as_linear_sum(&left.expression);
+                let right_sum = self.get_as_linear_sum(&right.expression);
+                left_sum.add(&right_sum)
+            }
+            CompileTimeConstant(ConstantDomain::Char(ch)) => {
+                Some(LinearSum::from_constant(i128::from(*ch as u32)))
+            }
+            CompileTimeConstant(ConstantDomain::I128(val)) => Some(LinearSum::from_constant(*val)),
+            CompileTimeConstant(ConstantDomain::U128(val)) if *val <= std::i128::MAX as u128 => {
+                Some(LinearSum::from_constant(*val as i128))
+            }
+            Mul { left, right } => {
+                let left_sum = self.get_as_linear_sum(&left.expression);
+                let right_sum = self.get_as_linear_sum(&right.expression);
+                match (left_sum.as_constant(), right_sum.as_constant()) {
+                    (Some(factor), _) => right_sum.multiply(factor),
+                    (_, Some(factor)) => left_sum.multiply(factor),
+                    _ => None,
+                }
+            }
+            Neg { operand } => self.get_as_linear_sum(&operand.expression).multiply(-1),
+            Sub { left, right } => {
+                let left_sum = self.get_as_linear_sum(&left.expression);
+                let right_sum = self.get_as_linear_sum(&right.expression);
+                left_sum.subtract(&right_sum)
+            }
+            Variable { var_type, .. }
+                if var_type.is_integer() || *var_type == ExpressionType::Bool =>
+            {
+                let variable = LinearSum::from_unknown(Unknown::Term(expression.clone()));
+                self.add_range_constraints(&variable, var_type);
+                Some(variable)
+            }
+            // Conversions that do not change the value have already been simplified away.
+            // The others may wrap around, which is not modeled, but the result is at least
+            // known to be in the range of the target type.
+            Cast { target_type, .. } if target_type.is_integer() => {
+                self.is_exact = false;
+                let variable = LinearSum::from_unknown(Unknown::Term(expression.clone()));
+                self.add_range_constraints(&variable, target_type);
+                Some(variable)
+            }
+            Widen { interval, .. } => {
+                let variable = LinearSum::from_unknown(Unknown::Term(expression.clone()));
+                self.add_interval_constraints(&variable, interval);
+                Some(variable)
+            }
+            _ => None,
+        };
+        sum.unwrap_or_else(|| {
+            self.is_exact = false;
+            self.get_opaque_unknown(expression)
+        })
+    }
+
+    /// Returns an unknown for the value of an expression that cannot be translated into a linear
+    /// sum. Different occurrences of the same expression get the same unknown, except where the
+    /// expression need not have the same value each time, as is the case for Top and for floating
+    /// point numbers, since NaN != NaN.
+    fn get_opaque_unknown(&mut self, expression: &Expression) -> LinearSum {
+        use self::Expression::*;
+        match expression {
+            Top
+            | Bottom
+            | CompileTimeConstant(..)
+            | Variable {
+                var_type: ExpressionType::F32,
+                ..
+            }
+            | Variable {
+                var_type: ExpressionType::F64,
+                ..
+            } => {
+                self.fresh_unknown_count += 1;
+                LinearSum::from_unknown(Unknown::Fresh(self.fresh_unknown_count))
+            }
+            _ => LinearSum::from_unknown(Unknown::Term(expression.clone())),
+        }
+    }
+
+    /// Adds constraints that limit the value of the given variable to the range of its type.
+    fn add_range_constraints(&mut self, variable: &LinearSum, var_type: &ExpressionType) {
+        let (min_value, max_value) = if *var_type == ExpressionType::Bool {
+            (0, 1)
+        } else {
+            (var_type.min_value(), var_type.max_value())
+        };
+        // min_value - variable <= 0
+        if let Some(constraint) = LinearSum::from_constant(min_value).subtract(variable) {
+            self.range_constraints.push(constraint);
+        }
+        // variable - max_value <= 0. Values above i128::MAX are not representable, so just omit
+        // the constraint for u128 variables.
+        if max_value <= std::i128::MAX as u128 {
+            if let Some(constraint) =
+                variable.subtract(&LinearSum::from_constant(max_value as i128))
+            {
+                self.range_constraints.push(constraint);
+            }
+        }
+    }
+
+    /// Adds constraints that limit the value of the given widened value to its interval.
+    fn add_interval_constraints(&mut self, variable: &LinearSum, interval: &IntervalDomain) {
+        // lower_bound - variable <= 0
+        if let Some(lower_bound) = interval.lower_bound() {
+            if let Some(constraint) = LinearSum::from_constant(lower_bound).subtract(variable) {
+                self.range_constraints.push(constraint);
+            }
+        }
+        // variable - upper_bound <= 0
+        if let Some(upper_bound) = interval.upper_bound() {
+            if let Some(constraint) = variable.subtract(&LinearSum::from_constant(upper_bound)) {
+                self.range_constraints.push(constraint);
+            }
+        }
+    }
+
+    /// Returns the disjunctive normal form of the conjunction of the given formulas.
+    /// If the result would be too large, the right formula is left out.
+    fn conjoin(
+        &mut self,
+        left: Vec<Vec<LinearSum>>,
+        right: Vec<Vec<LinearSum>>,
+    ) -> Vec<Vec<LinearSum>> {
+        if left.len() * right.len() > k_limits::MAX_LINEAR_SOLVER_DISJUNCTS {
+            self.is_exact = false;
+            return left;
+        }
+        conjoin(&left, &right)
+    }
+
+    /// Returns the disjunctive normal form of the disjunction of the given formulas.
+    /// If the result would be too large, it is replaced with true.
+    fn disjoin(
+        &mut self,
+        mut left: Vec<Vec<LinearSum>>,
+        mut right: Vec<Vec<LinearSum>>,
+    ) -> Vec<Vec<LinearSum>> {
+        if left.len() + right.len() > k_limits::MAX_LINEAR_SOLVER_DISJUNCTS {
+            self.is_exact = false;
+            return vec![vec![]];
+        }
+        left.append(&mut right);
+        left
+    }
+}
+
+/// Returns the disjunctive normal form of the conjunction of the given formulas, which are
+/// also in disjunctive normal form.
+fn conjoin(left: &[Vec<LinearSum>], right: &[Vec<LinearSum>]) -> Vec<Vec<LinearSum>> {
+    let mut result = Vec::with_capacity(left.len() * right.len());
+    for left_disjunct in left.iter() {
+        for right_disjunct in right.iter() {
+            let mut disjunct = left_disjunct.clone();
+            disjunct.extend(right_disjunct.iter().cloned());
+            result.push(disjunct);
+        }
+    }
+    result
+}
+
+/// Uses Fourier-Motzkin elimination to determine if the conjunction of the given constraints,
+/// which all have the form sum <= 0, has a solution. Returns Some(true) if there is a solution
+/// over the rationals, Some(false) if there is no solution over the integers and None if the
+/// elimination becomes too expensive.
+fn is_satisfiable(constraints: Vec<LinearSum>) -> Option<bool> {
+    let mut constraints = constraints;
+    loop {
+        let mut normalized_constraints = Vec::with_capacity(constraints.len());
+        for constraint in constraints.into_iter() {
+            let constraint = constraint.normalize();
+            if constraint.coefficients.is_empty() {
+                if constraint.constant > 0 {
+                    return Some(false);
+                }
+                continue;
+            }
+            normalized_constraints.push(constraint);
+        }
+        normalized_constraints.sort();
+        normalized_constraints.dedup();
+        if normalized_constraints.len() > k_limits::MAX_LINEAR_SOLVER_CONSTRAINTS {
+            return None;
+        }
+
+        // Eliminate the unknown that gives rise to the fewest new constraints.
+        let mut bound_counts: BTreeMap<&Unknown, (usize, usize)> = BTreeMap::new();
+        for constraint in normalized_constraints.iter() {
+            for (unknown, coefficient) in constraint.coefficients.iter() {
+                let counts = bound_counts.entry(unknown).or_insert((0, 0));
+                if *coefficient > 0 {
+                    counts.0 += 1;
+                } else {
+                    counts.1 += 1;
+                }
+            }
+        }
+        let unknown = match bound_counts
+            .iter()
+            .min_by_key(|(_, (upper, lower))| upper * lower)
+        {
+            Some((unknown, _)) => (*unknown).clone(),
+            None => return Some(true),
+        };
+
+        // Each pair of an upper bound and a lower bound on the unknown gives a new constraint
+        // that does not mention the unknown. Constraints that do not mention it are kept as is.
+        let mut upper_bounds = Vec::new();
+        let mut lower_bounds = Vec::new();
+        constraints = Vec::new();
+        for constraint in normalized_constraints.into_iter() {
+            match constraint.coefficients.get(&unknown) {
+                Some(coefficient) if *coefficient > 0 => upper_bounds.push(constraint),
+                Some(_) => lower_bounds.push(constraint),
+                None => constraints.push(constraint),
+            }
+        }
+        for upper_bound in upper_bounds.iter() {
+            let upper_coefficient = upper_bound.coefficients[&unknown];
+            for lower_bound in lower_bounds.iter() {
+                let lower_coefficient = -lower_bound.coefficients[&unknown];
+                let combined = upper_bound
+                    .multiply(lower_coefficient)?
+                    .add(&lower_bound.multiply(upper_coefficient)?)?;
+                constraints.push(combined);
+            }
+        }
+    }
+}
+
+/// Constructors
+impl LinearSum {
+    /// Returns the linear sum that is just the given constant.
+    fn from_constant(constant: i128) -> LinearSum {
+        LinearSum {
+            coefficients: BTreeMap::new(),
+            constant,
+        }
+    }
+
+    /// Returns the linear sum that is just the given unknown.
+    fn from_unknown(unknown: Unknown) -> LinearSum {
+        let mut coefficients = BTreeMap::new();
+        coefficients.insert(unknown, 1);
+        LinearSum {
+            coefficients,
+            constant: 0,
+        }
+    }
+}
+
+/// Arithmetic. These return None if the result cannot be represented with 128 bit coefficients.
+impl LinearSum {
+    /// Returns self + other.
+    fn add(&self, other: &LinearSum) -> Option<LinearSum> {
+        let mut coefficients = self.coefficients.clone();
+        for (unknown, coefficient) in other.coefficients.iter() {
+            let sum = match coefficients.get(unknown) {
+                Some(c) => c.checked_add(*coefficient)?,
+                None => *coefficient,
+            };
+            if sum == 0 {
+                coefficients.remove(unknown);
+            } else {
+                coefficients.insert(unknown.clone(), sum);
+            }
+        }
+        Some(LinearSum {
+            coefficients,
+            constant: self.constant.checked_add(other.constant)?,
+        })
+    }
+
+    /// Returns the value of self if it does not depend on any unknowns.
+    fn as_constant(&self) -> Option<i128> {
+        if self.coefficients.is_empty() {
+            Some(self.constant)
+        } else {
+            None
+        }
+    }
+
+    /// Returns self * factor.
+    fn multiply(&self, factor: i128) -> Option<LinearSum> {
+        if factor == 0 {
+            return Some(LinearSum::from_constant(0));
+        }
+        let mut coefficients = BTreeMap::new();
+        for (unknown, coefficient) in self.coefficients.iter() {
+            coefficients.insert(unknown.clone(), coefficient.checked_mul(factor)?);
+        }
+        Some(LinearSum {
+            coefficients,
+            constant: self.constant.checked_mul(factor)?,
+        })
+    }
+
+    /// Returns self - other.
+    fn subtract(&self, other: &LinearSum) -> Option<LinearSum> {
+        self.add(&other.multiply(-1)?)
+    }
+
+    /// Returns a constraint that has the same integer solutions as the constraint self <= 0,
+    /// but whose coefficients have no common divisor other than 1.
+    fn normalize(self) -> LinearSum {
+        let divisor = self.coefficients.values().fold(0, |divisor, coefficient| {
+            gcd(divisor, coefficient.checked_abs().unwrap_or(1))
+        });
+        if divisor <= 1 {
+            return self;
+        }
+        // a*x + c <= 0 iff (a/d)*x <= -c/d iff (a/d)*x <= floor(-c/d) iff (a/d)*x + ceil(c/d) <= 0
+        let mut constant = self.constant / divisor;
+        if self.constant % divisor > 0 {
+            constant += 1;
+        }
+        let coefficients = self
+            .coefficients
+            .into_iter()
+            .map(|(unknown, coefficient)| (unknown, coefficient / divisor))
+            .collect();
+        LinearSum {
+            coefficients,
+            constant,
+        }
+    }
+}
+
+/// Returns the greatest common divisor of the given non negative numbers.
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_domains::local_variable;
+
+    fn constant(value: i128) -> AbstractDomain {
+        Expression::CompileTimeConstant(ConstantDomain::I128(value)).into()
+    }
+
+    fn less_than(left: &AbstractDomain, right: &AbstractDomain) -> AbstractDomain {
+        Expression::LessThan {
+            left: box left.clone(),
+            right: box right.clone(),
+        }
+        .into()
+    }
+
+    fn equals(left: &AbstractDomain, right: &AbstractDomain) -> AbstractDomain {
+        Expression::Equals {
+            left: box left.clone(),
+            right: box right.clone(),
+        }
+        .into()
+    }
+
+    fn and(left: AbstractDomain, right: AbstractDomain) -> AbstractDomain {
+        Expression::And {
+            left: box left,
+            right: box right,
+        }
+        .into()
+    }
+
+    fn or(left: AbstractDomain, right: AbstractDomain) -> AbstractDomain {
+        Expression::Or {
+            left: box left,
+            right: box right,
+        }
+        .into()
+    }
+
+    /// Asserts the given conditions in a new context and returns the result of solving it.
+    fn solve(solver: &mut LinearSolver, conditions: &[AbstractDomain]) -> SmtResult {
+        solver.set_backtrack_position();
+        for condition in conditions {
+            let formula = solver.get_as_smt_predicate(&condition.expression);
+            solver.assert(&formula);
+        }
+        let result = solver.solve();
+        solver.backtrack();
+        result
+    }
+
+    #[test]
+    fn satisfiable_constraints() {
+        let (x, y) = (local_variable(1), local_variable(2));
+        let mut solver = LinearSolver::new();
+        assert_eq!(
+            solve(
+                &mut solver,
+                &[less_than(&x, &y), less_than(&y, &constant(10))]
+            ),
+            SmtResult::Satisfiable
+        );
+    }
+
+    #[test]
+    fn unsatisfiable_constraints() {
+        let (x, y, z) = (local_variable(1), local_variable(2), local_variable(3));
+        let mut solver = LinearSolver::new();
+        // x < y, y < z and z < x + 2 cannot all hold, since x + 2 <= z over the integers.
+        let x_plus_two: AbstractDomain = Expression::Add {
+            left: box x.clone(),
+            right: box constant(2),
+        }
+        .into();
+        assert_eq!(
+            solve(
+                &mut solver,
+                &[
+                    less_than(&x, &y),
+                    less_than(&y, &z),
+                    less_than(&z, &x_plus_two)
+                ]
+            ),
+            SmtResult::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn constraints_without_integer_solutions() {
+        let x = local_variable(1);
+        let two_x: AbstractDomain = Expression::Mul {
+            left: box constant(2),
+            right: box x.clone(),
+        }
+        .into();
+        let mut solver = LinearSolver::new();
+        assert_eq!(
+            solve(&mut solver, &[equals(&two_x, &constant(1))]),
+            SmtResult::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn range_constraints() {
+        let x = local_variable(1);
+        let mut solver = LinearSolver::new();
+        // An i32 value cannot exceed i32::MAX.
+        assert_eq!(
+            solve(
+                &mut solver,
+                &[less_than(&constant(i128::from(std::i32::MAX)), &x)]
+            ),
+            SmtResult::Unsatisfiable
+        );
+    }
+
+    #[test]
+    fn backtracking_removes_assertions() {
+        let x = local_variable(1);
+        let mut solver = LinearSolver::new();
+        solver.set_backtrack_position();
+        let formula = solver.get_as_smt_predicate(&less_than(&x, &constant(0)).expression);
+        solver.assert(&formula);
+        assert_eq!(
+            solve(&mut solver, &[less_than(&constant(0), &x)]),
+            SmtResult::Unsatisfiable
+        );
+        solver.backtrack();
+        assert_eq!(
+            solve(&mut solver, &[less_than(&constant(0), &x)]),
+            SmtResult::Satisfiable
+        );
+    }
+
+    /// Returns x == 0 || x == 1 || ... || x == count - 1.
+    fn one_of(x: &AbstractDomain, count: i128) -> AbstractDomain {
+        (1..count).fold(equals(x, &constant(0)), |disjunction, value| {
+            or(disjunction, equals(x, &constant(value)))
+        })
+    }
+
+    #[test]
+    fn disjunct_limit() {
+        let x = local_variable(1);
+        let negative = less_than(&x, &constant(0));
+        let mut solver = LinearSolver::new();
+        let small_count = k_limits::MAX_LINEAR_SOLVER_DISJUNCTS as i128;
+        assert_eq!(
+            solve(&mut solver, &[one_of(&x, small_count), negative.clone()]),
+            SmtResult::Unsatisfiable
+        );
+        // A formula with too many disjuncts is approximated with true, so the solver can no
+        // longer show that there is no solution, but it does not claim that there is one.
+        assert_eq!(
+            solve(&mut solver, &[one_of(&x, small_count + 1), negative]),
+            SmtResult::Undefined
+        );
+    }
+
+    /// Returns x1 < y1 && x2 < y2 && ... && x_count < y_count.
+    fn chain(count: usize) -> AbstractDomain {
+        (1..count).fold(
+            less_than(&local_variable(1), &local_variable(2)),
+            |conjunction, i| {
+                let comparison = less_than(&local_variable(2 * i + 1), &local_variable(2 * i + 2));
+                and(conjunction, comparison)
+            },
+        )
+    }
+
+    #[test]
+    fn constraint_limit() {
+        let mut solver = LinearSolver::new();
+        assert_eq!(solve(&mut solver, &[chain(10)]), SmtResult::Satisfiable);
+        // Every comparison gives one constraint and every variable two range constraints.
+        let count = k_limits::MAX_LINEAR_SOLVER_CONSTRAINTS / 5 + 1;
+        assert_eq!(solve(&mut solver, &[chain(count)]), SmtResult::Undefined);
+    }
+}
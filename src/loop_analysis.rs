@@ -0,0 +1,609 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::interval_domain::{self, IntervalDomain};
+
+use rustc::mir;
+use rustc::mir::interpret::{ConstValue, Scalar};
+use rustc::ty::{Const, LazyConst, TyKind};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+
+// Finds the natural loops of a MIR body and the induction variables of the loops, which are the
+// locals that every iteration changes only by adding the same constant to them and that a guard
+// of the loop compares with a bound that the loop does not change. Given the values that an
+// induction variable and its bound have when the loop is entered, this gives the range of the
+// values that the variable has at the head of the loop and the number of times the loop
+// iterates. The visitor uses the ranges at loop heads, since widening the values of a variable
+// that a loop increments tends to lose the bound that the loop guard puts on it.
+// The locals that every iteration changes at most once by adding the same constant, but that no
+// guard compares with a bound, are counters. The trip count of the loop bounds their ranges, so
+// that counting something in a loop is not reported as a possible overflow.
+//
+// The induction variables are recognized in MIR like that of
+//     while i < n { ...; i += 1; }
+// where the guard and the increment may copy i into temporaries first:
+//     _5 = _1; _4 = Lt(move _5, _2); switchInt(move _4) -> [false: bb_exit, otherwise: bb_body]
+//     _7 = CheckedAdd(_1, const 1usize); assert(!move (_7.1: bool)) -> bb_next
+//     _1 = move (_7.0: usize)
+
+/// A loop of the control flow graph, with a head that dominates all of the blocks of the loop.
+#[derive(Clone, Debug)]
+pub struct NaturalLoop {
+    /// The block through which every path from outside the loop enters the loop.
+    pub head: mir::BasicBlock,
+    /// The blocks of the loop, including the head and the blocks of nested loops.
+    pub body: HashSet<mir::BasicBlock>,
+    /// The blocks of the loop with an edge back to the head.
+    pub latches: Vec<mir::BasicBlock>,
+    /// The induction variables of the loop.
+    pub induction_variables: Vec<InductionVariable>,
+    /// The locals that the loop changes like induction variables, but without a guard.
+    pub counters: Vec<Counter>,
+}
+
+/// The value with which a loop guard compares an induction variable.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LoopBound {
+    Constant(i128),
+    /// A local that the loop does not change.
+    Local(mir::Local),
+}
+
+/// A local that every iteration of a loop changes at most once, by adding step to it, and that
+/// a guard that every iteration passes compares with a bound.
+#[derive(Clone, Debug)]
+pub struct InductionVariable {
+    pub local: mir::Local,
+    /// The constant that is added to the local, which is positive if comparison is Lt or Le
+    /// and negative if comparison is Gt or Ge.
+    pub step: i128,
+    /// The comparison of the local with the bound that holds if the loop does not exit.
+    pub comparison: mir::BinOp,
+    pub bound: LoopBound,
+}
+
+impl InductionVariable {
+    /// Returns the interval of the values that the variable has at the head of the loop, given
+    /// the intervals of its value and of the value of the bound when the loop is entered.
+    /// Returns bottom if one of those is not known.
+    pub fn get_head_interval(
+        &self,
+        initial: &IntervalDomain,
+        bound: &IntervalDomain,
+    ) -> IntervalDomain {
+        if initial.is_bottom() || bound.is_bottom() {
+            return interval_domain::BOTTOM;
+        }
+        // An iteration that continues starts with a value that satisfies the comparison, to which
+        // it may add step, so the values are between the initial value and the last value that
+        // satisfies the comparison, plus step.
+        let last_step = match self.comparison {
+            mir::BinOp::Lt => self.step - 1,
+            mir::BinOp::Gt => self.step + 1,
+            _ => self.step,
+        };
+        let reached = initial.join(&bound.add(&IntervalDomain::from(last_step)));
+        if self.step > 0 {
+            reached.refine_greater_than(initial, false)
+        } else {
+            reached.refine_less_than(initial, false)
+        }
+    }
+
+    /// Returns the largest number of times that the loop can iterate, given the intervals of
+    /// the values of the variable and of the bound when the loop is entered, or None if that is
+    /// not known.
+    pub fn get_trip_count(&self, initial: &IntervalDomain, bound: &IntervalDomain) -> Option<u128> {
+        let distance = if self.step > 0 {
+            bound.upper_bound()?.checked_sub(initial.lower_bound()?)?
+        } else {
+            initial.upper_bound()?.checked_sub(bound.lower_bound()?)?
+        };
+        let distance = match self.comparison {
+            mir::BinOp::Le | mir::BinOp::Ge => distance.checked_add(1)?,
+            _ => distance,
+        };
+        if distance <= 0 {
+            return Some(0);
+        }
+        let step = self.step.checked_abs()?;
+        u128::try_from((distance - 1) / step + 1).ok()
+    }
+}
+
+/// A local that every iteration of a loop changes at most once, by adding step to it, and that
+/// no guard of the loop compares with a bound.
+#[derive(Clone, Debug)]
+pub struct Counter {
+    pub local: mir::Local,
+    pub step: i128,
+}
+
+impl Counter {
+    /// Returns the interval of the values that the counter has at the head of the loop, given
+    /// the interval of its value when the loop is entered and the largest number of times that
+    /// the loop iterates. Returns bottom if the initial value is not known.
+    pub fn get_head_interval(&self, initial: &IntervalDomain, trip_count: u128) -> IntervalDomain {
+        if initial.is_bottom() {
+            return interval_domain::BOTTOM;
+        }
+        let largest_change = i128::try_from(trip_count)
+            .ok()
+            .and_then(|trip_count| trip_count.checked_mul(self.step));
+        match largest_change {
+            Some(change) => initial.join(&initial.add(&IntervalDomain::from(change))),
+            None => interval_domain::TOP,
+        }
+    }
+}
+
+/// Returns the natural loops of the given body, ordered by their heads. Loops that share a head
+/// are merged into one loop.
+pub fn find_loops(mir: &mir::Mir<'_>) -> Vec<NaturalLoop> {
+    let dominators = mir.dominators();
+    let mut latches: BTreeMap<mir::BasicBlock, Vec<mir::BasicBlock>> = BTreeMap::new();
+    for (bb, data) in mir.basic_blocks().iter_enumerated() {
+        if !dominators.is_reachable(bb) {
+            continue;
+        }
+        for successor in data.terminator().successors() {
+            if dominators.is_dominated_by(bb, *successor) {
+                latches.entry(*successor).or_insert_with(Vec::new).push(bb);
+            }
+        }
+    }
+    let mut loops: Vec<NaturalLoop> = latches
+        .into_iter()
+        .map(|(head, latches)| {
+            // The body consists of the blocks from which a latch can be reached without going
+            // through the head.
+            let mut body = HashSet::new();
+            body.insert(head);
+            let mut blocks_to_visit = latches.clone();
+            while let Some(bb) = blocks_to_visit.pop() {
+                if body.insert(bb) {
+                    blocks_to_visit.extend(
+                        mir.predecessors_for(bb)
+                            .iter()
+                            .filter(|pred| dominators.is_reachable(**pred)),
+                    );
+                }
+            }
+            NaturalLoop {
+                head,
+                body,
+                latches,
+                induction_variables: Vec::new(),
+                counters: Vec::new(),
+            }
+        })
+        .collect();
+    let variables: Vec<(Vec<InductionVariable>, Vec<Counter>)> = loops
+        .iter()
+        .map(|natural_loop| find_induction_variables(mir, natural_loop, &loops))
+        .collect();
+    for (natural_loop, (induction_variables, counters)) in loops.iter_mut().zip(variables) {
+        natural_loop.induction_variables = induction_variables;
+        natural_loop.counters = counters;
+    }
+    loops
+}
+
+/// An assignment to a local in a loop: the location of the statement or terminator that does
+/// it and the assigned value, if the assignment is a statement.
+type Definition<'a, 'tcx> = (mir::Location, Option<&'a mir::Rvalue<'tcx>>);
+
+/// Returns the induction variables and the counters of natural_loop. The other loops of the body
+/// are needed to make sure that the variable is not changed by a nested loop.
+fn find_induction_variables(
+    mir: &mir::Mir<'_>,
+    natural_loop: &NaturalLoop,
+    loops: &[NaturalLoop],
+) -> (Vec<InductionVariable>, Vec<Counter>) {
+    let definitions = match get_definitions(mir, natural_loop) {
+        Some(definitions) => definitions,
+        None => return (Vec::new(), Vec::new()),
+    };
+    let borrowed_locals = get_borrowed_locals(mir);
+    let dominators = mir.dominators();
+    let mut blocks: Vec<mir::BasicBlock> = natural_loop.body.iter().cloned().collect();
+    blocks.sort();
+    let mut induction_variables = Vec::new();
+    let mut counters = Vec::new();
+    for (local, local_definitions) in &definitions {
+        if borrowed_locals.contains(local) || local_definitions.len() != 1 {
+            continue;
+        }
+        match mir.local_decls[*local].ty.sty {
+            TyKind::Int(..) | TyKind::Uint(..) => (),
+            _ => continue,
+        }
+        let (location, rvalue) = local_definitions[0];
+        let step = match rvalue.and_then(|rvalue| get_step(mir, *local, rvalue, &definitions)) {
+            Some(step) if step != 0 => step,
+            _ => continue,
+        };
+        // The local must not be incremented more than once per iteration, which would happen if
+        // the increment were in a nested loop.
+        if loops.iter().any(|other_loop| {
+            other_loop.head != natural_loop.head
+                && natural_loop.body.contains(&other_loop.head)
+                && other_loop.body.contains(&location.block)
+        }) {
+            continue;
+        }
+        // Every iteration has to pass the guard, so the guard must dominate the latches.
+        let guard = blocks
+            .iter()
+            .filter(|bb| {
+                natural_loop
+                    .latches
+                    .iter()
+                    .all(|latch| dominators.is_dominated_by(*latch, **bb))
+            })
+            .find_map(|bb| {
+                get_guard(
+                    mir,
+                    natural_loop,
+                    *bb,
+                    *local,
+                    &definitions,
+                    &borrowed_locals,
+                )
+            });
+        match guard {
+            Some((comparison, bound)) => {
+                let is_increasing = match comparison {
+                    mir::BinOp::Lt | mir::BinOp::Le => true,
+                    _ => false,
+                };
+                if is_increasing == (step > 0) {
+                    induction_variables.push(InductionVariable {
+                        local: *local,
+                        step,
+                        comparison,
+                        bound,
+                    });
+                }
+            }
+            None => counters.push(Counter {
+                local: *local,
+                step,
+            }),
+        }
+    }
+    induction_variables.sort_by_key(|induction_variable| induction_variable.local);
+    counters.sort_by_key(|counter| counter.local);
+    (induction_variables, counters)
+}
+
+/// Returns the assignments to the locals that the blocks of natural_loop make, or None if the
+/// loop has inline assembly, which may assign to any local.
+fn get_definitions<'a, 'tcx>(
+    mir: &'a mir::Mir<'tcx>,
+    natural_loop: &NaturalLoop,
+) -> Option<HashMap<mir::Local, Vec<Definition<'a, 'tcx>>>> {
+    let mut definitions: HashMap<mir::Local, Vec<Definition<'a, 'tcx>>> = HashMap::new();
+    for bb in &natural_loop.body {
+        let data = &mir[*bb];
+        for (statement_index, statement) in data.statements.iter().enumerate() {
+            let location = mir::Location {
+                block: *bb,
+                statement_index,
+            };
+            match &statement.kind {
+                mir::StatementKind::Assign(place, rvalue) => {
+                    if let Some(local) = get_base_local(place) {
+                        let rvalue = match place {
+                            mir::Place::Base(..) => Some(&**rvalue),
+                            mir::Place::Projection(..) => None,
+                        };
+                        definitions
+                            .entry(local)
+                            .or_insert_with(Vec::new)
+                            .push((location, rvalue));
+                    }
+                }
+                mir::StatementKind::InlineAsm { .. } => return None,
+                _ => (),
+            }
+        }
+        let place = match &data.terminator().kind {
+            mir::TerminatorKind::Call {
+                destination: Some((place, _)),
+                ..
+            } => place,
+            mir::TerminatorKind::DropAndReplace { location, .. } => location,
+            _ => continue,
+        };
+        if let Some(local) = get_base_local(place) {
+            let location = mir::Location {
+                block: *bb,
+                statement_index: data.statements.len(),
+            };
+            definitions
+                .entry(local)
+                .or_insert_with(Vec::new)
+                .push((location, None));
+        }
+    }
+    Some(definitions)
+}
+
+/// Returns the locals that the body borrows, which can then be changed through references.
+fn get_borrowed_locals(mir: &mir::Mir<'_>) -> HashSet<mir::Local> {
+    let mut borrowed_locals = HashSet::new();
+    for data in mir.basic_blocks().iter() {
+        for statement in &data.statements {
+            if let mir::StatementKind::Assign(_, rvalue) = &statement.kind {
+                if let mir::Rvalue::Ref(_, _, place) = &**rvalue {
+                    if let Some(local) = get_base_local(place) {
+                        borrowed_locals.insert(local);
+                    }
+                }
+            }
+        }
+    }
+    borrowed_locals
+}
+
+/// If rvalue is local + c, local - c or c + local, possibly computed with overflow checks,
+/// returns c (or -c).
+fn get_step<'tcx>(
+    mir: &mir::Mir<'tcx>,
+    local: mir::Local,
+    rvalue: &mir::Rvalue<'tcx>,
+    definitions: &HashMap<mir::Local, Vec<Definition<'_, 'tcx>>>,
+) -> Option<i128> {
+    // The location of the operation, before which the local may be copied to temporaries.
+    let (location, bin_op, left, right) = match rvalue {
+        mir::Rvalue::BinaryOp(bin_op, left, right) => {
+            let location = get_single_definition(local, definitions)?.0;
+            (location, bin_op, left, right)
+        }
+        // The result of a checked operation is the first field of a tuple.
+        mir::Rvalue::Use(mir::Operand::Move(mir::Place::Projection(projection)))
+        | mir::Rvalue::Use(mir::Operand::Copy(mir::Place::Projection(projection))) => {
+            match (&projection.base, &projection.elem) {
+                (
+                    mir::Place::Base(mir::PlaceBase::Local(result)),
+                    mir::ProjectionElem::Field(field, _),
+                ) if field.index() == 0 => match get_single_definition(*result, definitions)? {
+                    (location, Some(mir::Rvalue::CheckedBinaryOp(bin_op, left, right))) => {
+                        (location, bin_op, left, right)
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let left_is_local = is_copy_of(mir, left, local, location, definitions);
+    let right_is_local = is_copy_of(mir, right, local, location, definitions);
+    match bin_op {
+        mir::BinOp::Add if left_is_local => get_integer_constant(right),
+        mir::BinOp::Add if right_is_local => get_integer_constant(left),
+        mir::BinOp::Sub if left_is_local => get_integer_constant(right)?.checked_neg(),
+        _ => None,
+    }
+}
+
+/// If bb ends in a branch on a comparison of local with a loop invariant bound, such that one
+/// branch leaves the loop and the other does not, returns the comparison that holds if the
+/// loop does not exit, with local on the left, and the bound.
+fn get_guard(
+    mir: &mir::Mir<'_>,
+    natural_loop: &NaturalLoop,
+    bb: mir::BasicBlock,
+    local: mir::Local,
+    definitions: &HashMap<mir::Local, Vec<Definition<'_, '_>>>,
+    borrowed_locals: &HashSet<mir::Local>,
+) -> Option<(mir::BinOp, LoopBound)> {
+    let data = &mir[bb];
+    let (discr, values, targets) = match &data.terminator().kind {
+        mir::TerminatorKind::SwitchInt {
+            discr,
+            values,
+            targets,
+            ..
+        } => (discr, values, targets),
+        _ => return None,
+    };
+    if values.len() != 1 || values[0] != 0 || targets.len() != 2 {
+        return None;
+    }
+    let continues_if_true = match (
+        natural_loop.body.contains(&targets[0]),
+        natural_loop.body.contains(&targets[1]),
+    ) {
+        (false, true) => true,
+        (true, false) => false,
+        _ => return None,
+    };
+    let condition = match discr {
+        mir::Operand::Copy(place) | mir::Operand::Move(place) => get_local(place)?,
+        mir::Operand::Constant(..) => return None,
+    };
+    let (location, rvalue) = get_single_definition(condition, definitions)?;
+    if location.block != bb {
+        return None;
+    }
+    let (bin_op, left, right) = match rvalue? {
+        mir::Rvalue::BinaryOp(bin_op, left, right) => (*bin_op, left, right),
+        _ => return None,
+    };
+    let (bin_op, bound) = if is_copy_of(mir, left, local, location, definitions) {
+        (bin_op, right)
+    } else if is_copy_of(mir, right, local, location, definitions) {
+        (swap_comparison(bin_op)?, left)
+    } else {
+        return None;
+    };
+    let bin_op = if continues_if_true {
+        bin_op
+    } else {
+        negate_comparison(bin_op)?
+    };
+    let bound = get_loop_invariant(mir, bound, location, definitions, borrowed_locals)?;
+    debug!(
+        "guard of loop {:?} at {:?}: {:?} {:?} {:?}",
+        natural_loop.head, bb, local, bin_op, bound
+    );
+    Some((bin_op, bound))
+}
+
+/// Returns the comparison that holds for right and left if bin_op holds for left and right.
+fn swap_comparison(bin_op: mir::BinOp) -> Option<mir::BinOp> {
+    match bin_op {
+        mir::BinOp::Lt => Some(mir::BinOp::Gt),
+        mir::BinOp::Le => Some(mir::BinOp::Ge),
+        mir::BinOp::Gt => Some(mir::BinOp::Lt),
+        mir::BinOp::Ge => Some(mir::BinOp::Le),
+        _ => None,
+    }
+}
+
+/// Returns the comparison that holds if bin_op does not.
+fn negate_comparison(bin_op: mir::BinOp) -> Option<mir::BinOp> {
+    match bin_op {
+        mir::BinOp::Lt => Some(mir::BinOp::Ge),
+        mir::BinOp::Le => Some(mir::BinOp::Gt),
+        mir::BinOp::Gt => Some(mir::BinOp::Le),
+        mir::BinOp::Ge => Some(mir::BinOp::Lt),
+        _ => None,
+    }
+}
+
+/// Returns true if operand is local, or a temporary to which local is copied in the block of
+/// the given location, before it, without local being assigned to in between.
+fn is_copy_of(
+    mir: &mir::Mir<'_>,
+    operand: &mir::Operand<'_>,
+    local: mir::Local,
+    location: mir::Location,
+    definitions: &HashMap<mir::Local, Vec<Definition<'_, '_>>>,
+) -> bool {
+    let operand_local = match operand {
+        mir::Operand::Copy(place) | mir::Operand::Move(place) => match get_local(place) {
+            Some(operand_local) => operand_local,
+            None => return false,
+        },
+        mir::Operand::Constant(..) => return false,
+    };
+    if operand_local == local {
+        return true;
+    }
+    let copy_location = match get_copy_source(operand_local, location, definitions) {
+        Some((source, copy_location)) if source == local => copy_location,
+        _ => return false,
+    };
+    definitions[&local].iter().all(|(local_location, _)| {
+        local_location.block != location.block
+            || local_location.statement_index < copy_location.statement_index
+            || local_location.statement_index >= location.statement_index
+    })
+}
+
+/// If temporary is assigned to only once in the loop, by copying a local to it in the block of
+/// the given location before it, returns the local and the location of the copy.
+fn get_copy_source(
+    temporary: mir::Local,
+    location: mir::Location,
+    definitions: &HashMap<mir::Local, Vec<Definition<'_, '_>>>,
+) -> Option<(mir::Local, mir::Location)> {
+    let (copy_location, rvalue) = get_single_definition(temporary, definitions)?;
+    if copy_location.block != location.block
+        || copy_location.statement_index >= location.statement_index
+    {
+        return None;
+    }
+    match rvalue? {
+        mir::Rvalue::Use(mir::Operand::Copy(place))
+        | mir::Rvalue::Use(mir::Operand::Move(place)) => Some((get_local(place)?, copy_location)),
+        _ => None,
+    }
+}
+
+/// If operand is an integer constant or a local that the loop does not change, possibly
+/// copied to a temporary first, returns it as a loop bound.
+fn get_loop_invariant(
+    mir: &mir::Mir<'_>,
+    operand: &mir::Operand<'_>,
+    location: mir::Location,
+    definitions: &HashMap<mir::Local, Vec<Definition<'_, '_>>>,
+    borrowed_locals: &HashSet<mir::Local>,
+) -> Option<LoopBound> {
+    let local = match operand {
+        mir::Operand::Copy(place) | mir::Operand::Move(place) => get_local(place)?,
+        mir::Operand::Constant(..) => {
+            return get_integer_constant(operand).map(LoopBound::Constant)
+        }
+    };
+    let local = if definitions.contains_key(&local) {
+        get_copy_source(local, location, definitions)?.0
+    } else {
+        local
+    };
+    if definitions.contains_key(&local) || borrowed_locals.contains(&local) {
+        None
+    } else {
+        Some(LoopBound::Local(local))
+    }
+}
+
+/// Returns the only assignment to local in the loop, if there is exactly one.
+fn get_single_definition<'a, 'tcx>(
+    local: mir::Local,
+    definitions: &HashMap<mir::Local, Vec<Definition<'a, 'tcx>>>,
+) -> Option<Definition<'a, 'tcx>> {
+    match definitions.get(&local) {
+        Some(local_definitions) if local_definitions.len() == 1 => Some(local_definitions[0]),
+        _ => None,
+    }
+}
+
+/// Returns the value of operand if it is an integer constant.
+fn get_integer_constant(operand: &mir::Operand<'_>) -> Option<i128> {
+    let literal = match operand {
+        mir::Operand::Constant(constant) => constant.literal,
+        _ => return None,
+    };
+    match literal {
+        LazyConst::Evaluated(Const {
+            val: ConstValue::Scalar(Scalar::Bits { bits, size }),
+            ty,
+        }) => match ty.sty {
+            TyKind::Int(..) => Some(match *size {
+                1 => i128::from(*bits as i8),
+                2 => i128::from(*bits as i16),
+                4 => i128::from(*bits as i32),
+                8 => i128::from(*bits as i64),
+                _ => *bits as i128,
+            }),
+            TyKind::Uint(..) => i128::try_from(*bits).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the local if place is just a local.
+fn get_local(place: &mir::Place<'_>) -> Option<mir::Local> {
+    match place {
+        mir::Place::Base(mir::PlaceBase::Local(local)) => Some(*local),
+        _ => None,
+    }
+}
+
+/// Returns the local that place is a part of, if any.
+fn get_base_local(place: &mir::Place<'_>) -> Option<mir::Local> {
+    match place {
+        mir::Place::Base(mir::PlaceBase::Local(local)) => Some(*local),
+        mir::Place::Base(..) => None,
+        mir::Place::Projection(projection) => get_base_local(&projection.base),
+    }
+}
@@ -17,10 +17,14 @@
 
 extern crate env_logger;
 extern crate mirai;
+extern crate rustc;
 extern crate rustc_driver;
 
 use mirai::callbacks;
+use mirai::options::Options;
 use mirai::utils;
+use rustc::session::config::ErrorOutputType;
+use rustc::session::early_error;
 use std::env;
 use std::path::Path;
 
@@ -48,6 +52,10 @@ fn main() {
             command_line_arguments.remove(1);
         }
 
+        // Extract the options meant for Mirai, since the compiler does not know about them.
+        let options = Options::parse_from_args(&mut command_line_arguments)
+            .unwrap_or_else(|message| early_error(ErrorOutputType::default(), &message));
+
         // Tell compiler where to find the std library and so on.
         // The compiler relies on the standard rustc driver to tell it, so we have to do likewise.
         command_line_arguments.push(String::from("--sysroot"));
@@ -55,7 +63,7 @@ fn main() {
 
         rustc_driver::run_compiler(
             &command_line_arguments,
-            box callbacks::MiraiCallbacks::default(),
+            box callbacks::MiraiCallbacks::with_options(options),
             None, // use default file loader
             None, // emit output to default destination
         )
@@ -0,0 +1,410 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+use crate::abstract_value::{Path, PathSelector};
+use crate::k_limits;
+
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A term of an octagonal constraint: the value at a path, or the negation of that value.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum OctagonTerm {
+    Negative(Path),
+    Positive(Path),
+}
+
+impl OctagonTerm {
+    /// Returns the term that denotes the negation of the value denoted by self.
+    pub fn negate(&self) -> OctagonTerm {
+        match self {
+            OctagonTerm::Negative(path) => OctagonTerm::Positive(path.clone()),
+            OctagonTerm::Positive(path) => OctagonTerm::Negative(path.clone()),
+        }
+    }
+
+    /// Returns the path whose value is denoted by self.
+    pub fn path(&self) -> &Path {
+        match self {
+            OctagonTerm::Negative(path) | OctagonTerm::Positive(path) => path,
+        }
+    }
+
+    // Returns c if 2*self <= c is known to hold regardless of the state, because of the kind
+    // of value that the path denotes. Array lengths, for example, are never negative.
+    fn get_intrinsic_bound(&self) -> Option<i128> {
+        match self {
+            OctagonTerm::Negative(Path::QualifiedPath { selector, .. })
+                if **selector == PathSelector::ArrayLength =>
+            {
+                Some(0)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An element of the Octagon domain is a conjunction of constraints of the form a + b <= c,
+/// where a and b are terms of the form x or -x, x is the value at a path in an environment
+/// and c is an integer constant. If a and b are the same term, the constraint bounds a single
+/// value, for example x + x <= 10 says that x <= 5.
+/// Unlike the other domains, the Octagon domain relates the values of different paths, so it is
+/// part of an Environment, rather than of an AbstractDomain. This makes it possible to keep
+/// facts such as i < v.len(), which hold at the head of a loop that iterates over v, but which
+/// cannot be expressed by the interval of i or the interval of v.len().
+/// Elements are kept closed, so that the tightest bound of every constraint that follows from
+/// the others is explicitly present.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OctagonDomain {
+    // Maps (a, b), where a <= b, to the smallest known c such that a + b <= c.
+    constraints: BTreeMap<(OctagonTerm, OctagonTerm), i128>,
+    // True if the constraints cannot all be satisfied, in which case constraints is empty.
+    is_contradictory: bool,
+}
+
+impl OctagonDomain {
+    /// Adds the constraints a + b <= c to self and closes the result.
+    /// Constraints that would make self relate more than k_limits::MAX_OCTAGON_PATHS paths
+    /// are left out, which is sound, since every element of the domain over-approximates
+    /// the states it describes.
+    pub fn add_constraints(&mut self, constraints: Vec<(OctagonTerm, OctagonTerm, i128)>) {
+        if self.is_contradictory {
+            return;
+        }
+        let mut paths = self.get_paths();
+        for (a, b, c) in constraints {
+            if a == b.negate() {
+                // a + -a <= c holds iff 0 <= c.
+                if c < 0 {
+                    self.set_contradictory();
+                    return;
+                }
+                continue;
+            }
+            let mut new_paths = vec![];
+            for path in &[a.path(), b.path()] {
+                if !paths.contains(*path) && !new_paths.contains(path) {
+                    new_paths.push(*path);
+                }
+            }
+            if paths.len() + new_paths.len() > k_limits::MAX_OCTAGON_PATHS {
+                continue;
+            }
+            for path in new_paths {
+                paths.insert(path.clone());
+                let negative_term = OctagonTerm::Negative(path.clone());
+                if let Some(bound) = negative_term.get_intrinsic_bound() {
+                    self.insert_constraint(negative_term.clone(), negative_term, bound);
+                }
+            }
+            self.insert_constraint(a, b, c);
+        }
+        self.close();
+    }
+
+    /// Updates self to reflect that the value at source plus offset has been assigned to path.
+    pub fn assign(&mut self, path: &Path, source: &Path, offset: i128) {
+        if self.is_contradictory {
+            return;
+        }
+        if path == source {
+            self.shift(path, offset);
+            return;
+        }
+        self.forget(path);
+        if source.is_rooted_by(path) {
+            // The value at source is gone.
+            return;
+        }
+        if let Some(negated_offset) = offset.checked_neg() {
+            self.add_constraints(vec![
+                (
+                    OctagonTerm::Positive(path.clone()),
+                    OctagonTerm::Negative(source.clone()),
+                    offset,
+                ),
+                (
+                    OctagonTerm::Negative(path.clone()),
+                    OctagonTerm::Positive(source.clone()),
+                    negated_offset,
+                ),
+            ]);
+        }
+    }
+
+    /// Updates self to reflect that the given constant has been assigned to path.
+    pub fn assign_constant(&mut self, path: &Path, constant: i128) {
+        if self.is_contradictory {
+            return;
+        }
+        self.forget(path);
+        if let (Some(upper), Some(lower)) = (constant.checked_mul(2), constant.checked_mul(-2)) {
+            let positive_term = OctagonTerm::Positive(path.clone());
+            let negative_term = OctagonTerm::Negative(path.clone());
+            self.add_constraints(vec![
+                (positive_term.clone(), positive_term, upper),
+                (negative_term.clone(), negative_term, lower),
+            ]);
+        }
+    }
+
+    /// Removes all constraints that mention path, or a path rooted by path, since the value
+    /// at path is about to change in an unknown way.
+    pub fn forget(&mut self, path: &Path) {
+        let mentions_path =
+            |term: &OctagonTerm| term.path() == path || term.path().is_rooted_by(path);
+        self.constraints = self
+            .constraints
+            .iter()
+            .filter(|((a, b), _)| !mentions_path(a) && !mentions_path(b))
+            .map(|(key, c)| (key.clone(), *c))
+            .collect();
+    }
+
+    /// Returns true if a + b <= c holds in every state described by self.
+    pub fn implies(&self, a: &OctagonTerm, b: &OctagonTerm, c: i128) -> bool {
+        if self.is_contradictory || (*a == b.negate() && c >= 0) {
+            return true;
+        }
+        match self.get_bound(a, b) {
+            Some(bound) => bound <= c,
+            None => false,
+        }
+    }
+
+    /// True if no state satisfies the constraints of self.
+    pub fn is_bottom(&self) -> bool {
+        self.is_contradictory
+    }
+
+    /// True if self does not constrain any values.
+    pub fn is_top(&self) -> bool {
+        !self.is_contradictory && self.constraints.is_empty()
+    }
+
+    /// Returns an element that holds in all states described by self and in all states described
+    /// by other, by keeping the larger bound of every constraint that both imply.
+    pub fn join(&self, other: &Self) -> Self {
+        self.join_or_widen(other, |c1, c2| Some(cmp::max(c1, c2)))
+    }
+
+    /// Returns an element that refines self with the constraints of other that self does
+    /// not have. Self is expected to describe a superset of the states described by other,
+    /// so the result is sound. It is closed, since the refinement can tighten other bounds.
+    pub fn narrow(&self, other: &Self) -> Self {
+        if self.is_contradictory || other.is_contradictory {
+            return other.clone();
+        }
+        let mut result = self.clone();
+        let missing_constraints = other
+            .constraints
+            .iter()
+            .filter(|(key, _)| !self.constraints.contains_key(key))
+            .map(|((a, b), c)| (a.clone(), b.clone(), *c))
+            .collect();
+        result.add_constraints(missing_constraints);
+        result
+    }
+
+    /// Returns true if every state described by self is also described by other.
+    pub fn subset(&self, other: &Self) -> bool {
+        if self.is_contradictory {
+            return true;
+        }
+        if other.is_contradictory {
+            return false;
+        }
+        other
+            .constraints
+            .iter()
+            .all(|((a, b), c)| self.implies(a, b, *c))
+    }
+
+    /// Returns an element that holds in all states described by self and in all states described
+    /// by other. Only the constraints that have the same bound in both are kept, so that a
+    /// sequence of widenings cannot keep growing the bounds of constraints forever.
+    pub fn widen(&self, other: &Self) -> Self {
+        self.join_or_widen(other, |c1, c2| if c1 == c2 { Some(c1) } else { None })
+    }
+
+    // Returns the tightest known c such that a + b <= c. If self does not constrain a + b, but
+    // the terms have intrinsic bounds or bounds in self, the bound of a + b follows from those.
+    fn get_bound(&self, a: &OctagonTerm, b: &OctagonTerm) -> Option<i128> {
+        let key = if a <= b {
+            (a.clone(), b.clone())
+        } else {
+            (b.clone(), a.clone())
+        };
+        if let Some(c) = self.constraints.get(&key) {
+            return Some(*c);
+        }
+        let get_single_bound = |t: &OctagonTerm| {
+            self.constraints
+                .get(&(t.clone(), t.clone()))
+                .cloned()
+                .or_else(|| t.get_intrinsic_bound())
+        };
+        // 2a <= c1 and 2b <= c2 implies a + b <= (c1 + c2) / 2
+        let c1 = get_single_bound(a)?;
+        let c2 = get_single_bound(b)?;
+        Some(c1.checked_add(c2)?.div_euclid(2))
+    }
+
+    // Returns the set of paths that are mentioned by the constraints of self.
+    fn get_paths(&self) -> BTreeSet<Path> {
+        let mut paths = BTreeSet::new();
+        for (a, b) in self.constraints.keys() {
+            paths.insert(a.path().clone());
+            paths.insert(b.path().clone());
+        }
+        paths
+    }
+
+    // Records that a + b <= c, unless a tighter bound is already known.
+    fn insert_constraint(&mut self, a: OctagonTerm, b: OctagonTerm, c: i128) {
+        let key = if a <= b { (a, b) } else { (b, a) };
+        let bound = self.constraints.entry(key).or_insert(c);
+        *bound = cmp::min(*bound, c);
+    }
+
+    // Shared implementation of join and widen. The bound of a constraint in the result is
+    // computed by combine from its bounds in self and other, if both imply the constraint.
+    fn join_or_widen<F>(&self, other: &Self, combine: F) -> Self
+    where
+        F: Fn(i128, i128) -> Option<i128>,
+    {
+        if self.is_contradictory {
+            return other.clone();
+        }
+        if other.is_contradictory {
+            return self.clone();
+        }
+        let mut constraints = BTreeMap::new();
+        for (a, b) in self.constraints.keys().chain(other.constraints.keys()) {
+            if let (Some(c1), Some(c2)) = (self.get_bound(a, b), other.get_bound(a, b)) {
+                if let Some(c) = combine(c1, c2) {
+                    constraints.insert((a.clone(), b.clone()), c);
+                }
+            }
+        }
+        OctagonDomain {
+            constraints,
+            is_contradictory: false,
+        }
+    }
+
+    // Replaces self with an element that has no constraints and that describes no states.
+    fn set_contradictory(&mut self) {
+        self.constraints.clear();
+        self.is_contradictory = true;
+    }
+
+    // Updates the bounds of self to reflect that offset has been added to the value at path.
+    fn shift(&mut self, path: &Path, offset: i128) {
+        let mut constraints = BTreeMap::new();
+        for ((a, b), c) in self.constraints.iter() {
+            let mut c = Some(*c);
+            for term in &[a, b] {
+                c = match term {
+                    OctagonTerm::Positive(p) if p == path => c.and_then(|c| c.checked_add(offset)),
+                    OctagonTerm::Negative(p) if p == path => c.and_then(|c| c.checked_sub(offset)),
+                    _ => c,
+                };
+            }
+            if let Some(c) = c {
+                constraints.insert((a.clone(), b.clone()), c);
+            }
+        }
+        self.constraints = constraints;
+    }
+
+    // Adds all of the constraints that follow from the constraints in self, using the tightest
+    // possible bounds. If the constraints turn out to be contradictory, self becomes bottom.
+    //
+    // The constraints are encoded as a difference bound matrix over the terms x and -x for every
+    // path x. Entry m[i][j] is a bound on t[j] - t[i], so a + b <= c is encoded both as a bound
+    // on a - (-b) and as a bound on b - (-a). The shortest paths of the matrix then give the
+    // tightest bounds of the differences, after which bounds on single terms are rounded to even
+    // numbers, since the values are integers, and combined into bounds on sums of terms.
+    fn close(&mut self) {
+        let paths: Vec<Path> = self.get_paths().into_iter().collect();
+        let n = 2 * paths.len();
+        let index_of = |term: &OctagonTerm| {
+            let i = 2 * paths.binary_search(term.path()).unwrap();
+            match term {
+                OctagonTerm::Positive(..) => i,
+                OctagonTerm::Negative(..) => i + 1,
+            }
+        };
+        let term_at = |i: usize| {
+            if i % 2 == 0 {
+                OctagonTerm::Positive(paths[i / 2].clone())
+            } else {
+                OctagonTerm::Negative(paths[i / 2].clone())
+            }
+        };
+        let mut m: Vec<Vec<Option<i128>>> = vec![vec![None; n]; n];
+        let tighten = |entry: &mut Option<i128>, c: i128| {
+            if entry.map_or(true, |e| c < e) {
+                *entry = Some(c);
+            }
+        };
+        for i in 0..n {
+            m[i][i] = Some(0);
+        }
+        for ((a, b), c) in self.constraints.iter() {
+            tighten(&mut m[index_of(&b.negate())][index_of(a)], *c);
+            tighten(&mut m[index_of(&a.negate())][index_of(b)], *c);
+        }
+        for k in 0..n {
+            for i in 0..n {
+                if let Some(c1) = m[i][k] {
+                    for j in 0..n {
+                        if let Some(c) = m[k][j].and_then(|c2| c1.checked_add(c2)) {
+                            tighten(&mut m[i][j], c);
+                        }
+                    }
+                }
+            }
+        }
+        for i in 0..n {
+            if m[i][i].map_or(false, |c| c < 0) {
+                self.set_contradictory();
+                return;
+            }
+            // m[i][i ^ 1] bounds -t[i] - t[i], which is an even number.
+            if let Some(c) = m[i][i ^ 1] {
+                m[i][i ^ 1] = Some(c.div_euclid(2) * 2);
+            }
+        }
+        for i in 0..n {
+            if let (Some(c1), Some(c2)) = (m[i][i ^ 1], m[i ^ 1][i]) {
+                if c1.checked_add(c2).map_or(false, |c| c < 0) {
+                    self.set_contradictory();
+                    return;
+                }
+            }
+        }
+        for i in 0..n {
+            for j in 0..n {
+                // t[j] - t[i] = (t[j] - (-t[j])) / 2 + (-t[i] - t[i]) / 2
+                if let (Some(c1), Some(c2)) = (m[i][i ^ 1], m[j ^ 1][j]) {
+                    if let Some(c) = c1.checked_add(c2) {
+                        tighten(&mut m[i][j], c.div_euclid(2));
+                    }
+                }
+            }
+        }
+        self.constraints.clear();
+        for i in 0..n {
+            for j in 0..n {
+                if let (false, Some(c)) = (i == j, m[i][j]) {
+                    self.insert_constraint(term_at(j), term_at(i).negate(), c);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::k_limits;
+
+use std::path::PathBuf;
+
+/// The SMT solver that MIRAI uses to decide conditions that the abstract domains cannot decide.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SolverChoice {
+    /// Do not use a solver. All conditions that the abstract domains cannot decide remain undecided.
+    Stub,
+    /// Use the built-in solver for linear integer constraints, which needs nothing to be installed.
+    Linear,
+    /// Use the Z3 library that is linked into MIRAI. Requires MIRAI to be built with the z3 feature.
+    Z3,
+    /// Run the solver executable found at the given path and talk to it in SMT-LIB2.
+    SmtLib(PathBuf),
+}
+
+/// Determines which diagnostics MIRAI reports.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DiagLevel {
+    /// Only report errors that will definitely happen if the code containing them is reached.
+    Relaxed,
+    /// Also report errors that might happen in public functions, since these can be called with
+    /// any arguments. Possible errors in private functions become preconditions.
+    Default,
+    /// Report all possible errors, treating every function as if it could be called with any arguments.
+    Paranoid,
+}
+
+/// The options that control MIRAI. These are obtained from command line arguments of the form
+/// --mirai-<name>=<value>, which are removed before the remaining arguments are given to rustc.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Which diagnostics to report.
+    pub diag_level: DiagLevel,
+    /// The largest depth that the expression of a value joined at a merge point may have.
+    pub max_expression_depth: usize,
+    /// The largest number of nodes that the expression of a value joined at a merge point may have.
+    pub max_expression_node_count: usize,
+    /// The maximum number of times that the bodies of the crate are analyzed while summaries
+    /// are still changing.
+    pub max_fixpoint_iterations: usize,
+    /// The SMT solver to use. Unless a Z3 or SMT-LIB2 solver is chosen, this is the built-in
+    /// linear solver, so that conditions that need a solver are decided without installing one.
+    pub solver: SolverChoice,
+    /// If not None, a file that receives a transcript of the queries sent to an SMT-LIB2 solver.
+    pub solver_transcript_path: Option<PathBuf>,
+    /// The file path of the persistent summary store. If None, the store is created in the
+    /// output directory of the compilation.
+    pub summary_store_path: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            diag_level: DiagLevel::Default,
+            max_expression_depth: k_limits::MAX_EXPRESSION_DEPTH,
+            max_expression_node_count: k_limits::MAX_EXPRESSION_NODE_COUNT,
+            max_fixpoint_iterations: k_limits::MAX_OUTER_FIXPOINT_ITERATIONS,
+            solver: SolverChoice::Linear,
+            solver_transcript_path: None,
+            summary_store_path: None,
+        }
+    }
+}
+
+/// The prefix shared by all of the command line arguments that are meant for MIRAI.
+const OPTION_PREFIX: &str = "--mirai-";
+
+impl Options {
+    /// Removes all of the arguments meant for MIRAI from the given command line arguments and
+    /// returns the options they specify. Option values may follow the option name after an = sign
+    /// or be given as the next argument.
+    /// Returns an error message if an option is not recognized or has an invalid value.
+    pub fn parse_from_args(command_line_arguments: &mut Vec<String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut remaining_arguments = Vec::with_capacity(command_line_arguments.len());
+        let mut arguments = command_line_arguments.drain(..);
+        while let Some(argument) = arguments.next() {
+            if !argument.starts_with(OPTION_PREFIX) {
+                remaining_arguments.push(argument);
+                continue;
+            }
+            let (name, value) = match argument.find('=') {
+                Some(index) => (
+                    String::from(&argument[OPTION_PREFIX.len()..index]),
+                    String::from(&argument[index + 1..]),
+                ),
+                None => {
+                    let name = String::from(&argument[OPTION_PREFIX.len()..]);
+                    match arguments.next() {
+                        Some(value) => (name, value),
+                        None => return Err(format!("missing value for option {}", argument)),
+                    }
+                }
+            };
+            options.set_option(&name, &value)?;
+        }
+        drop(arguments);
+        *command_line_arguments = remaining_arguments;
+        Ok(options)
+    }
+
+    /// Updates the option with the given name (sans prefix) with the given value.
+    fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "diag-level" => {
+                self.diag_level = match value {
+                    "relaxed" => DiagLevel::Relaxed,
+                    "default" => DiagLevel::Default,
+                    "paranoid" => DiagLevel::Paranoid,
+                    _ => {
+                        return Err(format!(
+                            "invalid value for {}diag-level: {}, expected relaxed, default or paranoid",
+                            OPTION_PREFIX, value
+                        ));
+                    }
+                }
+            }
+            "max-expression-depth" => self.max_expression_depth = Self::parse_number(name, value)?,
+            "max-expression-node-count" => {
+                self.max_expression_node_count = Self::parse_number(name, value)?
+            }
+            "max-fixpoint-iterations" => {
+                self.max_fixpoint_iterations = Self::parse_number(name, value)?
+            }
+            "solver" => self.solver = Self::parse_solver_choice(value)?,
+            "solver-transcript" => self.solver_transcript_path = Some(PathBuf::from(value)),
+            "summary-store" => self.summary_store_path = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option {}{}", OPTION_PREFIX, name)),
+        }
+        Ok(())
+    }
+
+    /// Parses the value of the option with the given name (sans prefix) as a number.
+    fn parse_number(name: &str, value: &str) -> Result<usize, String> {
+        value.parse::<usize>().map_err(|_| {
+            format!(
+                "invalid value for {}{}: {}, expected a number",
+                OPTION_PREFIX, name, value
+            )
+        })
+    }
+
+    /// Parses the value of the --mirai-solver option.
+    fn parse_solver_choice(value: &str) -> Result<SolverChoice, String> {
+        if value == "stub" {
+            Ok(SolverChoice::Stub)
+        } else if value == "linear" {
+            Ok(SolverChoice::Linear)
+        } else if value == "z3" {
+            if cfg!(feature = "z3") {
+                Ok(SolverChoice::Z3)
+            } else {
+                Err(String::from(
+                    "MIRAI was built without Z3, use --mirai-solver=smtlib:<path to z3> or rebuild MIRAI with --features z3",
+                ))
+            }
+        } else if value.starts_with("smtlib:") && value.len() > "smtlib:".len() {
+            Ok(SolverChoice::SmtLib(PathBuf::from(
+                &value["smtlib:".len()..],
+            )))
+        } else {
+            Err(format!(
+                "invalid value for {}solver: {}, expected stub, linear, z3 or smtlib:<path>",
+                OPTION_PREFIX, value
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_arguments(arguments: &[&str]) -> Vec<String> {
+        arguments.iter().map(|arg| String::from(*arg)).collect()
+    }
+
+    #[test]
+    fn no_mirai_options() {
+        let mut arguments = to_arguments(&["mirai", "--crate-type", "lib", "lib.rs"]);
+        let options = Options::parse_from_args(&mut arguments).unwrap();
+        assert_eq!(options.diag_level, DiagLevel::Default);
+        assert_eq!(options.solver, SolverChoice::Linear);
+        assert_eq!(
+            arguments,
+            to_arguments(&["mirai", "--crate-type", "lib", "lib.rs"])
+        );
+    }
+
+    #[test]
+    fn valid_values_are_parsed_and_removed() {
+        let mut arguments = to_arguments(&[
+            "mirai",
+            "--mirai-diag-level=paranoid",
+            "lib.rs",
+            "--mirai-max-expression-depth",
+            "7",
+            "--mirai-max-expression-node-count=100",
+            "--mirai-max-fixpoint-iterations=2",
+            "--mirai-solver=linear",
+            "--mirai-solver-transcript",
+            "queries.smt2",
+            "--mirai-summary-store=summaries.sled",
+        ]);
+        let options = Options::parse_from_args(&mut arguments).unwrap();
+        assert_eq!(arguments, to_arguments(&["mirai", "lib.rs"]));
+        assert_eq!(options.diag_level, DiagLevel::Paranoid);
+        assert_eq!(options.max_expression_depth, 7);
+        assert_eq!(options.max_expression_node_count, 100);
+        assert_eq!(options.max_fixpoint_iterations, 2);
+        assert_eq!(options.solver, SolverChoice::Linear);
+        assert_eq!(
+            options.solver_transcript_path,
+            Some(PathBuf::from("queries.smt2"))
+        );
+        assert_eq!(
+            options.summary_store_path,
+            Some(PathBuf::from("summaries.sled"))
+        );
+    }
+
+    #[test]
+    fn smtlib_solver_paths() {
+        let mut arguments = to_arguments(&["--mirai-solver=smtlib:/usr/bin/z3"]);
+        let options = Options::parse_from_args(&mut arguments).unwrap();
+        assert_eq!(
+            options.solver,
+            SolverChoice::SmtLib(PathBuf::from("/usr/bin/z3"))
+        );
+
+        // The path may itself contain a colon or an equals sign.
+        let mut arguments = to_arguments(&["--mirai-solver", "smtlib:C:\\solvers\\cvc4=1.7.exe"]);
+        let options = Options::parse_from_args(&mut arguments).unwrap();
+        assert_eq!(
+            options.solver,
+            SolverChoice::SmtLib(PathBuf::from("C:\\solvers\\cvc4=1.7.exe"))
+        );
+
+        let mut arguments = to_arguments(&["--mirai-solver=smtlib:"]);
+        assert!(Options::parse_from_args(&mut arguments).is_err());
+    }
+
+    #[test]
+    fn z3_solver_needs_z3_feature() {
+        let mut arguments = to_arguments(&["--mirai-solver=z3"]);
+        let result = Options::parse_from_args(&mut arguments);
+        if cfg!(feature = "z3") {
+            assert_eq!(result.unwrap().solver, SolverChoice::Z3);
+        } else {
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let invalid_arguments = [
+            "--mirai-diag-level=loud",
+            "--mirai-max-expression-depth=-1",
+            "--mirai-max-expression-node-count=many",
+            "--mirai-max-fixpoint-iterations=",
+            "--mirai-solver=cvc4",
+            "--mirai-unknown=1",
+        ];
+        for argument in invalid_arguments.iter() {
+            let mut arguments = to_arguments(&[*argument]);
+            assert!(
+                Options::parse_from_args(&mut arguments).is_err(),
+                "{} was accepted",
+                argument
+            );
+        }
+    }
+
+    #[test]
+    fn missing_value_is_rejected() {
+        let mut arguments = to_arguments(&["lib.rs", "--mirai-solver"]);
+        assert_eq!(
+            Options::parse_from_args(&mut arguments).unwrap_err(),
+            "missing value for option --mirai-solver"
+        );
+    }
+}
@@ -0,0 +1,851 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+use crate::abstract_domains::AbstractDomain;
+use crate::congruence_domain::{self, CongruenceDomain};
+use crate::constant_domain::ConstantDomain;
+use crate::expression::{Expression, ExpressionType};
+use crate::interval_domain::{self, IntervalDomain};
+use crate::known_bits_domain::{self, KnownBitsDomain};
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+// An AbstractDomain is an expression, which is the most precise description of its values that
+// MIRAI has. Questions that the expression does not answer by itself are answered by constructing
+// elements of less precise domains on demand. The interval domain is built into AbstractDomain,
+// since its elements are cached and widened, and every other domain can be reduced with it.
+// The congruence and known bits domains are components of a reduced product that decides
+// conditions, such as equalities, whenever any one of the components can decide them. Each
+// component is a Domain that is registered with this module, so domain specific abstractions,
+// such as the states of a protocol, can be added to the analysis without changing
+// AbstractDomain. The floating point interval, string, octagon and variant set domains are not
+// components. They are still constructed, or stored in environments, by the code that uses them.
+// Elements of the components are computed from expressions, which are already joined and widened
+// where control flow merges, so the product itself does not call widen. It is part of the trait
+// for components that keep elements of their own across the iterations of a loop.
+
+/// The operations that a domain needs to provide to be a component of the reduced product.
+/// Elements are computed from AbstractDomain expressions by evaluating the transfer function of
+/// each operator on the elements of the operands. Every transfer function defaults to top, so a
+/// domain only needs to provide the operations that it knows something about.
+pub trait Domain: Clone + Debug + 'static {
+    /// Returns the element that contains all values.
+    fn top() -> Self;
+
+    /// True if the element does not describe the value of an expression, for example because
+    /// the expression is of a type that the domain does not know about.
+    fn is_bottom(&self) -> bool;
+
+    /// True if the element contains all values, so nothing is known.
+    fn is_top(&self) -> bool;
+
+    /// Returns an element that contains the values of both self and other.
+    fn join(&self, other: &Self) -> Self;
+
+    /// Returns an element that contains the values that are in both self and other. It may
+    /// contain more values, but it must not contain more values than either self or other.
+    fn meet(&self, other: &Self) -> Self;
+
+    /// Returns true if every value of self is also in other.
+    fn subset(&self, other: &Self) -> bool;
+
+    /// Returns an element that contains the values of both self and other, such that a small
+    /// number of widen calls deterministically reach a fixed point.
+    fn widen(&self, other: &Self) -> Self {
+        self.join(other)
+    }
+
+    /// Returns the element for a compile time constant.
+    fn for_constant(_value: &ConstantDomain) -> Self {
+        Self::top()
+    }
+
+    /// Returns the element for an expression that is not an operation with a transfer function,
+    /// such as a variable or a widened value.
+    fn for_expression(_expression: &AbstractDomain) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self + other".
+    fn add(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self & other".
+    fn bit_and(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self | other".
+    fn bit_or(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self ^ other".
+    fn bit_xor(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self as target_type".
+    fn cast(&self, _target_type: &ExpressionType) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self / other".
+    fn div(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self * other".
+    fn mul(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "-self".
+    fn neg(&self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self % other".
+    fn rem(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self << other", where the result is of type result_type.
+    fn shl(&self, _other: &Self, _result_type: &ExpressionType) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self >> other", where the result is of type result_type.
+    fn shr(&self, _other: &Self, _result_type: &ExpressionType) -> Self {
+        Self::top()
+    }
+
+    /// Transfer function for "self - other".
+    fn sub(&self, _other: &Self) -> Self {
+        Self::top()
+    }
+
+    /// Returns the element that contains the values of self that are in the given interval.
+    fn reduce(&self, _interval: &IntervalDomain) -> Self {
+        self.clone()
+    }
+
+    /// Returns an interval that contains all of the values of self.
+    fn as_interval(&self) -> IntervalDomain {
+        interval_domain::TOP
+    }
+
+    /// Returns Some(true) if self and other are the same single value, Some(false) if they have
+    /// no values in common, and None otherwise.
+    fn equals(&self, _other: &Self) -> Option<bool> {
+        None
+    }
+
+    /// Returns Some(true) if every value of self is less than every value of other,
+    /// Some(false) if no value of self is less than a value of other, and None otherwise.
+    fn less_than(&self, _other: &Self) -> Option<bool> {
+        None
+    }
+
+    /// Returns Some(true) if every value of self is less than or equal to every value of other,
+    /// Some(false) if no value of self is less than or equal to a value of other, and None
+    /// otherwise.
+    fn less_or_equal(&self, _other: &Self) -> Option<bool> {
+        None
+    }
+
+    /// Adds to facts what follows for the operands of value from value being in element.
+    /// Backward transfer functions go here, for example x % 4 == 1 implies that x is odd.
+    fn refine_operands(
+        _value: &AbstractDomain,
+        _element: &Self,
+        _facts: &mut HashMap<AbstractDomain, Self>,
+    ) {
+    }
+
+    /// Adds to facts what follows for value, and for its operands, from value not being equal
+    /// to any value in other.
+    fn refine_operands_of_inequality(
+        _value: &AbstractDomain,
+        _other: &Self,
+        _facts: &mut HashMap<AbstractDomain, Self>,
+    ) {
+    }
+}
+
+/// Adds the domain D to the components of the reduced product, unless it is already one of them.
+/// The congruence and known bits domains are registered by default. The components are kept per
+/// thread, so this must be called on the thread that runs the analysis, for example in the
+/// closure that is passed to rustc_driver::run.
+pub fn register<D: Domain>() {
+    COMPONENTS.with(|components| {
+        let mut components = components.borrow_mut();
+        if components
+            .iter()
+            .all(|component| component.domain_type() != TypeId::of::<D>())
+        {
+            components.push(Rc::new(RegisteredDomain::<D>::default()));
+        }
+    });
+}
+
+/// Removes the domain D from the components of the reduced product.
+pub fn deregister<D: Domain>() {
+    COMPONENTS.with(|components| {
+        components
+            .borrow_mut()
+            .retain(|component| component.domain_type() != TypeId::of::<D>());
+    });
+}
+
+/// Returns the value of condition if one of the components of the reduced product can decide it.
+/// If there is a path condition, the components assume the facts that it implies for the sub
+/// expressions of condition, and do not try to decide condition if it implies no facts.
+pub fn decide(condition: &AbstractDomain, path_condition: Option<&AbstractDomain>) -> Option<bool> {
+    get_components()
+        .iter()
+        .filter_map(|component| component.decide(condition, path_condition))
+        .next()
+}
+
+/// Returns the smallest interval that the components of the reduced product know to contain the
+/// value of expression.
+pub fn get_as_interval(expression: &AbstractDomain) -> IntervalDomain {
+    get_components()
+        .iter()
+        .fold(interval_domain::TOP, |interval, component| {
+            interval.intersect(&component.get_as_interval(expression))
+        })
+}
+
+/// Constructs an element of domain D for the given expression, given elements for sub
+/// expressions that are known to hold, for example because of a path condition.
+pub fn evaluate<D: Domain>(expression: &AbstractDomain, facts: &HashMap<AbstractDomain, D>) -> D {
+    evaluate_with_cache(expression, facts, &mut HashMap::new())
+}
+
+/// Constructs an element of domain D for the given expression, like evaluate, and stores the
+/// elements of its sub expressions in cache. Expressions are interned, so a sub expression that
+/// occurs more than once in the expression is a single node, which is only evaluated once.
+fn evaluate_with_cache<D: Domain>(
+    expression: &AbstractDomain,
+    facts: &HashMap<AbstractDomain, D>,
+    cache: &mut HashMap<*const Expression, D>,
+) -> D {
+    let node: *const Expression = expression.expression;
+    if let Some(element) = cache.get(&node) {
+        return element.clone();
+    }
+    let mut eval = |operand: &AbstractDomain| evaluate_with_cache(operand, facts, cache);
+    let element = match &expression.expression {
+        Expression::Add { left, right } => eval(left).add(&eval(right)),
+        Expression::BitAnd { left, right } => eval(left).bit_and(&eval(right)),
+        Expression::BitOr { left, right } => eval(left).bit_or(&eval(right)),
+        Expression::BitXor { left, right } => eval(left).bit_xor(&eval(right)),
+        Expression::Cast {
+            operand,
+            target_type,
+        } => eval(operand).cast(target_type),
+        Expression::CompileTimeConstant(value) => D::for_constant(value),
+        Expression::ConditionalExpression {
+            consequent,
+            alternate,
+            ..
+        } => eval(consequent).join(&eval(alternate)),
+        Expression::Div { left, right } => eval(left).div(&eval(right)),
+        Expression::Mul { left, right } => eval(left).mul(&eval(right)),
+        Expression::Neg { operand } => eval(operand).neg(),
+        Expression::Rem { left, right } => eval(left).rem(&eval(right)),
+        Expression::Shl {
+            left,
+            right,
+            result_type,
+        } => eval(left).shl(&eval(right), result_type),
+        Expression::Shr {
+            left,
+            right,
+            result_type,
+        } => eval(left).shr(&eval(right), result_type),
+        Expression::Sub { left, right } => eval(left).sub(&eval(right)),
+        _ => D::for_expression(expression),
+    };
+    let element = match facts.get(expression) {
+        Some(fact) => element.meet(fact),
+        None => element,
+    };
+    cache.insert(node, element.clone());
+    element
+}
+
+/// Adds to facts the elements of domain D that sub expressions of condition must be in if
+/// condition evaluates to is_true. Only conjuncts that are equalities or inequalities are
+/// considered.
+pub fn add_facts<D: Domain>(
+    condition: &AbstractDomain,
+    is_true: bool,
+    facts: &mut HashMap<AbstractDomain, D>,
+) {
+    match &condition.expression {
+        Expression::And { left, right } if is_true => {
+            add_facts(left, true, facts);
+            add_facts(right, true, facts);
+        }
+        Expression::Or { left, right } if !is_true => {
+            add_facts(left, false, facts);
+            add_facts(right, false, facts);
+        }
+        Expression::Not { operand } => add_facts(operand, !is_true, facts),
+        Expression::Equals { left, right } => add_equality_facts(left, right, is_true, facts),
+        Expression::Ne { left, right } => add_equality_facts(left, right, !is_true, facts),
+        _ => (),
+    }
+}
+
+/// Adds to facts the elements of domain D that left and right, and their operands, must be in
+/// if (left == right) is is_equal.
+fn add_equality_facts<D: Domain>(
+    left: &AbstractDomain,
+    right: &AbstractDomain,
+    is_equal: bool,
+    facts: &mut HashMap<AbstractDomain, D>,
+) {
+    let left_element = evaluate(left, facts);
+    let right_element = evaluate(right, facts);
+    if is_equal {
+        // Both sides have one of the values that they have in common.
+        let element = left_element.meet(&right_element);
+        if element.is_bottom() || element.is_top() {
+            return;
+        }
+        facts.insert(left.clone(), element.clone());
+        facts.insert(right.clone(), element.clone());
+        D::refine_operands(left, &element, facts);
+        D::refine_operands(right, &element, facts);
+    } else {
+        D::refine_operands_of_inequality(left, &right_element, facts);
+        D::refine_operands_of_inequality(right, &left_element, facts);
+    }
+}
+
+/// Returns the element of domain D for expression, reduced with the interval of expression.
+fn evaluate_and_reduce<D: Domain>(
+    expression: &AbstractDomain,
+    facts: &HashMap<AbstractDomain, D>,
+) -> D {
+    let element = evaluate(expression, facts);
+    if element.is_bottom() || element.is_top() {
+        element
+    } else {
+        element.reduce(&expression.get_as_interval())
+    }
+}
+
+thread_local! {
+    /// The domains that are the components of the reduced product, in the order in which they
+    /// are asked to decide conditions.
+    static COMPONENTS: RefCell<Vec<Rc<dyn Component>>> = RefCell::new(vec![
+        Rc::new(RegisteredDomain::<CongruenceDomain>::default()),
+        Rc::new(RegisteredDomain::<KnownBitsDomain>::default()),
+    ]);
+}
+
+/// Returns a copy of the list of components, so that a component can use the reduced product
+/// while it is being asked to decide a condition.
+fn get_components() -> Vec<Rc<dyn Component>> {
+    COMPONENTS.with(|components| components.borrow().clone())
+}
+
+/// A component of the reduced product, with the type of the elements of its domain erased, so
+/// that domains of different types can be kept in one list.
+trait Component {
+    /// The type of the elements of the domain, which identifies the component.
+    fn domain_type(&self) -> TypeId;
+
+    /// Returns the value of condition if the domain decides it, given that path_condition holds.
+    fn decide(
+        &self,
+        condition: &AbstractDomain,
+        path_condition: Option<&AbstractDomain>,
+    ) -> Option<bool>;
+
+    /// Returns an interval that the domain knows to contain the value of expression.
+    fn get_as_interval(&self, expression: &AbstractDomain) -> IntervalDomain;
+}
+
+struct RegisteredDomain<D: Domain> {
+    phantom: PhantomData<D>,
+}
+
+impl<D: Domain> Default for RegisteredDomain<D> {
+    fn default() -> Self {
+        RegisteredDomain {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D: Domain> Component for RegisteredDomain<D> {
+    fn domain_type(&self) -> TypeId {
+        TypeId::of::<D>()
+    }
+
+    fn decide(
+        &self,
+        condition: &AbstractDomain,
+        path_condition: Option<&AbstractDomain>,
+    ) -> Option<bool> {
+        let mut facts = HashMap::new();
+        if let Some(path_condition) = path_condition {
+            add_facts::<D>(path_condition, true, &mut facts);
+            if facts.is_empty() {
+                return None;
+            }
+        }
+        let compare = |left: &AbstractDomain, right: &AbstractDomain| {
+            (
+                evaluate_and_reduce::<D>(left, &facts),
+                evaluate_and_reduce::<D>(right, &facts),
+            )
+        };
+        match &condition.expression {
+            Expression::Equals { left, right } => {
+                let (left, right) = compare(left, right);
+                left.equals(&right)
+            }
+            Expression::Ne { left, right } => {
+                let (left, right) = compare(left, right);
+                left.equals(&right).map(|result| !result)
+            }
+            Expression::LessThan { left, right } => {
+                let (left, right) = compare(left, right);
+                left.less_than(&right)
+            }
+            Expression::LessOrEqual { left, right } => {
+                let (left, right) = compare(left, right);
+                left.less_or_equal(&right)
+            }
+            Expression::GreaterThan { left, right } => {
+                let (left, right) = compare(left, right);
+                right.less_than(&left)
+            }
+            Expression::GreaterOrEqual { left, right } => {
+                let (left, right) = compare(left, right);
+                right.less_or_equal(&left)
+            }
+            _ => None,
+        }
+    }
+
+    fn get_as_interval(&self, expression: &AbstractDomain) -> IntervalDomain {
+        evaluate::<D>(expression, &HashMap::new()).as_interval()
+    }
+}
+
+impl Domain for CongruenceDomain {
+    fn top() -> Self {
+        congruence_domain::TOP
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.is_bottom()
+    }
+
+    fn is_top(&self) -> bool {
+        self.is_top()
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        self.join(other)
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        self.meet(other)
+    }
+
+    fn subset(&self, other: &Self) -> bool {
+        self.subset(other)
+    }
+
+    fn for_constant(value: &ConstantDomain) -> Self {
+        match value {
+            ConstantDomain::I128(val) => (*val).into(),
+            ConstantDomain::U128(val) => (*val).into(),
+            _ => congruence_domain::BOTTOM,
+        }
+    }
+
+    fn for_expression(expression: &AbstractDomain) -> Self {
+        match &expression.expression {
+            Expression::Top => congruence_domain::TOP,
+            Expression::Variable { var_type, .. } if var_type.is_integer() => {
+                congruence_domain::TOP
+            }
+            Expression::Widen { congruence, .. } => congruence.clone(),
+            _ => congruence_domain::BOTTOM,
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.add(other)
+    }
+
+    fn bit_and(&self, other: &Self) -> Self {
+        self.bit_and(other)
+    }
+
+    fn cast(&self, target_type: &ExpressionType) -> Self {
+        self.cast(target_type)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.mul(other)
+    }
+
+    fn neg(&self) -> Self {
+        self.neg()
+    }
+
+    fn rem(&self, other: &Self) -> Self {
+        self.rem(other)
+    }
+
+    fn shl(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        self.shl(other, result_type)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self.sub(other)
+    }
+
+    // If the congruence has just one element in the interval, the result is that element.
+    fn reduce(&self, interval: &IntervalDomain) -> Self {
+        self.reduce(interval)
+    }
+
+    fn as_interval(&self) -> IntervalDomain {
+        match self.as_constant() {
+            Some(constant) => constant.into(),
+            None => interval_domain::TOP,
+        }
+    }
+
+    fn equals(&self, other: &Self) -> Option<bool> {
+        self.equals(other)
+    }
+
+    // x % m == c implies that x is in m*Z + c and x & (2^n-1) == c implies that x is in
+    // 2^n*Z + c.
+    fn refine_operands(
+        value: &AbstractDomain,
+        element: &Self,
+        facts: &mut HashMap<AbstractDomain, Self>,
+    ) {
+        let constant = match element.as_constant() {
+            Some(constant) => constant,
+            None => return,
+        };
+        let (operand, modulus) = match &value.expression {
+            Expression::Rem { left, right } => match evaluate::<Self>(right, facts).as_constant() {
+                Some(modulus) if modulus != 0 => (left, modulus),
+                _ => return,
+            },
+            Expression::BitAnd { left, right } => {
+                match evaluate::<Self>(right, facts).as_constant() {
+                    Some(mask)
+                        if mask > 0 && mask.checked_add(1).map_or(false, |m| mask & m == 0) =>
+                    {
+                        (left, mask + 1)
+                    }
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+        facts.insert(
+            (**operand).clone(),
+            CongruenceDomain::new(modulus, constant),
+        );
+    }
+}
+
+impl Domain for KnownBitsDomain {
+    fn top() -> Self {
+        known_bits_domain::TOP
+    }
+
+    fn is_bottom(&self) -> bool {
+        self.is_bottom()
+    }
+
+    fn is_top(&self) -> bool {
+        self.is_top()
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        self.join(other)
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        self.meet(other)
+    }
+
+    fn subset(&self, other: &Self) -> bool {
+        self.subset(other)
+    }
+
+    fn for_constant(value: &ConstantDomain) -> Self {
+        match value {
+            ConstantDomain::I128(val) => (*val).into(),
+            ConstantDomain::U128(val) => (*val).into(),
+            _ => known_bits_domain::BOTTOM,
+        }
+    }
+
+    // The bits of expressions that are not bitwise or additive operations come from their
+    // intervals.
+    fn for_expression(expression: &AbstractDomain) -> Self {
+        match &expression.expression {
+            Expression::Top => known_bits_domain::TOP,
+            _ => KnownBitsDomain::from_interval(&expression.get_as_interval()),
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self.add(other)
+    }
+
+    fn bit_and(&self, other: &Self) -> Self {
+        self.bit_and(other)
+    }
+
+    fn bit_or(&self, other: &Self) -> Self {
+        self.bit_or(other)
+    }
+
+    fn bit_xor(&self, other: &Self) -> Self {
+        self.bit_xor(other)
+    }
+
+    fn cast(&self, target_type: &ExpressionType) -> Self {
+        self.cast(target_type)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self.mul(other)
+    }
+
+    fn neg(&self) -> Self {
+        self.neg()
+    }
+
+    fn shl(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        self.shl(other, result_type)
+    }
+
+    fn shr(&self, other: &Self, result_type: &ExpressionType) -> Self {
+        self.shr(other, result_type)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self.sub(other)
+    }
+
+    fn reduce(&self, interval: &IntervalDomain) -> Self {
+        self.meet(&KnownBitsDomain::from_interval(interval))
+    }
+
+    fn as_interval(&self) -> IntervalDomain {
+        self.as_interval()
+    }
+
+    fn equals(&self, other: &Self) -> Option<bool> {
+        self.equals(other)
+    }
+
+    // x & m == c implies that the bits of x that are set in m are the bits of c.
+    fn refine_operands(
+        value: &AbstractDomain,
+        element: &Self,
+        facts: &mut HashMap<AbstractDomain, Self>,
+    ) {
+        if let Some(constant) = element.as_constant() {
+            refine_masked_operand(value, constant, true, facts);
+        }
+    }
+
+    // x & 2^n != 0 implies that bit n of x is one.
+    fn refine_operands_of_inequality(
+        value: &AbstractDomain,
+        other: &Self,
+        facts: &mut HashMap<AbstractDomain, Self>,
+    ) {
+        if let Some(constant) = other.as_constant() {
+            refine_masked_operand(value, constant, false, facts);
+        }
+    }
+}
+
+/// Adds to facts the bits that x must have if (x & m == constant) is is_equal, where value
+/// is x & m and m is known.
+fn refine_masked_operand(
+    value: &AbstractDomain,
+    constant: u128,
+    is_equal: bool,
+    facts: &mut HashMap<AbstractDomain, KnownBitsDomain>,
+) {
+    let (operand, mask) = match &value.expression {
+        Expression::BitAnd { left, right } => {
+            match evaluate::<KnownBitsDomain>(right, facts).as_constant() {
+                Some(mask) => (left, mask),
+                None => return,
+            }
+        }
+        _ => return,
+    };
+    let fact = if is_equal && constant & !mask == 0 {
+        KnownBitsDomain::new(constant, !mask)
+    } else if !is_equal && constant == 0 && mask.is_power_of_two() {
+        KnownBitsDomain::new(mask, !mask)
+    } else {
+        return;
+    };
+    let fact = match facts.get(&**operand) {
+        Some(previous_fact) => previous_fact.meet(&fact),
+        None => fact,
+    };
+    facts.insert((**operand).clone(), fact);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abstract_domains::local_variable;
+
+    /// A domain that only knows whether integers are zero.
+    #[derive(Clone, Debug, PartialEq)]
+    enum Zeroness {
+        Bottom,
+        Zero,
+        NonZero,
+        Top,
+    }
+
+    impl Domain for Zeroness {
+        fn top() -> Self {
+            Zeroness::Top
+        }
+
+        fn is_bottom(&self) -> bool {
+            *self == Zeroness::Bottom
+        }
+
+        fn is_top(&self) -> bool {
+            *self == Zeroness::Top
+        }
+
+        fn join(&self, other: &Self) -> Self {
+            match (self, other) {
+                (Zeroness::Bottom, _) => other.clone(),
+                (_, Zeroness::Bottom) => self.clone(),
+                _ if self == other => self.clone(),
+                _ => Zeroness::Top,
+            }
+        }
+
+        fn meet(&self, other: &Self) -> Self {
+            match (self, other) {
+                (Zeroness::Top, _) => other.clone(),
+                (_, Zeroness::Top) => self.clone(),
+                _ if self == other => self.clone(),
+                _ => Zeroness::Bottom,
+            }
+        }
+
+        fn subset(&self, other: &Self) -> bool {
+            *self == Zeroness::Bottom || *other == Zeroness::Top || self == other
+        }
+
+        fn for_constant(value: &ConstantDomain) -> Self {
+            match value {
+                ConstantDomain::I128(0) | ConstantDomain::U128(0) => Zeroness::Zero,
+                ConstantDomain::I128(..) | ConstantDomain::U128(..) => Zeroness::NonZero,
+                _ => Zeroness::Bottom,
+            }
+        }
+
+        fn mul(&self, other: &Self) -> Self {
+            match (self, other) {
+                (Zeroness::Bottom, _) | (_, Zeroness::Bottom) => Zeroness::Bottom,
+                (Zeroness::Zero, _) | (_, Zeroness::Zero) => Zeroness::Zero,
+                (Zeroness::NonZero, Zeroness::NonZero) => Zeroness::NonZero,
+                _ => Zeroness::Top,
+            }
+        }
+
+        fn equals(&self, other: &Self) -> Option<bool> {
+            match (self, other) {
+                (Zeroness::Zero, Zeroness::Zero) => Some(true),
+                (Zeroness::Zero, Zeroness::NonZero) | (Zeroness::NonZero, Zeroness::Zero) => {
+                    Some(false)
+                }
+                _ => None,
+            }
+        }
+
+        // x != 0 implies that x is not zero.
+        fn refine_operands_of_inequality(
+            value: &AbstractDomain,
+            other: &Self,
+            facts: &mut HashMap<AbstractDomain, Self>,
+        ) {
+            if *other == Zeroness::Zero {
+                facts.insert(value.clone(), Zeroness::NonZero);
+            }
+        }
+    }
+
+    fn is_not_zero(operand: &AbstractDomain) -> AbstractDomain {
+        Expression::Ne {
+            left: box operand.clone(),
+            right: box Expression::CompileTimeConstant(ConstantDomain::I128(0)).into(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn registered_domain_decides_conditions() {
+        let (x, y) = (local_variable(1), local_variable(2));
+        let x_times_y: AbstractDomain = Expression::Mul {
+            left: box x.clone(),
+            right: box y.clone(),
+        }
+        .into();
+        let path_condition: AbstractDomain = Expression::And {
+            left: box is_not_zero(&x),
+            right: box is_not_zero(&y),
+        }
+        .into();
+        let condition = is_not_zero(&x_times_y);
+
+        // The default components know nothing about products.
+        assert!(!path_condition.implies(&condition));
+
+        register::<Zeroness>();
+        assert!(path_condition.implies(&condition));
+        assert_eq!(decide(&condition, Some(&path_condition)), Some(true));
+        // Without the path condition, the domain knows nothing about x and y.
+        assert_eq!(decide(&condition, None), None);
+
+        deregister::<Zeroness>();
+        assert!(!path_condition.implies(&condition));
+    }
+}
@@ -0,0 +1,681 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+use crate::abstract_domains::AbstractDomain;
+use crate::constant_domain::ConstantDomain;
+use crate::expression::Expression;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem::discriminant;
+
+// The constructors of AbstractDomain only simplify an operation when its operands are known,
+// so the expressions that are built up while analyzing a function body, and particularly the
+// preconditions that are promoted from callees, tend to grow into deeply nested trees of
+// conditional expressions and disjunctions. This module rewrites such trees into a normal form:
+// - the operands of commutative operators are put into a canonical order, and > and >= become
+//   < and <= with their operands swapped;
+// - chains of associative operators are flattened, so that their constant operands can be
+//   folded together and duplicate operands of idempotent operators can be removed;
+// - conjunctions and disjunctions absorb their duplicate and absorbed operands, and are false
+//   or true if they contain an operand as well as its negation;
+// - negations are pushed into conjunctions, disjunctions and comparisons using De Morgan's laws;
+// - sums and differences of integers become sums of terms multiplied by constants, in which
+//   like terms are combined, and comparisons of integers get the terms with positive
+//   coefficients on one side and the terms with negative coefficients on the other side.
+// The result is rebuilt with the constructors of AbstractDomain, so that their simplifications
+// apply to the normalized operands.
+
+/// Returns an expression that has the same values as the given expression, but which is in the
+/// normal form described above. Expressions that are equivalent because of the rules of the
+/// normal form become the same expression.
+pub fn simplify(value: &AbstractDomain) -> AbstractDomain {
+    match &value.expression {
+        Expression::Add { .. } | Expression::Neg { .. } | Expression::Sub { .. }
+            if is_integer(value) =>
+        {
+            simplify_sum(value)
+        }
+        Expression::And { .. } => simplify_junction(value, true),
+        Expression::BitAnd { .. }
+        | Expression::BitOr { .. }
+        | Expression::BitXor { .. }
+        | Expression::Mul { .. } => simplify_chain(value),
+        Expression::ConditionalExpression {
+            condition,
+            consequent,
+            alternate,
+        } => simplify_conditional(
+            &simplify(condition),
+            &simplify(consequent),
+            &simplify(alternate),
+        ),
+        Expression::Equals { left, right } => {
+            simplify_comparison(Comparison::Equals, &simplify(left), &simplify(right))
+        }
+        Expression::GreaterOrEqual { left, right } => {
+            simplify_comparison(Comparison::LessOrEqual, &simplify(right), &simplify(left))
+        }
+        Expression::GreaterThan { left, right } => {
+            simplify_comparison(Comparison::LessThan, &simplify(right), &simplify(left))
+        }
+        Expression::LessOrEqual { left, right } => {
+            simplify_comparison(Comparison::LessOrEqual, &simplify(left), &simplify(right))
+        }
+        Expression::LessThan { left, right } => {
+            simplify_comparison(Comparison::LessThan, &simplify(left), &simplify(right))
+        }
+        Expression::Ne { left, right } => {
+            simplify_comparison(Comparison::Ne, &simplify(left), &simplify(right))
+        }
+        Expression::Not { operand } => negate(&simplify(operand)),
+        Expression::Or { .. } => simplify_junction(value, false),
+        _ => simplify_operands(value),
+    }
+}
+
+/// Rebuilds value from its simplified operands, without rewriting value itself, other than by
+/// the simplifications of the AbstractDomain constructors.
+fn simplify_operands(value: &AbstractDomain) -> AbstractDomain {
+    match &value.expression {
+        Expression::Top
+        | Expression::Bottom
+        | Expression::AbstractHeapAddress(..)
+        | Expression::CompileTimeConstant(..)
+        | Expression::Reference(..)
+        | Expression::Variable { .. }
+        | Expression::Widen { .. } => value.clone(),
+        Expression::Add { left, right } => simplify(left).add(&simplify(right)),
+        Expression::AddOverflows {
+            left,
+            right,
+            result_type,
+        } => simplify(left).add_overflows(&mut simplify(right), result_type.clone()),
+        Expression::And { left, right } => simplify(left).and(&simplify(right)),
+        Expression::BitAnd { left, right } => simplify(left).bit_and(&simplify(right)),
+        Expression::BitOr { left, right } => simplify(left).bit_or(&simplify(right)),
+        Expression::BitXor { left, right } => simplify(left).bit_xor(&simplify(right)),
+        Expression::Cast {
+            operand,
+            target_type,
+        } => simplify(operand).cast(target_type.clone()),
+        Expression::ConditionalExpression {
+            condition,
+            consequent,
+            alternate,
+        } => simplify(consequent).join(&simplify(alternate), &simplify(condition)),
+        Expression::Div { left, right } => simplify(left).div(&simplify(right)),
+        Expression::Equals { left, right } => simplify(left).equals(&simplify(right)),
+        Expression::GreaterOrEqual { left, right } => {
+            simplify(left).greater_or_equal(&mut simplify(right))
+        }
+        Expression::GreaterThan { left, right } => {
+            simplify(left).greater_than(&mut simplify(right))
+        }
+        Expression::LessOrEqual { left, right } => {
+            simplify(left).less_or_equal(&mut simplify(right))
+        }
+        Expression::LessThan { left, right } => simplify(left).less_than(&mut simplify(right)),
+        Expression::Mul { left, right } => simplify(left).mul(&simplify(right)),
+        Expression::MulOverflows {
+            left,
+            right,
+            result_type,
+        } => simplify(left).mul_overflows(&mut simplify(right), result_type.clone()),
+        Expression::Ne { left, right } => simplify(left).not_equals(&simplify(right)),
+        Expression::Neg { operand } => simplify(operand).neg(),
+        Expression::Not { operand } => simplify(operand).not(),
+        Expression::Offset { left, right } => simplify(left).offset(&simplify(right)),
+        Expression::Or { left, right } => simplify(left).or(&simplify(right)),
+        Expression::Rem { left, right } => simplify(left).rem(&simplify(right)),
+        Expression::Shl {
+            left,
+            right,
+            result_type,
+        } => simplify(left).shl(&simplify(right), result_type.clone()),
+        Expression::ShlOverflows {
+            left,
+            right,
+            result_type,
+        } => simplify(left).shl_overflows(&mut simplify(right), result_type.clone()),
+        Expression::Shr {
+            left,
+            right,
+            result_type,
+        } => simplify(left).shr(&simplify(right), result_type.clone()),
+        Expression::ShrOverflows {
+            left,
+            right,
+            result_type,
+        } => simplify(left).shr_overflows(&mut simplify(right), result_type.clone()),
+        Expression::StrSlice {
+            operand,
+            start,
+            end,
+        } => simplify(operand).str_slice(&simplify(start), &simplify(end)),
+        Expression::Sub { left, right } => simplify(left).sub(&simplify(right)),
+        Expression::SubOverflows {
+            left,
+            right,
+            result_type,
+        } => simplify(left).sub_overflows(&mut simplify(right), result_type.clone()),
+    }
+}
+
+/// True if value is known to be an integer, so that the laws of integer arithmetic apply to it.
+/// Floating point arithmetic is not associative, and x == x is not true if x is NaN.
+fn is_integer(value: &AbstractDomain) -> bool {
+    match &value.expression {
+        Expression::Add { left, right }
+        | Expression::BitAnd { left, right }
+        | Expression::BitOr { left, right }
+        | Expression::BitXor { left, right }
+        | Expression::Div { left, right }
+        | Expression::Mul { left, right }
+        | Expression::Rem { left, right }
+        | Expression::Sub { left, right } => is_integer(left) && is_integer(right),
+        Expression::Cast { target_type, .. } => target_type.is_integer(),
+        Expression::CompileTimeConstant(ConstantDomain::I128(..))
+        | Expression::CompileTimeConstant(ConstantDomain::U128(..)) => true,
+        Expression::ConditionalExpression {
+            consequent,
+            alternate,
+            ..
+        } => is_integer(consequent) && is_integer(alternate),
+        Expression::Neg { operand } => is_integer(operand),
+        Expression::Shl { result_type, .. } | Expression::Shr { result_type, .. } => {
+            result_type.is_integer()
+        }
+        Expression::Variable { var_type, .. } => var_type.is_integer(),
+        Expression::Widen { .. } => true,
+        _ => false,
+    }
+}
+
+/// A sum of terms that are multiplied by coefficients, plus a constant. The constants of an
+/// integer expression are either all signed or all unsigned, depending on its type.
+#[derive(Default)]
+struct LinearSum {
+    terms: BTreeMap<AbstractDomain, i128>,
+    constant: i128,
+    is_signed: Option<bool>,
+}
+
+impl LinearSum {
+    /// Adds value multiplied by coefficient to self, unless a coefficient or the constant
+    /// overflows, or value mixes signed and unsigned constants.
+    fn add(&mut self, value: &AbstractDomain, coefficient: i128) -> Option<()> {
+        match &value.expression {
+            Expression::Add { left, right } => {
+                self.add(left, coefficient)?;
+                self.add(right, coefficient)
+            }
+            Expression::CompileTimeConstant(ConstantDomain::I128(val)) => {
+                self.add_constant(*val, true, coefficient)
+            }
+            Expression::CompileTimeConstant(ConstantDomain::U128(val)) if *val as i128 >= 0 => {
+                self.add_constant(*val as i128, false, coefficient)
+            }
+            Expression::Mul { left, right } => match (&left.expression, &right.expression) {
+                (_, Expression::CompileTimeConstant(..)) => {
+                    let factor = self.get_factor(right)?;
+                    self.add(left, coefficient.checked_mul(factor)?)
+                }
+                (Expression::CompileTimeConstant(..), _) => {
+                    let factor = self.get_factor(left)?;
+                    self.add(right, coefficient.checked_mul(factor)?)
+                }
+                _ => self.add_term(value, coefficient),
+            },
+            Expression::Neg { operand } => self.add(operand, coefficient.checked_neg()?),
+            Expression::Sub { left, right } => {
+                self.add(left, coefficient)?;
+                self.add(right, coefficient.checked_neg()?)
+            }
+            _ => self.add_term(value, coefficient),
+        }
+    }
+
+    fn add_constant(&mut self, value: i128, is_signed: bool, coefficient: i128) -> Option<()> {
+        if *self.is_signed.get_or_insert(is_signed) != is_signed {
+            return None;
+        }
+        self.constant = self.constant.checked_add(value.checked_mul(coefficient)?)?;
+        Some(())
+    }
+
+    fn add_term(&mut self, value: &AbstractDomain, coefficient: i128) -> Option<()> {
+        let sum = self.terms.get(value).cloned().unwrap_or(0);
+        let sum = sum.checked_add(coefficient)?;
+        if sum == 0 {
+            self.terms.remove(value);
+        } else {
+            self.terms.insert(value.clone(), sum);
+        }
+        Some(())
+    }
+
+    /// Returns the value of a constant factor of a term, if it has the same signedness as the
+    /// other constants of self.
+    fn get_factor(&mut self, factor: &AbstractDomain) -> Option<i128> {
+        let (value, is_signed) = match &factor.expression {
+            Expression::CompileTimeConstant(ConstantDomain::I128(val)) => (*val, true),
+            Expression::CompileTimeConstant(ConstantDomain::U128(val)) if *val as i128 >= 0 => {
+                (*val as i128, false)
+            }
+            _ => return None,
+        };
+        if *self.is_signed.get_or_insert(is_signed) != is_signed {
+            return None;
+        }
+        Some(value)
+    }
+
+    /// Returns an expression for constant, which must not be negative if self is unsigned.
+    fn get_constant(&self, constant: i128) -> Option<AbstractDomain> {
+        match self.is_signed {
+            Some(true) => Some(ConstantDomain::I128(constant).into()),
+            Some(false) if constant >= 0 => Some(ConstantDomain::U128(constant as u128).into()),
+            _ => None,
+        }
+    }
+
+    /// Returns the sum of the terms whose coefficients have the given sign, with the magnitudes
+    /// of their coefficients, plus constant. The result is None if a constant cannot be
+    /// expressed, because there are no constants to get its type from.
+    fn get_sum_of_terms(&self, positive: bool, constant: i128) -> Option<Option<AbstractDomain>> {
+        let mut sum: Option<AbstractDomain> = None;
+        for (term, coefficient) in self.terms.iter() {
+            if (*coefficient > 0) != positive {
+                continue;
+            }
+            let term = term.clone();
+            let magnitude = coefficient.checked_abs()?;
+            let term = if magnitude == 1 {
+                term
+            } else {
+                term.mul(&self.get_constant(magnitude)?)
+            };
+            sum = Some(match sum {
+                Some(sum) => sum.add(&term),
+                None => term,
+            });
+        }
+        if constant != 0 {
+            let constant = self.get_constant(constant)?;
+            sum = Some(match sum {
+                Some(sum) => sum.add(&constant),
+                None => constant,
+            });
+        }
+        Some(sum)
+    }
+
+    /// Returns an expression for self, with the terms with negative coefficients subtracted from
+    /// the terms with positive coefficients.
+    fn to_expression(&self) -> Option<AbstractDomain> {
+        let positive_constant = self.constant.max(0);
+        let negative_constant = self.constant.min(0).checked_neg()?;
+        let positive_sum = self.get_sum_of_terms(true, positive_constant)?;
+        let negative_sum = self.get_sum_of_terms(false, negative_constant)?;
+        match (positive_sum, negative_sum) {
+            (Some(positive_sum), Some(negative_sum)) => Some(positive_sum.sub(&negative_sum)),
+            (Some(positive_sum), None) => Some(positive_sum),
+            (None, Some(negative_sum)) => match self.get_constant(0) {
+                Some(zero) => Some(zero.sub(&negative_sum)),
+                None => Some(negative_sum.neg()),
+            },
+            (None, None) => self.get_constant(0),
+        }
+    }
+
+    /// Returns expressions for the two sides of a comparison of self with zero, with the terms
+    /// and constants with positive coefficients on the left and those with negative coefficients
+    /// on the right.
+    fn to_sides(&self) -> Option<(AbstractDomain, AbstractDomain)> {
+        let positive_constant = self.constant.max(0);
+        let negative_constant = self.constant.min(0).checked_neg()?;
+        let left = self.get_sum_of_terms(true, positive_constant)?;
+        let right = self.get_sum_of_terms(false, negative_constant)?;
+        let left = left.or_else(|| self.get_constant(0))?;
+        let right = right.or_else(|| self.get_constant(0))?;
+        Some((left, right))
+    }
+}
+
+/// Rewrites a sum or difference of integers, into a sum of terms multiplied by constants, in
+/// which like terms are combined and constants are folded together.
+fn simplify_sum(value: &AbstractDomain) -> AbstractDomain {
+    let simplified_value = simplify_operands(value);
+    let mut sum = LinearSum::default();
+    sum.add(&simplified_value, 1)
+        .and_then(|_| sum.to_expression())
+        .unwrap_or(simplified_value)
+}
+
+/// Rewrites a chain of applications of one of the associative and commutative operators *, &, |
+/// and ^, so that its constant operands are folded together and its other operands are in a
+/// canonical order. The duplicate operands of & and | are removed.
+fn simplify_chain(value: &AbstractDomain) -> AbstractDomain {
+    let rebuild = |left: &AbstractDomain, right: &AbstractDomain| match &value.expression {
+        Expression::BitAnd { .. } => left.bit_and(right),
+        Expression::BitOr { .. } => left.bit_or(right),
+        Expression::BitXor { .. } => left.bit_xor(right),
+        _ => left.mul(right),
+    };
+    if !is_integer(value) {
+        // Floating point multiplication is commutative, but not associative.
+        if let Expression::Mul { left, right } = &value.expression {
+            let (left, right) = order(simplify(left), simplify(right));
+            return rebuild(&left, &right);
+        }
+        return simplify_operands(value);
+    }
+    let mut operands = Vec::new();
+    collect_chain(value, &value.expression, true, &mut operands);
+    // Fold the constants together.
+    let mut constant: Option<ConstantDomain> = None;
+    let mut others = Vec::new();
+    for operand in operands.into_iter() {
+        match operand.expression {
+            Expression::CompileTimeConstant(c) => {
+                constant = match constant {
+                    Some(folded) => match rebuild(&folded.into(), &c.clone().into()).expression {
+                        Expression::CompileTimeConstant(folded) => Some(folded.clone()),
+                        _ => return simplify_operands(value),
+                    },
+                    None => Some(c.clone()),
+                };
+            }
+            _ => others.push(operand),
+        }
+    }
+    let is_idempotent = match &value.expression {
+        Expression::BitAnd { .. } | Expression::BitOr { .. } => true,
+        _ => false,
+    };
+    others.sort();
+    if is_idempotent {
+        others.dedup();
+    }
+    if let Some(constant) = constant {
+        let is_zero = match constant {
+            ConstantDomain::I128(0) | ConstantDomain::U128(0) => true,
+            _ => false,
+        };
+        let is_one = match constant {
+            ConstantDomain::I128(1) | ConstantDomain::U128(1) => true,
+            _ => false,
+        };
+        let constant: AbstractDomain = constant.into();
+        match &value.expression {
+            // x & 0 and x * 0 are just 0.
+            Expression::BitAnd { .. } | Expression::Mul { .. } if is_zero => return constant,
+            _ if others.is_empty() => return constant,
+            // x | 0, x ^ 0 and x * 1 are just x.
+            Expression::BitOr { .. } | Expression::BitXor { .. } if is_zero => (),
+            Expression::Mul { .. } if is_one => (),
+            _ => others.push(constant),
+        }
+    }
+    let mut operands = others.iter();
+    let first = operands.next().cloned().unwrap_or_else(|| value.clone());
+    operands.fold(first, |chain, operand| rebuild(&chain, operand))
+}
+
+/// Adds the operands of the chain of applications of the operator of kind that starts at value
+/// to operands. If simplify_operands is true, the operands are simplified, and operands that
+/// simplify into chains of the same operator are flattened as well.
+fn collect_chain(
+    value: &AbstractDomain,
+    kind: &Expression,
+    simplify_operands: bool,
+    operands: &mut Vec<AbstractDomain>,
+) {
+    match (&value.expression, kind) {
+        (Expression::BitAnd { left, right }, Expression::BitAnd { .. })
+        | (Expression::BitOr { left, right }, Expression::BitOr { .. })
+        | (Expression::BitXor { left, right }, Expression::BitXor { .. })
+        | (Expression::Mul { left, right }, Expression::Mul { .. }) => {
+            collect_chain(left, kind, simplify_operands, operands);
+            collect_chain(right, kind, simplify_operands, operands);
+        }
+        _ if simplify_operands => {
+            let operand = simplify(value);
+            if discriminant(operand.expression) == discriminant(kind) {
+                collect_chain(&operand, kind, false, operands);
+            } else {
+                operands.push(operand);
+            }
+        }
+        _ => operands.push(value.clone()),
+    }
+}
+
+/// Returns left and right in canonical order, which puts a constant on the right.
+fn order(left: AbstractDomain, right: AbstractDomain) -> (AbstractDomain, AbstractDomain) {
+    let is_constant = |operand: &AbstractDomain| match operand.expression {
+        Expression::CompileTimeConstant(..) => true,
+        _ => false,
+    };
+    let is_swapped = match (is_constant(&left), is_constant(&right)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => right < left,
+    };
+    if is_swapped {
+        (right, left)
+    } else {
+        (left, right)
+    }
+}
+
+/// The comparisons that remain after > and >= are rewritten into < and <=.
+enum Comparison {
+    Equals,
+    LessOrEqual,
+    LessThan,
+    Ne,
+}
+
+/// Rewrites the comparison of left and right, which are simplified.
+fn simplify_comparison(
+    comparison: Comparison,
+    left: &AbstractDomain,
+    right: &AbstractDomain,
+) -> AbstractDomain {
+    let (mut left, mut right) = (left.clone(), right.clone());
+    if is_integer(&left) && is_integer(&right) {
+        // Compare the difference of left and right with 0.
+        let mut difference = LinearSum::default();
+        let sides = difference
+            .add(&left, 1)
+            .and_then(|_| difference.add(&right, -1))
+            .and_then(|_| difference.to_sides());
+        if let Some((l, r)) = sides {
+            left = l;
+            right = r;
+        }
+    }
+    match comparison {
+        Comparison::Equals => {
+            let (left, right) = order(left, right);
+            left.equals(&right)
+        }
+        Comparison::LessOrEqual => left.less_or_equal(&mut right),
+        Comparison::LessThan => left.less_than(&mut right),
+        Comparison::Ne => {
+            let (left, right) = order(left, right);
+            left.not_equals(&right)
+        }
+    }
+}
+
+/// Returns the negation of value, which is simplified, pushing the negation into conjunctions,
+/// disjunctions and comparisons.
+fn negate(value: &AbstractDomain) -> AbstractDomain {
+    match &value.expression {
+        Expression::And { .. } | Expression::Or { .. } => {
+            // !(x && y) is !x || !y and !(x || y) is !x && !y.
+            let is_and = if let Expression::And { .. } = value.expression {
+                true
+            } else {
+                false
+            };
+            let mut operands = Vec::new();
+            collect_junction(value, is_and, false, &mut operands);
+            let negated_operands = operands.iter().map(negate).collect();
+            combine_junction(negated_operands, !is_and)
+        }
+        Expression::Equals { left, right } => left.not_equals(right),
+        Expression::Ne { left, right } => left.equals(right),
+        // For integers, !(x < y) is y <= x and !(x <= y) is y < x. This is not true for floating
+        // point numbers, since all comparisons with NaN are false.
+        Expression::LessOrEqual { left, right } if is_integer(left) && is_integer(right) => {
+            (**right).clone().less_than(&mut (**left).clone())
+        }
+        Expression::LessThan { left, right } if is_integer(left) && is_integer(right) => {
+            (**right).clone().less_or_equal(&mut (**left).clone())
+        }
+        _ => value.not(),
+    }
+}
+
+/// Rewrites a conjunction, if is_and, or a disjunction of operands.
+fn simplify_junction(value: &AbstractDomain, is_and: bool) -> AbstractDomain {
+    let mut operands = Vec::new();
+    collect_junction(value, is_and, true, &mut operands);
+    combine_junction(operands, is_and)
+}
+
+/// Adds the operands of the chain of && operators, if is_and, or || operators, that starts at
+/// value to operands. If simplify_operands is true, the operands are simplified, and operands
+/// that simplify into chains of the same operator are flattened as well.
+fn collect_junction(
+    value: &AbstractDomain,
+    is_and: bool,
+    simplify_operands: bool,
+    operands: &mut Vec<AbstractDomain>,
+) {
+    match &value.expression {
+        Expression::And { left, right } if is_and => {
+            collect_junction(left, is_and, simplify_operands, operands);
+            collect_junction(right, is_and, simplify_operands, operands);
+        }
+        Expression::Or { left, right } if !is_and => {
+            collect_junction(left, is_and, simplify_operands, operands);
+            collect_junction(right, is_and, simplify_operands, operands);
+        }
+        _ if simplify_operands => {
+            let operand = simplify(value);
+            match operand.expression {
+                Expression::And { .. } if is_and => {
+                    collect_junction(&operand, is_and, false, operands)
+                }
+                Expression::Or { .. } if !is_and => {
+                    collect_junction(&operand, is_and, false, operands)
+                }
+                _ => operands.push(operand),
+            }
+        }
+        _ => operands.push(value.clone()),
+    }
+}
+
+/// Returns the conjunction, if is_and, or the disjunction of the given simplified operands.
+/// - x && true is x, x && false is false, x || false is x and x || true is true;
+/// - x && x is x and x || x is x;
+/// - x && !x is false and x || !x is true;
+/// - x && (x || y) is x and x || (x && y) is x.
+fn combine_junction(operands: Vec<AbstractDomain>, is_and: bool) -> AbstractDomain {
+    let mut set = BTreeSet::new();
+    for operand in operands.into_iter() {
+        match operand.as_bool_if_known() {
+            Some(b) if b == is_and => continue,
+            Some(_) => return operand,
+            None => {
+                set.insert(operand);
+            }
+        }
+    }
+    let is_contradiction = set.iter().any(|operand| match &operand.expression {
+        Expression::And { .. } | Expression::Or { .. } => false,
+        _ => set.contains(&negate(operand)),
+    });
+    if is_contradiction {
+        return (!is_and).into();
+    }
+    let absorbed: Vec<AbstractDomain> = set
+        .iter()
+        .filter(|operand| {
+            let mut sub_operands = Vec::new();
+            match &operand.expression {
+                Expression::Or { .. } if is_and => {
+                    collect_junction(operand, false, false, &mut sub_operands)
+                }
+                Expression::And { .. } if !is_and => {
+                    collect_junction(operand, true, false, &mut sub_operands)
+                }
+                _ => return false,
+            }
+            sub_operands
+                .iter()
+                .any(|sub_operand| set.contains(sub_operand))
+        })
+        .cloned()
+        .collect();
+    for operand in absorbed.iter() {
+        set.remove(operand);
+    }
+    let mut operands = set.into_iter();
+    match operands.next() {
+        Some(first) => operands.fold(first, |junction, operand| {
+            if is_and {
+                junction.and(&operand)
+            } else {
+                junction.or(&operand)
+            }
+        }),
+        None => is_and.into(),
+    }
+}
+
+/// Rewrites condition ? consequent : alternate, where the operands are simplified.
+/// - !c ? x : y is c ? y : x;
+/// - c ? (c ? x : y) : z is c ? x : z and c ? x : (c ? y : z) is c ? x : z;
+/// - c ? true : y is c || y, c ? false : y is !c && y, c ? x : true is !c || x and
+///   c ? x : false is c && x.
+fn simplify_conditional(
+    condition: &AbstractDomain,
+    consequent: &AbstractDomain,
+    alternate: &AbstractDomain,
+) -> AbstractDomain {
+    if let Expression::Not { operand } = &condition.expression {
+        return simplify_conditional(operand, alternate, consequent);
+    }
+    let consequent = match &consequent.expression {
+        Expression::ConditionalExpression {
+            condition: c,
+            consequent,
+            ..
+        } if **c == *condition => consequent.as_ref(),
+        _ => consequent,
+    };
+    let alternate = match &alternate.expression {
+        Expression::ConditionalExpression {
+            condition: c,
+            alternate,
+            ..
+        } if **c == *condition => alternate.as_ref(),
+        _ => alternate,
+    };
+    match (consequent.as_bool_if_known(), alternate.as_bool_if_known()) {
+        (Some(true), _) => combine_junction(vec![condition.clone(), alternate.clone()], false),
+        (Some(false), _) => combine_junction(vec![negate(condition), alternate.clone()], true),
+        (_, Some(true)) => combine_junction(vec![negate(condition), consequent.clone()], false),
+        (_, Some(false)) => combine_junction(vec![condition.clone(), consequent.clone()], true),
+        _ => consequent.join(alternate, condition),
+    }
+}
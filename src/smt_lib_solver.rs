@@ -0,0 +1,1012 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::abstract_domains::AbstractDomain;
+use crate::abstract_value::Path;
+use crate::constant_domain::ConstantDomain;
+use crate::expression::{Expression, ExpressionType};
+use crate::interval_domain::IntervalDomain;
+use crate::k_limits;
+use crate::smt_solver::{SmtResult, SmtSolver};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// An implementation of SmtSolver that translates MIRAI expressions into SMT-LIB2 text and
+/// sends them to a solver executable (such as z3 or cvc4) that runs in a separate process and
+/// reads commands from its standard input. Yices is not supported, since it accepts neither the
+/// ALL logic nor the conversions between integers and bit vectors that the encoding relies on.
+///
+/// Since the solver only sees text, it does not need to be linked into MIRAI. The text can also be
+/// copied into a transcript file, so that the same queries can be replayed with other solvers
+/// and their answers compared.
+///
+/// The encoding is the same as the one used by the Z3 backend: integral values are mathematical
+/// integers that are constrained to the ranges of their types, bitwise operations go via bit
+/// vectors that are wide enough for any Rust integer, overflow checks use bit vectors that are
+/// exactly as wide as the type of the result and values that cannot be modeled precisely become
+/// fresh, unconstrained constants.
+///
+/// A solver process that does not respond within k_limits::SMT_SOLVER_PROCESS_TIMEOUT_MILLISECONDS
+/// is killed, after which all queries are Undefined.
+pub struct SmtLibSolver {
+    /// The solver process. Kept so that it can be shut down when the solver is dropped.
+    solver_process: Child,
+    /// The standard input of the solver process, to which commands are written.
+    solver_input: ChildStdin,
+    /// The lines written to the standard output of the solver process, which are read by a
+    /// separate thread, so that waiting for a response can time out.
+    solver_output: Receiver<String>,
+    /// The sorts of all of the constants that have been declared so far, keyed by name.
+    declared_constants: HashMap<String, &'static str>,
+    /// The number of fresh constants that have been declared so far.
+    fresh_constant_count: usize,
+    /// The variables (and their types and symbols) that appear in terms that have been
+    /// translated, but not yet asserted.
+    translated_variables: Vec<(Path, ExpressionType, String)>,
+    /// Constraints that limit the widened values that appear in terms that have been translated,
+    /// but not yet asserted, to their intervals.
+    interval_constraints: Vec<String>,
+    /// The variables that appear in the assertions of each of the nested contexts of the solver.
+    /// The last element corresponds to the current context.
+    asserted_variables: Vec<Vec<(Path, ExpressionType, String)>>,
+    /// If not None, a file that receives a copy of everything sent to and received from the solver.
+    transcript: Option<File>,
+    /// True if communication with the solver process has failed. All queries are then Undefined.
+    is_broken: bool,
+}
+
+/// The number of bits in the bit vectors used to model bitwise operations. This is one more than
+/// the width of the widest integer type, so that every value of every integer type, signed or
+/// unsigned, has the same two's complement representation in a bit vector of this width.
+const BIT_VECTOR_WIDTH: u32 = 129;
+
+/// Constructors
+impl SmtLibSolver {
+    /// Starts the solver executable found at solver_path and prepares it to receive queries.
+    /// If transcript_path is provided, all communication with the solver is logged to that file.
+    pub fn new(
+        solver_path: &path::Path,
+        transcript_path: Option<&path::Path>,
+    ) -> io::Result<SmtLibSolver> {
+        if Self::get_solver_name(solver_path).starts_with("yices") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "yices is not supported, since it does not accept the ALL logic, int2bv and bv2nat",
+            ));
+        }
+        let mut solver_process = Command::new(solver_path)
+            .args(Self::get_arguments_for(solver_path))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let solver_input = solver_process.stdin.take().unwrap();
+        let process_output = BufReader::new(solver_process.stdout.take().unwrap());
+        let (sender, solver_output) = mpsc::channel();
+        thread::spawn(move || {
+            // Ends when the solver process closes its output, or when the solver is dropped.
+            for line in process_output.lines() {
+                match line {
+                    Ok(line) if sender.send(line).is_ok() => (),
+                    _ => break,
+                }
+            }
+        });
+        let transcript = match transcript_path {
+            Some(path) => Some(File::create(path)?),
+            None => None,
+        };
+        let mut solver = SmtLibSolver {
+            solver_process,
+            solver_input,
+            solver_output,
+            declared_constants: HashMap::new(),
+            fresh_constant_count: 0,
+            translated_variables: Vec::new(),
+            interval_constraints: Vec::new(),
+            asserted_variables: vec![Vec::new()],
+            transcript,
+            is_broken: false,
+        };
+        // Declarations made inside a push/pop pair must survive the pop, since the
+        // constants they declare are cached by this object and may be used again later.
+        solver.send("(set-option :global-declarations true)");
+        // Counterexamples are obtained with get-value, which needs models to be enabled before
+        // the logic is set.
+        solver.send("(set-option :produce-models true)");
+        solver.send("(set-logic ALL)");
+        Ok(solver)
+    }
+
+    /// Returns the name of the solver executable, without its extension.
+    fn get_solver_name(solver_path: &path::Path) -> &str {
+        solver_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("")
+    }
+
+    /// Returns the command line arguments that put the well known solvers into incremental
+    /// SMT-LIB2 mode, reading from standard input and enforcing the solver time limit.
+    /// Other solvers get no arguments and are limited only by the time that receive waits for
+    /// their responses.
+    fn get_arguments_for(solver_path: &path::Path) -> Vec<String> {
+        let timeout = k_limits::SMT_SOLVER_TIMEOUT_MILLISECONDS;
+        let solver_name = Self::get_solver_name(solver_path);
+        if solver_name.starts_with("z3") {
+            vec![
+                String::from("-in"),
+                String::from("-smt2"),
+                format!("-t:{}", timeout),
+            ]
+        } else if solver_name.starts_with("cvc4") {
+            vec![
+                String::from("--lang=smt2"),
+                String::from("--incremental"),
+                format!("--tlimit-per={}", timeout),
+            ]
+        } else {
+            vec![]
+        }
+    }
+}
+
+impl Drop for SmtLibSolver {
+    fn drop(&mut self) {
+        self.send("(exit)");
+        // The process may be stuck on a query, so do not wait for it to exit by itself.
+        // Killing a process that has already exited does no harm.
+        let _ = self.solver_process.kill();
+        let _ = self.solver_process.wait();
+    }
+}
+
+impl SmtSolver<String> for SmtLibSolver {
+    fn as_debug_string(&self, expression: &String) -> String {
+        expression.clone()
+    }
+
+    fn assert(&mut self, expression: &String) {
+        let mut translated_variables =
+            std::mem::replace(&mut self.translated_variables, Vec::new());
+        let mut range_constraints: Vec<String> = translated_variables
+            .iter()
+            .filter(|(_, var_type, _)| var_type.is_integer())
+            .map(|(_, var_type, symbol)| Self::get_range_constraint(symbol, var_type))
+            .collect();
+        range_constraints.append(&mut self.interval_constraints);
+        range_constraints.sort();
+        range_constraints.dedup();
+        for range_constraint in range_constraints {
+            self.send(&format!("(assert {})", range_constraint));
+        }
+        if let Some(variables) = self.asserted_variables.last_mut() {
+            variables.append(&mut translated_variables);
+        }
+        self.send(&format!("(assert {})", expression));
+    }
+
+    fn backtrack(&mut self) {
+        if self.asserted_variables.len() > 1 {
+            self.asserted_variables.pop();
+        }
+        self.send("(pop 1)");
+    }
+
+    fn get_as_smt_predicate(&mut self, mirai_expression: &Expression) -> String {
+        self.get_as_bool_term(mirai_expression)
+    }
+
+    fn get_model(&mut self) -> Vec<(Path, ConstantDomain)> {
+        let mut variables: Vec<(Path, ExpressionType, String)> = self
+            .asserted_variables
+            .iter()
+            .flat_map(|vars| vars.iter().cloned())
+            .collect();
+        variables.sort();
+        variables.dedup();
+        if variables.is_empty() {
+            return Vec::new();
+        }
+        let symbols: Vec<&str> = variables.iter().map(|(_, _, s)| s.as_str()).collect();
+        self.send(&format!("(get-value ({}))", symbols.join(" ")));
+        match self.receive_s_expression() {
+            Some(response) => Self::get_as_model(variables, &response),
+            None => Vec::new(),
+        }
+    }
+
+    fn set_backtrack_position(&mut self) {
+        self.asserted_variables.push(Vec::new());
+        self.send("(push 1)");
+    }
+
+    fn solve(&mut self) -> SmtResult {
+        self.send("(check-sat)");
+        // Errors are reported on the output stream, ahead of the answer to check-sat.
+        // Skip over them, so that the dialogue does not get out of step.
+        while let Some(response) = self.receive() {
+            match Self::get_as_smt_result(&response) {
+                Some(result) => return result,
+                None => debug!("unexpected response from solver: {}", response),
+            }
+        }
+        SmtResult::Undefined
+    }
+}
+
+/// Communication with the solver process.
+impl SmtLibSolver {
+    /// Writes the given command to the solver process (and the transcript, if any).
+    fn send(&mut self, command: &str) {
+        if self.is_broken {
+            return;
+        }
+        if let Some(transcript) = &mut self.transcript {
+            let _ = writeln!(transcript, "{}", command);
+        }
+        let result =
+            writeln!(self.solver_input, "{}", command).and_then(|_| self.solver_input.flush());
+        if let Err(err) = result {
+            info!("lost connection to the solver process: {}", err);
+            self.is_broken = true;
+        }
+    }
+
+    /// Reads the next non empty line of output from the solver process (and copies it to
+    /// the transcript as a comment). Returns None if the solver process is no longer responding.
+    /// If the solver process does not respond in time, it is killed, since the dialogue with it
+    /// would otherwise get out of step.
+    fn receive(&mut self) -> Option<String> {
+        if self.is_broken {
+            return None;
+        }
+        let timeout = Duration::from_millis(k_limits::SMT_SOLVER_PROCESS_TIMEOUT_MILLISECONDS);
+        loop {
+            match self.solver_output.recv_timeout(timeout) {
+                Ok(response) => {
+                    let response = response.trim();
+                    if response.is_empty() {
+                        continue;
+                    }
+                    if let Some(transcript) = &mut self.transcript {
+                        let _ = writeln!(transcript, "; {}", response);
+                    }
+                    return Some(String::from(response));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    info!("the solver process did not respond in time");
+                    let _ = self.solver_process.kill();
+                    self.is_broken = true;
+                    return None;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    info!("lost connection to the solver process");
+                    self.is_broken = true;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Translation of MIRAI expressions into SMT-LIB2 terms.
+impl SmtLibSolver {
+    /// Returns true if the given expression results in a Boolean value, rather than a number.
+    fn is_bool_expression(expression: &Expression) -> bool {
+        use self::Expression::*;
+        match expression {
+            AddOverflows { .. }
+            | And { .. }
+            | Equals { .. }
+            | GreaterOrEqual { .. }
+            | GreaterThan { .. }
+            | LessOrEqual { .. }
+            | LessThan { .. }
+            | MulOverflows { .. }
+            | Ne { .. }
+            | Not { .. }
+            | Or { .. }
+            | ShlOverflows { .. }
+            | ShrOverflows { .. }
+            | SubOverflows { .. } => true,
+            CompileTimeConstant(ConstantDomain::True)
+            | CompileTimeConstant(ConstantDomain::False) => true,
+            ConditionalExpression { consequent, .. } => {
+                Self::is_bool_expression(&consequent.expression)
+            }
+            Variable { var_type, .. } => *var_type == ExpressionType::Bool,
+            _ => false,
+        }
+    }
+
+    /// Translates the given expression into an SMT-LIB2 term of sort Bool.
+    /// Numeric expressions are treated as true if they are not equal to zero.
+    fn get_as_bool_term(&mut self, expression: &Expression) -> String {
+        use self::Expression::*;
+        match expression {
+            AddOverflows {
+                left,
+                right,
+                result_type,
+            } => self.get_as_bit_vector_overflow_check(left, right, result_type, "bvadd"),
+            And { left, right } => {
+                let left_term = self.get_as_bool_term(&left.expression);
+                let right_term = self.get_as_bool_term(&right.expression);
+                format!("(and {} {})", left_term, right_term)
+            }
+            CompileTimeConstant(ConstantDomain::False) => String::from("false"),
+            CompileTimeConstant(ConstantDomain::True) => String::from("true"),
+            ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                let condition_term = self.get_as_bool_term(&condition.expression);
+                let consequent_term = self.get_as_bool_term(&consequent.expression);
+                let alternate_term = self.get_as_bool_term(&alternate.expression);
+                format!(
+                    "(ite {} {} {})",
+                    condition_term, consequent_term, alternate_term
+                )
+            }
+            Equals { left, right } => self.get_as_equality(left, right),
+            GreaterOrEqual { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(>= {} {})", left_term, right_term)
+            }
+            GreaterThan { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(> {} {})", left_term, right_term)
+            }
+            LessOrEqual { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(<= {} {})", left_term, right_term)
+            }
+            LessThan { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(< {} {})", left_term, right_term)
+            }
+            MulOverflows {
+                left,
+                right,
+                result_type,
+            } => self.get_as_bit_vector_overflow_check(left, right, result_type, "bvmul"),
+            Ne { left, right } => format!("(not {})", self.get_as_equality(left, right)),
+            Not { operand } => format!("(not {})", self.get_as_bool_term(&operand.expression)),
+            Or { left, right } => {
+                let left_term = self.get_as_bool_term(&left.expression);
+                let right_term = self.get_as_bool_term(&right.expression);
+                format!("(or {} {})", left_term, right_term)
+            }
+            ShlOverflows {
+                right, result_type, ..
+            }
+            | ShrOverflows {
+                right, result_type, ..
+            } => {
+                // Shifts overflow if the number of bits to shift by is not less than the width of
+                // the type of the value being shifted. Since the shift amount is an integer that
+                // is constrained to the range of its type, this is just as precise as comparing
+                // bit vectors.
+                let right_term = self.get_as_numeric_term(&right.expression);
+                format!(
+                    "(or (< {} 0) (>= {} {}))",
+                    right_term,
+                    right_term,
+                    result_type.bit_length()
+                )
+            }
+            SubOverflows {
+                left,
+                right,
+                result_type,
+            } => self.get_as_bit_vector_overflow_check(left, right, result_type, "bvsub"),
+            Top | Bottom => self.get_fresh_constant("Bool"),
+            Variable { path, var_type } if *var_type == ExpressionType::Bool => {
+                self.get_variable(path, var_type)
+            }
+            _ => {
+                // A number used as a condition. This should not really happen, but be conservative.
+                let numeric_term = self.get_as_numeric_term(expression);
+                format!("(not (= {} 0))", numeric_term)
+            }
+        }
+    }
+
+    /// Translates the given expression into an SMT-LIB2 term of sort Int.
+    /// Boolean expressions are treated as 1 if true and 0 if false.
+    fn get_as_numeric_term(&mut self, expression: &Expression) -> String {
+        use self::Expression::*;
+        if Self::is_bool_expression(expression) {
+            let condition = self.get_as_bool_term(expression);
+            return format!("(ite {} 1 0)", condition);
+        }
+        match expression {
+            AbstractHeapAddress(ordinal) => {
+                self.get_named_constant(&format!("address_{}", ordinal), "Int")
+            }
+            Add { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(+ {} {})", left_term, right_term)
+            }
+            BitAnd { left, right } => self.get_as_bit_vector_operation(left, right, "bvand"),
+            BitOr { left, right } => self.get_as_bit_vector_operation(left, right, "bvor"),
+            BitXor { left, right } => self.get_as_bit_vector_operation(left, right, "bvxor"),
+            CompileTimeConstant(ConstantDomain::Char(ch)) => Self::get_numeral(*ch as u32),
+            CompileTimeConstant(ConstantDomain::I128(val)) => Self::get_numeral(*val),
+            CompileTimeConstant(ConstantDomain::U128(val)) => Self::get_numeral(*val),
+            Cast {
+                operand,
+                target_type,
+            } => self.get_as_cast(&operand.expression, target_type),
+            ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                let condition_term = self.get_as_bool_term(&condition.expression);
+                let consequent_term = self.get_as_numeric_term(&consequent.expression);
+                let alternate_term = self.get_as_numeric_term(&alternate.expression);
+                format!(
+                    "(ite {} {} {})",
+                    condition_term, consequent_term, alternate_term
+                )
+            }
+            Div { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                Self::get_truncated_division(&left_term, &right_term)
+            }
+            Mul { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(* {} {})", left_term, right_term)
+            }
+            Neg { operand } => format!("(- {})", self.get_as_numeric_term(&operand.expression)),
+            Reference(path) => self.get_named_constant(&format!("&{:?}", path), "Int"),
+            Rem { left, right } => {
+                // Rust uses truncated division, so left % right == left - right * (left / right)
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                let quotient = Self::get_truncated_division(&left_term, &right_term);
+                format!("(- {} (* {} {}))", left_term, right_term, quotient)
+            }
+            Shl {
+                left,
+                right,
+                result_type,
+            } => {
+                if !result_type.is_integer() {
+                    return self.get_fresh_constant("Int");
+                }
+                // The bits that are shifted out of a value of result_type are lost, so the shift
+                // is done on bit vectors that are exactly as wide as the type.
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                let width = result_type.bit_length();
+                let result = format!(
+                    "(bvshl ((_ int2bv {}) {}) ((_ int2bv {}) {}))",
+                    width, left_term, width, right_term
+                );
+                Self::get_bit_vector_as_integer(&result, width, result_type.is_signed_integer())
+            }
+            Shr {
+                left,
+                right,
+                result_type,
+            } => {
+                if let CompileTimeConstant(ConstantDomain::U128(shift)) = *right.expression {
+                    if shift < u128::from(result_type.bit_length()) {
+                        // An arithmetic (or logical, for unsigned values) shift right is the same
+                        // as a division that rounds towards negative infinity.
+                        let left_term = self.get_as_numeric_term(&left.expression);
+                        return format!("(div {} {})", left_term, 1u128 << shift);
+                    }
+                }
+                if result_type.is_signed_integer() {
+                    self.get_as_bit_vector_operation(left, right, "bvashr")
+                } else {
+                    self.get_as_bit_vector_operation(left, right, "bvlshr")
+                }
+            }
+            Sub { left, right } => {
+                let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+                format!("(- {} {})", left_term, right_term)
+            }
+            Variable { path, var_type } => match var_type {
+                // Floating point values are not modeled yet. Since NaN != NaN, a float variable
+                // cannot even be assumed to be equal to itself, so each occurrence is fresh.
+                ExpressionType::F32 | ExpressionType::F64 => self.get_fresh_constant("Int"),
+                _ => self.get_variable(path, var_type),
+            },
+            Widen {
+                path,
+                block,
+                interval,
+                ..
+            } => self.get_widened_variable(path, *block, interval),
+            // Values that cannot be modeled precisely as integers become unconstrained values.
+            Top | Bottom | CompileTimeConstant(..) | Offset { .. } => {
+                self.get_fresh_constant("Int")
+            }
+            AddOverflows { .. }
+            | And { .. }
+            | Equals { .. }
+            | GreaterOrEqual { .. }
+            | GreaterThan { .. }
+            | LessOrEqual { .. }
+            | LessThan { .. }
+            | MulOverflows { .. }
+            | Ne { .. }
+            | Not { .. }
+            | Or { .. }
+            | ShlOverflows { .. }
+            | ShrOverflows { .. }
+            | SubOverflows { .. } => {
+                // These are Boolean expressions and have been handled above.
+                unreachable!()
+            }
+        }
+    }
+
+    /// Translates both operands into SMT-LIB2 terms of sort Int.
+    fn get_as_numeric_operands(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+    ) -> (String, String) {
+        let left_term = self.get_as_numeric_term(&left.expression);
+        let right_term = self.get_as_numeric_term(&right.expression);
+        (left_term, right_term)
+    }
+
+    /// Translates "left == right" into an SMT-LIB2 term of sort Bool, comparing the operands
+    /// as Booleans if either of them is Boolean, and as numbers otherwise.
+    fn get_as_equality(&mut self, left: &AbstractDomain, right: &AbstractDomain) -> String {
+        let (left_term, right_term) = if Self::is_bool_expression(&left.expression)
+            || Self::is_bool_expression(&right.expression)
+        {
+            (
+                self.get_as_bool_term(&left.expression),
+                self.get_as_bool_term(&right.expression),
+            )
+        } else {
+            self.get_as_numeric_operands(left, right)
+        };
+        format!("(= {} {})", left_term, right_term)
+    }
+
+    /// Converts the operands into bit vectors, applies the given bit vector operation and
+    /// converts the result back to an integer, interpreting it as a two's complement value.
+    fn get_as_bit_vector_operation(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        operation: &str,
+    ) -> String {
+        let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+        let result = format!(
+            "(bv2nat ({} ((_ int2bv {}) {}) ((_ int2bv {}) {})))",
+            operation, BIT_VECTOR_WIDTH, left_term, BIT_VECTOR_WIDTH, right_term
+        );
+        // bv2nat interprets the bit vector as an unsigned number, so adjust for the sign bit.
+        let sign_bit = Self::get_numeral(1u128 << (BIT_VECTOR_WIDTH - 2));
+        format!(
+            "(let ((r {})) (ite (>= r (* 2 {})) (- r (* 4 {})) r))",
+            result, sign_bit, sign_bit
+        )
+    }
+
+    /// Converts the operands into bit vectors that are exactly as wide as result_type and returns
+    /// an SMT-LIB2 term that is true if the given bit vector operation overflows.
+    /// The operation is carried out on bit vectors of twice that width, into which the operands
+    /// are sign or zero extended, so that the result is always exact. It overflows if it does not
+    /// survive being truncated to the width of result_type and extended again.
+    fn get_as_bit_vector_overflow_check(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        result_type: &ExpressionType,
+        operation: &str,
+    ) -> String {
+        if !result_type.is_integer() {
+            return self.get_fresh_constant("Bool");
+        }
+        let (left_term, right_term) = self.get_as_numeric_operands(left, right);
+        let width = u32::from(result_type.bit_length());
+        let extend = if result_type.is_signed_integer() {
+            format!("(_ sign_extend {})", width)
+        } else {
+            format!("(_ zero_extend {})", width)
+        };
+        format!(
+            "(let ((r ({} ({} ((_ int2bv {}) {})) ({} ((_ int2bv {}) {}))))) \
+             (not (= r ({} ((_ extract {} 0) r)))))",
+            operation,
+            extend,
+            width,
+            left_term,
+            extend,
+            width,
+            right_term,
+            extend,
+            width - 1
+        )
+    }
+
+    /// Translates "operand as target_type" into an SMT-LIB2 term of sort Int.
+    /// A conversion between integer types keeps the bits of the operand that fit into the target
+    /// type, which is what a round trip through a bit vector of that width does.
+    /// Conversions to other types are not modeled.
+    fn get_as_cast(&mut self, operand: &Expression, target_type: &ExpressionType) -> String {
+        if !target_type.is_integer() {
+            return self.get_fresh_constant("Int");
+        }
+        let operand_term = if Self::is_bool_expression(operand) {
+            format!("(ite {} 1 0)", self.get_as_bool_term(operand))
+        } else {
+            self.get_as_numeric_term(operand)
+        };
+        let width = target_type.bit_length();
+        let operand_bv = format!("((_ int2bv {}) {})", width, operand_term);
+        Self::get_bit_vector_as_integer(&operand_bv, width, target_type.is_signed_integer())
+    }
+
+    /// Returns an SMT-LIB2 term of sort Int for the value of the given bit vector term, which has
+    /// the given width. If is_signed is true, the bit vector is a two's complement value.
+    fn get_bit_vector_as_integer(bit_vector: &str, width: u8, is_signed: bool) -> String {
+        let result = format!("(bv2nat {})", bit_vector);
+        if !is_signed {
+            return result;
+        }
+        // bv2nat interprets the bit vector as an unsigned number, so adjust for the sign bit.
+        let sign_bit = Self::get_numeral(1u128 << (width - 1));
+        format!(
+            "(let ((r {})) (ite (>= r {}) (- r (* 2 {})) r))",
+            result, sign_bit, sign_bit
+        )
+    }
+
+    /// Returns an SMT-LIB2 term that is true if the integer constant with the given symbol
+    /// is in the range of values that can be represented by var_type.
+    fn get_range_constraint(symbol: &str, var_type: &ExpressionType) -> String {
+        format!(
+            "(and (<= {} {}) (<= {} {}))",
+            Self::get_numeral(var_type.min_value()),
+            symbol,
+            symbol,
+            Self::get_numeral(var_type.max_value())
+        )
+    }
+
+    /// Returns an SMT-LIB2 term for left / right, where the quotient is truncated towards zero,
+    /// as is the case in Rust. SMT-LIB2 divisions on the other hand round towards negative
+    /// infinity for positive divisors.
+    fn get_truncated_division(left_term: &str, right_term: &str) -> String {
+        format!(
+            "(ite (>= {} 0) (div {} {}) (- (div (- {}) {})))",
+            left_term, left_term, right_term, left_term, right_term
+        )
+    }
+
+    /// Declares a new constant of the given sort that is distinct from all other constants.
+    fn get_fresh_constant(&mut self, sort: &'static str) -> String {
+        self.fresh_constant_count += 1;
+        let name = format!("fresh_{}", self.fresh_constant_count);
+        self.get_named_constant(&name, sort)
+    }
+
+    /// Returns the (quoted) symbol for the constant of the given sort with the given name,
+    /// declaring the constant to the solver if this has not already been done.
+    fn get_named_constant(&mut self, name: &str, sort: &'static str) -> String {
+        // Quoted symbols may not contain | or \.
+        let mut name = name.replace(|c: char| c == '|' || c == '\\', "_");
+        if let Some(declared_sort) = self.declared_constants.get(&name) {
+            if *declared_sort == sort {
+                return format!("|{}|", name);
+            }
+            // A path that is used with more than one type gets a constant for each type.
+            name = format!("{}:{}", name, sort);
+            if self.declared_constants.contains_key(&name) {
+                return format!("|{}|", name);
+            }
+        }
+        self.send(&format!("(declare-const |{}| {})", name, sort));
+        let symbol = format!("|{}|", name);
+        self.declared_constants.insert(name, sort);
+        symbol
+    }
+
+    /// Returns an SMT-LIB2 term of sort Int for the given numeric value.
+    /// Negative numbers do not have a literal representation, so they become negations.
+    fn get_numeral<T: ToString>(value: T) -> String {
+        let digits = value.to_string();
+        if digits.starts_with('-') {
+            format!("(- {})", &digits[1..])
+        } else {
+            digits
+        }
+    }
+
+    /// Returns the symbol of the constant that models the value of the variable at the given path.
+    fn get_variable(&mut self, path: &Path, var_type: &ExpressionType) -> String {
+        let sort = if *var_type == ExpressionType::Bool {
+            "Bool"
+        } else {
+            "Int"
+        };
+        let symbol = self.get_named_constant(&format!("{:?}", path), sort);
+        self.translated_variables
+            .push((path.clone(), var_type.clone(), symbol.clone()));
+        symbol
+    }
+
+    /// Returns the symbol of the constant that models the value of the variable at the given
+    /// path, widened at the entry of the given block. A constraint that limits it to the given
+    /// interval is asserted along with the term that is being translated.
+    fn get_widened_variable(
+        &mut self,
+        path: &Path,
+        block: usize,
+        interval: &IntervalDomain,
+    ) -> String {
+        let name = format!("widen:{:?}:{}:{:?}", path, block, interval);
+        let symbol = self.get_named_constant(&name, "Int");
+        if let Some(lower_bound) = interval.lower_bound() {
+            let lower_bound = Self::get_numeral(lower_bound);
+            self.interval_constraints
+                .push(format!("(<= {} {})", lower_bound, symbol));
+        }
+        if let Some(upper_bound) = interval.upper_bound() {
+            let upper_bound = Self::get_numeral(upper_bound);
+            self.interval_constraints
+                .push(format!("(<= {} {})", symbol, upper_bound));
+        }
+        symbol
+    }
+
+    /// Converts a value from a model returned by the solver into the corresponding constant.
+    /// Returns None if the value is not a Boolean or numeric literal.
+    fn get_as_constant(value: &SExpression, var_type: &ExpressionType) -> Option<ConstantDomain> {
+        match value {
+            SExpression::Atom(atom) if atom == "true" => Some(ConstantDomain::True),
+            SExpression::Atom(atom) if atom == "false" => Some(ConstantDomain::False),
+            SExpression::Atom(atom) if var_type.is_signed_integer() => {
+                atom.parse::<i128>().ok().map(ConstantDomain::I128)
+            }
+            SExpression::Atom(atom) => atom.parse::<u128>().ok().map(ConstantDomain::U128),
+            SExpression::List(elements) if elements.len() == 2 => {
+                // Negative numbers are written as (- n)
+                match (&elements[0], &elements[1]) {
+                    (SExpression::Atom(minus), SExpression::Atom(atom)) if minus == "-" => atom
+                        .parse::<i128>()
+                        .ok()
+                        .map(|val| ConstantDomain::I128(-val)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A parsed S-expression, as found in the responses of the solver.
+#[derive(Debug)]
+enum SExpression {
+    /// A numeral, keyword or symbol. Quoted symbols retain their quotes.
+    Atom(String),
+    /// A parenthesized list of S-expressions.
+    List(Vec<SExpression>),
+}
+
+/// Parsing of responses.
+impl SmtLibSolver {
+    /// Returns the result given by a line of output that answers a check-sat command, or None if
+    /// the line is something else, such as an error message.
+    fn get_as_smt_result(response: &str) -> Option<SmtResult> {
+        match response {
+            "sat" => Some(SmtResult::Satisfiable),
+            "unsat" => Some(SmtResult::Unsatisfiable),
+            "unknown" | "timeout" => Some(SmtResult::Undefined),
+            _ => None,
+        }
+    }
+
+    /// Pairs the given variables with their values in the response to a get-value command.
+    /// The values are listed in the same order as the symbols of the variables in the command.
+    /// Variables whose values are not literals are omitted.
+    fn get_as_model(
+        variables: Vec<(Path, ExpressionType, String)>,
+        response: &SExpression,
+    ) -> Vec<(Path, ConstantDomain)> {
+        let pairs = match response {
+            SExpression::List(pairs) => pairs,
+            SExpression::Atom(..) => return Vec::new(),
+        };
+        let mut result = Vec::new();
+        for ((path, var_type, _), pair) in variables.into_iter().zip(pairs.iter()) {
+            if let SExpression::List(symbol_and_value) = pair {
+                if let Some(value) = symbol_and_value.get(1) {
+                    if let Some(constant) = Self::get_as_constant(value, &var_type) {
+                        result.push((path, constant));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Reads lines of output from the solver process until they form a complete S-expression and
+    /// returns the parsed result. Returns None if the solver is not responding or the output
+    /// is not an S-expression.
+    fn receive_s_expression(&mut self) -> Option<SExpression> {
+        let mut text = String::new();
+        loop {
+            let line = self.receive()?;
+            text.push_str(&line);
+            text.push('\n');
+            let mut tokens = Self::tokenize(&text).into_iter().peekable();
+            if let Some(s_expression) = Self::parse(&mut tokens) {
+                return Some(s_expression);
+            }
+            if !text.starts_with('(') {
+                debug!("unexpected response from solver: {}", text);
+                return None;
+            }
+        }
+    }
+
+    /// Splits the given text into parentheses and atoms, keeping quoted symbols intact.
+    fn tokenize(text: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        let mut in_quoted_symbol = false;
+        for ch in text.chars() {
+            if in_quoted_symbol {
+                current.push(ch);
+                if ch == '|' {
+                    in_quoted_symbol = false;
+                }
+                continue;
+            }
+            match ch {
+                '(' | ')' => {
+                    if !current.is_empty() {
+                        tokens.push(std::mem::replace(&mut current, String::new()));
+                    }
+                    tokens.push(ch.to_string());
+                }
+                '|' => {
+                    current.push(ch);
+                    in_quoted_symbol = true;
+                }
+                _ if ch.is_whitespace() => {
+                    if !current.is_empty() {
+                        tokens.push(std::mem::replace(&mut current, String::new()));
+                    }
+                }
+                _ => current.push(ch),
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+        tokens
+    }
+
+    /// Parses a single S-expression from the given tokens.
+    /// Returns None if the tokens run out before the S-expression is complete.
+    fn parse<I: Iterator<Item = String>>(
+        tokens: &mut std::iter::Peekable<I>,
+    ) -> Option<SExpression> {
+        let token = tokens.next()?;
+        if token != "(" {
+            return Some(SExpression::Atom(token));
+        }
+        let mut elements = Vec::new();
+        loop {
+            if tokens.peek()? == ")" {
+                tokens.next();
+                return Some(SExpression::List(elements));
+            }
+            elements.push(Self::parse(tokens)?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_text(text: &str) -> Option<SExpression> {
+        let mut tokens = SmtLibSolver::tokenize(text).into_iter().peekable();
+        SmtLibSolver::parse(&mut tokens)
+    }
+
+    #[test]
+    fn check_sat_responses() {
+        assert_eq!(
+            SmtLibSolver::get_as_smt_result("sat"),
+            Some(SmtResult::Satisfiable)
+        );
+        assert_eq!(
+            SmtLibSolver::get_as_smt_result("unsat"),
+            Some(SmtResult::Unsatisfiable)
+        );
+        assert_eq!(
+            SmtLibSolver::get_as_smt_result("unknown"),
+            Some(SmtResult::Undefined)
+        );
+        assert_eq!(
+            SmtLibSolver::get_as_smt_result("timeout"),
+            Some(SmtResult::Undefined)
+        );
+        assert_eq!(
+            SmtLibSolver::get_as_smt_result("(error \"line 3 column 8: unknown constant x\")"),
+            None
+        );
+    }
+
+    #[test]
+    fn get_value_response() {
+        let x = Path::LocalVariable { ordinal: 1 };
+        let y = Path::LocalVariable { ordinal: 2 };
+        let b = Path::LocalVariable { ordinal: 3 };
+        let f = Path::LocalVariable { ordinal: 4 };
+        let variables = vec![
+            (x.clone(), ExpressionType::U32, String::from("|x|")),
+            (y.clone(), ExpressionType::I64, String::from("|y|")),
+            (b.clone(), ExpressionType::Bool, String::from("|b|")),
+            (f, ExpressionType::F64, String::from("|f|")),
+        ];
+        let response =
+            parse_text("((|x| 3)\n (|y| (- 5))\n (|b| true)\n (|f| (/ 1.0 3.0)))").unwrap();
+        assert_eq!(
+            SmtLibSolver::get_as_model(variables, &response),
+            vec![
+                (x, ConstantDomain::U128(3)),
+                (y, ConstantDomain::I128(-5)),
+                (b, ConstantDomain::True),
+            ]
+        );
+    }
+
+    #[test]
+    fn get_value_error_response() {
+        let variables = vec![(
+            Path::LocalVariable { ordinal: 1 },
+            ExpressionType::I32,
+            String::from("|x|"),
+        )];
+        let response = parse_text("unsupported").unwrap();
+        assert!(SmtLibSolver::get_as_model(variables, &response).is_empty());
+    }
+
+    #[test]
+    fn incomplete_s_expression() {
+        assert!(parse_text("((|x| 3)").is_none());
+        assert!(parse_text("((|x| 3))").is_some());
+    }
+
+    #[test]
+    fn quoted_symbols_are_single_tokens() {
+        assert_eq!(
+            SmtLibSolver::tokenize("((|a (b)| 1))"),
+            vec!["(", "(", "|a (b)|", "1", ")", ")"]
+        );
+    }
+
+    #[test]
+    fn yices_is_rejected() {
+        let result = SmtLibSolver::new(path::Path::new("/usr/local/bin/yices-smt2"), None);
+        match result {
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::InvalidInput),
+            Ok(..) => panic!("yices should have been rejected"),
+        }
+    }
+}
@@ -3,6 +3,8 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
+use crate::abstract_value::Path;
+use crate::constant_domain::ConstantDomain;
 use crate::expression::Expression;
 
 /// The result of using the solver to solve an expression.
@@ -30,6 +32,14 @@ pub trait SmtSolver<SmtExpressionType> {
     /// Translate the MIRAI expression into a corresponding expression for the Solver.
     fn get_as_smt_predicate(&mut self, mirai_expression: &Expression) -> SmtExpressionType;
 
+    /// Returns concrete values for the variables (places in memory identified by paths) that
+    /// appear in the assertions of the current context, such that all of the assertions are true.
+    /// Only call this right after solve has returned Satisfiable and before the context changes.
+    /// Solvers that cannot produce such models return an empty list.
+    fn get_model(&mut self) -> Vec<(Path, ConstantDomain)> {
+        Vec::new()
+    }
+
     /// Create a nested context. When a matching backtrack is called, the current context (state)
     /// of the solver will be restored to what it was when this was called.
     fn set_backtrack_position(&mut self);
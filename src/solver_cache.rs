@@ -0,0 +1,311 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::abstract_value::Path;
+use crate::constant_domain::ConstantDomain;
+use crate::expression::Expression;
+use crate::options::SolverChoice;
+use crate::smt_solver::{SmtResult, SmtSolver};
+
+use sled::Db;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// The prefix of the keys of solver results in the database that also stores the summaries.
+/// Summary keys are derived from item paths, so they never start with this prefix.
+const KEY_PREFIX: &str = "#smt_result#";
+
+/// Identifies the way in which MIRAI expressions are translated into solver terms. Increment this
+/// whenever a translation changes, so that results stored for queries in the old encoding are
+/// not used to answer queries in the new encoding.
+const ENCODING_VERSION: u32 = 2;
+
+/// A term of the underlying solver, together with the MIRAI expression it was translated from.
+/// The expression is used to identify the query when the term is asserted.
+pub struct CachedTerm<E> {
+    expression: Expression,
+    term: E,
+}
+
+/// An SMT solver that sits in front of another solver and remembers the results of the queries
+/// that the other solver has decided, both in memory and in a persistent database. A query is
+/// identified by the list of expressions that were asserted in the current context when solve
+/// was called. The database key is a stable hash of the serialized query, qualified by the
+/// solver and the encoding of its queries, so results carry over from one outer fixed point
+/// iteration to the next and from one build to the next, but not from one solver to another.
+pub struct CachingSolver<'a, E> {
+    /// The expressions that were asserted in each of the currently active (nested) contexts.
+    asserted_expressions: Vec<Vec<Expression>>,
+    /// The database that stores the results persistently.
+    db: Db,
+    /// True if the last result returned by solve came from the cache rather than the solver.
+    last_result_was_cached: bool,
+    /// Results that have already been looked up or computed during this run.
+    results: HashMap<Vec<u8>, SmtResult>,
+    /// The solver that decides the queries that are not in the cache.
+    smt_solver: &'a mut dyn SmtSolver<E>,
+    /// Identifies the solver and the encoding of its queries. Part of every database key.
+    solver_id: String,
+}
+
+impl<'a, E> CachingSolver<'a, E> {
+    /// Returns a solver that uses smt_solver, which is the solver chosen by solver_choice,
+    /// to decide queries and caches the results in db.
+    pub fn new(
+        smt_solver: &'a mut dyn SmtSolver<E>,
+        solver_choice: &SolverChoice,
+        db: Db,
+    ) -> CachingSolver<'a, E> {
+        CachingSolver {
+            asserted_expressions: vec![Vec::new()],
+            db,
+            last_result_was_cached: false,
+            results: HashMap::new(),
+            smt_solver,
+            solver_id: format!("{:?}#{}", solver_choice, ENCODING_VERSION),
+        }
+    }
+
+    /// Returns the serialized form of the expressions asserted in all of the active contexts.
+    fn get_current_query(&self) -> Vec<u8> {
+        let query: Vec<&Expression> = self.asserted_expressions.iter().flatten().collect();
+        bincode::serialize(&query).unwrap()
+    }
+
+    /// Returns the key under which the result of the given query is stored in the database.
+    fn get_persistent_key(&self, query: &[u8]) -> String {
+        format!(
+            "{}{}#{:016x}",
+            KEY_PREFIX,
+            self.solver_id,
+            stable_hash(query)
+        )
+    }
+
+    /// Returns the result that was previously stored for the given query, if any.
+    /// Since different queries can hash to the same key, the database stores the query
+    /// along with the result and a result is only returned if the stored query matches.
+    fn get_persistent_result(&self, query: &[u8]) -> Option<SmtResult> {
+        let persistent_key = self.get_persistent_key(query);
+        if let Ok(Some(pinned_value)) = self.db.get(persistent_key.as_bytes()) {
+            if let Ok((stored_query, result)) =
+                bincode::deserialize::<(Vec<u8>, SmtResult)>(pinned_value.deref())
+            {
+                if stored_query.as_slice() == query {
+                    return Some(result);
+                }
+            }
+        }
+        None
+    }
+
+    /// Stores the result for the given query in the database, replacing any result that
+    /// may be stored for another query with the same hash.
+    fn set_persistent_result(&self, query: &[u8], result: &SmtResult) {
+        let persistent_key = self.get_persistent_key(query);
+        let serialized_result = bincode::serialize(&(query, result)).unwrap();
+        let db_result = self.db.set(persistent_key.as_bytes(), serialized_result);
+        if db_result.is_err() {
+            println!("unable to set key in summary database: {:?}", db_result);
+        }
+    }
+}
+
+impl<'a, E> SmtSolver<CachedTerm<E>> for CachingSolver<'a, E> {
+    fn as_debug_string(&self, expression: &CachedTerm<E>) -> String {
+        self.smt_solver.as_debug_string(&expression.term)
+    }
+
+    fn assert(&mut self, expression: &CachedTerm<E>) {
+        self.asserted_expressions
+            .last_mut()
+            .expect("there is always a current context")
+            .push(expression.expression.clone());
+        self.smt_solver.assert(&expression.term);
+    }
+
+    fn backtrack(&mut self) {
+        if self.asserted_expressions.len() > 1 {
+            self.asserted_expressions.pop();
+        }
+        self.smt_solver.backtrack();
+    }
+
+    fn get_as_smt_predicate(&mut self, mirai_expression: &Expression) -> CachedTerm<E> {
+        CachedTerm {
+            expression: mirai_expression.clone(),
+            term: self.smt_solver.get_as_smt_predicate(mirai_expression),
+        }
+    }
+
+    /// Since a cached result does not come with a model, the underlying solver first has to
+    /// decide the current context if the last result came from the cache.
+    fn get_model(&mut self) -> Vec<(Path, ConstantDomain)> {
+        if self.last_result_was_cached {
+            self.last_result_was_cached = false;
+            if self.smt_solver.solve() != SmtResult::Satisfiable {
+                return Vec::new();
+            }
+        }
+        self.smt_solver.get_model()
+    }
+
+    fn set_backtrack_position(&mut self) {
+        self.asserted_expressions.push(Vec::new());
+        self.smt_solver.set_backtrack_position();
+    }
+
+    /// Returns the cached result for the current context if there is one. Otherwise asks the
+    /// underlying solver and caches its answer, unless the solver could not decide the query,
+    /// since a later attempt (perhaps with another solver or time limit) might succeed.
+    fn solve(&mut self) -> SmtResult {
+        let query = self.get_current_query();
+        let cached_result = match self.results.get(&query) {
+            Some(result) => Some(result.clone()),
+            None => self.get_persistent_result(&query),
+        };
+        if let Some(result) = cached_result {
+            self.last_result_was_cached = true;
+            self.results.insert(query, result.clone());
+            return result;
+        }
+        self.last_result_was_cached = false;
+        let result = self.smt_solver.solve();
+        if result != SmtResult::Undefined {
+            self.set_persistent_result(&query, &result);
+            self.results.insert(query, result.clone());
+        }
+        result
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of the given bytes. Unlike the hashers in the standard
+/// library, this gives the same result in every run and with every version of the compiler,
+/// which is needed for keys that are stored in a persistent database.
+fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ExpressionType;
+    use std::path::PathBuf;
+    use tempdir::TempDir;
+
+    /// A solver that always gives the same answer and counts how often it is asked.
+    struct CountingSolver {
+        result: SmtResult,
+        solve_count: usize,
+    }
+
+    impl SmtSolver<()> for CountingSolver {
+        fn as_debug_string(&self, _: &()) -> String {
+            String::from("counting")
+        }
+
+        fn assert(&mut self, _: &()) {}
+
+        fn backtrack(&mut self) {}
+
+        fn get_as_smt_predicate(&mut self, _mirai_expression: &Expression) {}
+
+        fn set_backtrack_position(&mut self) {}
+
+        fn solve(&mut self) -> SmtResult {
+            self.solve_count += 1;
+            self.result.clone()
+        }
+    }
+
+    /// Asks the caching solver to decide if the variable with the given ordinal can be true.
+    fn solve_query<E>(caching_solver: &mut CachingSolver<'_, E>, ordinal: usize) -> SmtResult {
+        let expression = Expression::Variable {
+            path: box Path::LocalVariable { ordinal },
+            var_type: ExpressionType::Bool,
+        };
+        let term = caching_solver.get_as_smt_predicate(&expression);
+        caching_solver.solve_expression(&term)
+    }
+
+    #[test]
+    fn results_are_stored_and_reused() {
+        let temp_dir = TempDir::new("miraiSolverCache").unwrap();
+        let db = Db::start_default(temp_dir.path().join("summary_store.sled")).unwrap();
+
+        let mut solver = CountingSolver {
+            result: SmtResult::Unsatisfiable,
+            solve_count: 0,
+        };
+        {
+            let mut caching_solver =
+                CachingSolver::new(&mut solver, &SolverChoice::Linear, db.clone());
+            assert_eq!(
+                solve_query(&mut caching_solver, 1),
+                SmtResult::Unsatisfiable
+            );
+            // The second query is answered from memory.
+            assert_eq!(
+                solve_query(&mut caching_solver, 1),
+                SmtResult::Unsatisfiable
+            );
+        }
+        assert_eq!(solver.solve_count, 1);
+
+        // A new caching solver (as in a later build) finds the result in the database, even
+        // though the underlying solver would now give another answer.
+        let mut solver = CountingSolver {
+            result: SmtResult::Satisfiable,
+            solve_count: 0,
+        };
+        {
+            let mut caching_solver =
+                CachingSolver::new(&mut solver, &SolverChoice::Linear, db.clone());
+            assert_eq!(
+                solve_query(&mut caching_solver, 1),
+                SmtResult::Unsatisfiable
+            );
+            // A different query goes to the underlying solver.
+            assert_eq!(solve_query(&mut caching_solver, 2), SmtResult::Satisfiable);
+        }
+        assert_eq!(solver.solve_count, 1);
+
+        // Results stored for one solver are not used for another.
+        let mut solver = CountingSolver {
+            result: SmtResult::Satisfiable,
+            solve_count: 0,
+        };
+        {
+            let mut caching_solver = CachingSolver::new(
+                &mut solver,
+                &SolverChoice::SmtLib(PathBuf::from("cvc4")),
+                db,
+            );
+            assert_eq!(solve_query(&mut caching_solver, 1), SmtResult::Satisfiable);
+        }
+        assert_eq!(solver.solve_count, 1);
+    }
+
+    #[test]
+    fn undecided_results_are_not_stored() {
+        let temp_dir = TempDir::new("miraiSolverCache").unwrap();
+        let db = Db::start_default(temp_dir.path().join("summary_store.sled")).unwrap();
+        let mut solver = CountingSolver {
+            result: SmtResult::Undefined,
+            solve_count: 0,
+        };
+        {
+            let mut caching_solver = CachingSolver::new(&mut solver, &SolverChoice::Linear, db);
+            assert_eq!(solve_query(&mut caching_solver, 1), SmtResult::Undefined);
+            assert_eq!(solve_query(&mut caching_solver, 1), SmtResult::Undefined);
+        }
+        assert_eq!(solver.solve_count, 2);
+    }
+}
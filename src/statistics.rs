@@ -0,0 +1,40 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::expression_arena;
+
+use std::cell::Cell;
+
+// Counters that show how close the analysis of a crate comes to the limits in k_limits.rs
+// and how often it hits them. They are logged at the end of the analysis and reset when the
+// expression scope of the next analysis is entered, just like the sizes kept by the arena.
+
+thread_local! {
+    /// The number of joined values whose expressions were abstracted because they were too large.
+    static ABSTRACTED_EXPRESSIONS: Cell<usize> = Cell::new(0);
+}
+
+/// Records that a joined value was abstracted because its expression was too large.
+pub fn record_abstracted_expression() {
+    ABSTRACTED_EXPRESSIONS.with(|count| count.set(count.get() + 1));
+}
+
+/// Sets the counters of the current thread back to zero.
+pub fn reset() {
+    ABSTRACTED_EXPRESSIONS.with(|count| count.set(0));
+}
+
+/// Logs the statistics gathered by the current thread.
+pub fn report() {
+    let largest_size = expression_arena::get_largest_size();
+    info!(
+        "interned expressions {}",
+        expression_arena::number_of_nodes()
+    );
+    info!("deepest expression {}", largest_size.depth);
+    info!("largest expression {} nodes", largest_size.node_count);
+    let abstracted_expressions = ABSTRACTED_EXPRESSIONS.with(|count| count.get());
+    info!("abstracted expressions {}", abstracted_expressions);
+}
@@ -0,0 +1,215 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+use crate::interval_domain::{self, IntervalDomain};
+
+/// An element of the String domain is a set of strings whose lengths, in bytes, are in an
+/// interval and which start with a known prefix and end with a known suffix. The prefix and
+/// suffix consist of whole characters, so the byte offsets in between them are char boundaries
+/// whenever they are char boundaries of the prefix or of the suffix.
+/// String domain elements are constructed on demand from AbstractDomain expressions.
+/// They are most useful for checking that string slices are taken at char boundaries, for
+/// example in parsers that work with byte offsets into strings.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialOrd, PartialEq, Hash, Ord)]
+pub struct StringDomain {
+    length: IntervalDomain,
+    prefix: String,
+    suffix: String,
+}
+
+pub const TOP: StringDomain = StringDomain {
+    length: interval_domain::TOP,
+    prefix: String::new(),
+    suffix: String::new(),
+};
+
+impl<'a> From<&'a str> for StringDomain {
+    fn from(s: &'a str) -> StringDomain {
+        StringDomain {
+            length: (s.len() as u128).into(),
+            prefix: String::from(s),
+            suffix: String::from(s),
+        }
+    }
+}
+
+impl StringDomain {
+    // Returns the strings with a length in the given interval that start with prefix and end
+    // with suffix. Strings that are shorter than their prefix or suffix are left out, and if
+    // the prefix or the suffix covers the whole string, they are the same.
+    fn new(length: IntervalDomain, prefix: String, suffix: String) -> Self {
+        let known_length = prefix.len().max(suffix.len()) as u128;
+        let length = length.refine_greater_than(&known_length.into(), false);
+        let (prefix, suffix) = if length.upper_bound() == Some(prefix.len() as i128) {
+            (prefix.clone(), prefix)
+        } else if length.upper_bound() == Some(suffix.len() as i128) {
+            (suffix.clone(), suffix)
+        } else {
+            (prefix, suffix)
+        };
+        StringDomain {
+            length,
+            prefix,
+            suffix,
+        }
+    }
+
+    // Returns the only string in self, if its contents are completely known.
+    pub fn as_constant(&self) -> Option<&str> {
+        if self.length.lower_bound() == Some(self.prefix.len() as i128)
+            && self.length.upper_bound() == Some(self.prefix.len() as i128)
+        {
+            Some(&self.prefix)
+        } else {
+            None
+        }
+    }
+
+    // Returns Some(true) if every value of index is a char boundary of every string in self and
+    // Some(false) if no value of index is a char boundary of any string in self. Returns None if
+    // this depends on the value of index, or if index may fall into a part of the string whose
+    // contents are not known.
+    pub fn is_char_boundary(&self, index: &IntervalDomain) -> Option<bool> {
+        let suffix_start = self.get_suffix_start();
+        let known_end = suffix_start.map_or(self.prefix.len(), |start| start + self.suffix.len());
+        let (lower_bound, upper_bound) = match (index.lower_bound(), index.upper_bound()) {
+            (Some(lower_bound), Some(upper_bound))
+                if 0 <= lower_bound && upper_bound <= known_end as i128 =>
+            {
+                (lower_bound as usize, upper_bound as usize)
+            }
+            _ => return None,
+        };
+        let mut result = None;
+        for i in lower_bound..=upper_bound {
+            let is_boundary = if i <= self.prefix.len() {
+                self.prefix.is_char_boundary(i)
+            } else {
+                match suffix_start {
+                    Some(start) if start <= i => self.suffix.is_char_boundary(i - start),
+                    _ => return None,
+                }
+            };
+            if result.is_some() && result != Some(is_boundary) {
+                return None;
+            }
+            result = Some(is_boundary);
+        }
+        result
+    }
+
+    // The known prefix and suffix of the join are the longest ones that both operands share.
+    pub fn join(&self, other: &Self) -> Self {
+        let prefix_length = self
+            .prefix
+            .bytes()
+            .zip(other.prefix.bytes())
+            .take_while(|(x, y)| x == y)
+            .count();
+        let suffix_length = self
+            .suffix
+            .bytes()
+            .rev()
+            .zip(other.suffix.bytes().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        Self::new(
+            self.length.join(&other.length),
+            String::from(get_prefix(&self.prefix, prefix_length)),
+            String::from(get_suffix(&self.suffix, suffix_length)),
+        )
+    }
+
+    // Returns the lengths, in bytes, of the strings in self.
+    pub fn length(&self) -> IntervalDomain {
+        self.length.clone()
+    }
+
+    // Returns the strings that result from slicing the strings of self with start..end, if this
+    // does not panic. If start is known, the slices begin with the known characters of self
+    // from start onwards, and if end is known, they end with the known characters before end.
+    pub fn slice(&self, start: &IntervalDomain, end: &IntervalDomain) -> Self {
+        let length = end
+            .sub(start)
+            .refine_greater_than(&0u128.into(), false)
+            .refine_less_than(&self.length, false);
+        let shortest_length = length.lower_bound().map_or(0, |l| l.max(0) as usize);
+        let prefix = match get_constant(start) {
+            Some(start) => get_prefix(self.get_known_text_from(start), shortest_length),
+            None => "",
+        };
+        let suffix = match get_constant(end) {
+            Some(end) => get_suffix(self.get_known_text_before(end), shortest_length),
+            None => "",
+        };
+        Self::new(length, String::from(prefix), String::from(suffix))
+    }
+
+    // Returns the known characters of self that start at byte offset index. The suffix extends
+    // to the end of the string, so it is preferred over the prefix.
+    fn get_known_text_from(&self, index: usize) -> &str {
+        let text_from_suffix = match self.get_suffix_start() {
+            Some(start) if start <= index => self.suffix.get(index - start..),
+            _ => None,
+        };
+        text_from_suffix
+            .or_else(|| self.prefix.get(index..))
+            .unwrap_or("")
+    }
+
+    // Returns the known characters of self that end at byte offset index. The prefix extends to
+    // the start of the string, so it is preferred over the suffix.
+    fn get_known_text_before(&self, index: usize) -> &str {
+        let text_from_prefix = self.prefix.get(..index);
+        text_from_prefix
+            .or_else(|| match self.get_suffix_start() {
+                Some(start) if start <= index => self.suffix.get(..index - start),
+                _ => None,
+            })
+            .unwrap_or("")
+    }
+
+    // Returns the byte offset at which the suffix starts, if the length of self is known.
+    fn get_suffix_start(&self) -> Option<usize> {
+        match get_constant(&self.length) {
+            Some(length) if self.suffix.len() <= length => Some(length - self.suffix.len()),
+            _ => None,
+        }
+    }
+}
+
+// Returns the value of the interval if it is a single value that fits into a usize.
+fn get_constant(interval: &IntervalDomain) -> Option<usize> {
+    match (interval.lower_bound(), interval.upper_bound()) {
+        (Some(lower_bound), Some(upper_bound))
+            if lower_bound == upper_bound
+                && 0 <= lower_bound
+                && lower_bound <= std::usize::MAX as i128 =>
+        {
+            Some(lower_bound as usize)
+        }
+        _ => None,
+    }
+}
+
+// Returns the longest prefix of s that has at most max_length bytes and ends at a char boundary.
+fn get_prefix(s: &str, max_length: usize) -> &str {
+    let mut length = max_length.min(s.len());
+    while !s.is_char_boundary(length) {
+        length -= 1;
+    }
+    &s[..length]
+}
+
+// Returns the longest suffix of s that has at most max_length bytes and starts at a char
+// boundary.
+fn get_suffix(s: &str, max_length: usize) -> &str {
+    let mut start = s.len() - max_length.min(s.len());
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+    &s[start..]
+}
@@ -5,6 +5,7 @@
 
 use crate::abstract_value::{AbstractValue, Path};
 use crate::environment::Environment;
+use crate::options::Options;
 use crate::utils;
 
 use rustc::hir::def_id::DefId;
@@ -96,10 +97,22 @@ pub fn summarize(
     unwind_condition: Option<AbstractValue>,
     unwind_environment: &Environment,
 ) -> Summary {
-    let mut preconditions: Vec<(AbstractValue, String)> = preconditions.to_owned();
-    let result = exit_environment.value_at(&Path::LocalVariable { ordinal: 0 });
+    // Simplify the values before storing them, since preconditions that are promoted from
+    // callees, in particular, tend to grow into large trees that are slow to refine.
+    let mut preconditions: Vec<(AbstractValue, String)> = preconditions
+        .iter()
+        .map(|(condition, message)| (condition.simplify(), message.clone()))
+        .filter(|(condition, _)| !condition.as_bool_if_known().unwrap_or(false))
+        .collect();
+    let result = exit_environment
+        .value_at(&Path::LocalVariable { ordinal: 0 })
+        .map(AbstractValue::simplify);
     let mut side_effects = extract_side_effects(exit_environment, argument_count);
-    let mut post_conditions: Vec<AbstractValue> = post_conditions.to_owned();
+    let mut post_conditions: Vec<AbstractValue> = post_conditions
+        .iter()
+        .map(AbstractValue::simplify)
+        .collect();
+    let unwind_condition = unwind_condition.map(|condition| condition.simplify());
     let mut unwind_side_effects = extract_side_effects(unwind_environment, argument_count);
 
     preconditions.sort();
@@ -109,7 +122,7 @@ pub fn summarize(
 
     Summary {
         preconditions,
-        result: result.cloned(),
+        result,
         side_effects,
         post_conditions,
         unwind_condition,
@@ -129,7 +142,7 @@ fn extract_side_effects(env: &Environment, argument_count: usize) -> Vec<(Path,
             .iter()
             .filter(|(p, _)| (**p) == root || p.is_rooted_by(&root))
         {
-            result.push((path.clone(), value.clone()));
+            result.push((path.clone(), value.simplify()));
         }
     }
     //todo: what about paths rooted by heap allocated (i.e. boxed) objects that are referenced by
@@ -148,12 +161,17 @@ pub struct PersistentSummaryCache<'a, 'tcx: 'a> {
 }
 
 impl<'a, 'tcx: 'a> PersistentSummaryCache<'a, 'tcx> {
-    /// Creates a new persistent summary cache, using (or creating) a Rocks data base at the given
-    /// file path.
+    /// Creates a new persistent summary cache, using (or creating) a Rocks data base at the
+    /// file path given by options.summary_store_path, which must have been set by the caller.
     pub fn new(
         type_context: &'a TyCtxt<'a, 'tcx, 'tcx>,
-        summary_store_path: String,
+        options: &Options,
     ) -> PersistentSummaryCache<'a, 'tcx> {
+        let summary_store_path = options
+            .summary_store_path
+            .as_ref()
+            .expect("the summary store path should have been set");
+        info!("storing summaries at {}", summary_store_path.display());
         PersistentSummaryCache {
             db: Db::start_default(summary_store_path)
                 .unwrap_or_else(|err| panic!(format!("{} ", err))),
@@ -164,6 +182,12 @@ impl<'a, 'tcx: 'a> PersistentSummaryCache<'a, 'tcx> {
         }
     }
 
+    /// Returns a handle to the database that stores the summaries, so that other persistent
+    /// caches can be kept next to the summaries.
+    pub fn get_db(&self) -> Db {
+        self.db.clone()
+    }
+
     /// Returns a list of DefIds for all functions in the current crate that are known
     /// to have used the summary of the function identified by def_id.
     /// Use this after all functions in a crate have been analyzed.
@@ -224,11 +248,86 @@ impl<'a, 'tcx: 'a> PersistentSummaryCache<'a, 'tcx> {
     /// Sets or updates the cache so that from now on def_id maps to the given summary.
     pub fn set_summary_for(&mut self, def_id: DefId, summary: Summary) -> Option<Summary> {
         let persistent_key = utils::summary_key_str(self.type_context, def_id);
-        let serialized_summary = bincode::serialize(&summary).unwrap();
-        let result = self.db.set(persistent_key.as_bytes(), serialized_summary);
+        Self::set_persistent_summary_for_db(&self.db, &persistent_key, &summary);
+        self.cache.insert(def_id, summary)
+    }
+
+    /// Helper for set_summary_for.
+    fn set_persistent_summary_for_db(db: &Db, persistent_key: &str, summary: &Summary) {
+        let serialized_summary = bincode::serialize(summary).unwrap();
+        let result = db.set(persistent_key.as_bytes(), serialized_summary);
         if result.is_err() {
             println!("unable to set key in summary database: {:?}", result);
         }
-        self.cache.insert(def_id, summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant_domain::ConstantDomain;
+    use crate::expression::{Expression, ExpressionType};
+    use crate::expression_arena;
+    use tempdir::TempDir;
+
+    /// Returns the value of the parameter with the given ordinal.
+    fn make_parameter(ordinal: usize) -> AbstractValue {
+        Expression::Variable {
+            path: box Path::LocalVariable { ordinal },
+            var_type: ExpressionType::I32,
+        }
+        .into()
+    }
+
+    /// Returns a summary whose values share the sub expression "x + 1".
+    fn make_summary() -> Summary {
+        let one: AbstractValue = ConstantDomain::I128(1).into();
+        let x_plus_one = make_parameter(1).add(&one, None);
+        let y = make_parameter(2);
+        let precondition: AbstractValue = Expression::LessThan {
+            left: box x_plus_one.domain.clone(),
+            right: box y.domain.clone(),
+        }
+        .into();
+        Summary {
+            preconditions: vec![(precondition, String::from("x + 1 < y"))],
+            result: Some(x_plus_one),
+            side_effects: vec![(Path::LocalVariable { ordinal: 0 }, y)],
+            ..Summary::default()
+        }
+    }
+
+    #[test]
+    fn summaries_survive_the_round_trip_through_the_store() {
+        let temp_dir = TempDir::new("summaries").unwrap();
+        let db = Db::start_default(temp_dir.path()).unwrap();
+        let key = "test_summary";
+        {
+            // The summary is dropped before the scope.
+            let _scope = unsafe { expression_arena::enter_scope() };
+            PersistentSummaryCache::set_persistent_summary_for_db(&db, key, &make_summary());
+        }
+        // The expressions of the first scope are gone, so these are interned from scratch.
+        // The scope is declared first, so that it is dropped after the summary.
+        let _scope = unsafe { expression_arena::enter_scope() };
+        assert_eq!(expression_arena::number_of_nodes(), 0);
+        let summary = PersistentSummaryCache::get_persistent_summary_for_db(&db, key);
+        assert_eq!(summary, make_summary());
+        let result = summary.result.unwrap();
+        let precondition = &summary.preconditions[0].0;
+        match &precondition.domain.expression {
+            Expression::LessThan { left, .. } => {
+                assert!(std::ptr::eq(left.expression, result.domain.expression))
+            }
+            _ => panic!("unexpected precondition {:?}", precondition),
+        }
+    }
+
+    #[test]
+    fn a_missing_summary_is_the_default_summary() {
+        let temp_dir = TempDir::new("summaries").unwrap();
+        let db = Db::start_default(temp_dir.path()).unwrap();
+        let summary = PersistentSummaryCache::get_persistent_summary_for_db(&db, "missing");
+        assert_eq!(summary, Summary::default());
     }
 }
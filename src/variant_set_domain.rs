@@ -0,0 +1,90 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+/// An element of the VariantSet domain is a set of the variants that an enum value may have,
+/// identified by their indices. Bit i of the mask is set if variant i is in the set.
+/// Unlike most other domains, variant sets are not constructed from AbstractDomain expressions,
+/// but are kept by the environment for the paths of enum values. They are updated when a
+/// discriminant is set and refined along the edges of switches on discriminants, so that match
+/// arms for variants that an enum cannot have are known to be unreachable.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialOrd, PartialEq, Hash, Ord)]
+pub struct VariantSetDomain {
+    mask: u128,
+}
+
+pub const BOTTOM: VariantSetDomain = VariantSetDomain { mask: 0 };
+
+// The largest number of variants that an enum can have and still be tracked.
+const MAX_VARIANTS: usize = 128;
+
+impl VariantSetDomain {
+    // Returns the set of all of the variants of an enum with the given number of variants,
+    // or None if the enum has too many variants to be tracked.
+    pub fn all(variant_count: usize) -> Option<Self> {
+        if variant_count < MAX_VARIANTS {
+            Some(VariantSetDomain {
+                mask: (1u128 << variant_count) - 1,
+            })
+        } else if variant_count == MAX_VARIANTS {
+            Some(VariantSetDomain {
+                mask: std::u128::MAX,
+            })
+        } else {
+            None
+        }
+    }
+
+    // Returns the set that contains only the given variant, or None if the variant index is
+    // too large to be tracked.
+    pub fn singleton(variant_index: usize) -> Option<Self> {
+        if variant_index < MAX_VARIANTS {
+            Some(VariantSetDomain {
+                mask: 1u128 << variant_index,
+            })
+        } else {
+            None
+        }
+    }
+
+    // Returns the index of the only variant in self, if there is exactly one.
+    pub fn as_singleton(&self) -> Option<usize> {
+        if self.mask.count_ones() == 1 {
+            Some(self.mask.trailing_zeros() as usize)
+        } else {
+            None
+        }
+    }
+
+    // Returns the variants that are in self, but not in other.
+    pub fn difference(&self, other: &Self) -> Self {
+        VariantSetDomain {
+            mask: self.mask & !other.mask,
+        }
+    }
+
+    // Returns the variants that are in both self and other.
+    pub fn intersect(&self, other: &Self) -> Self {
+        VariantSetDomain {
+            mask: self.mask & other.mask,
+        }
+    }
+
+    pub fn is_bottom(&self) -> bool {
+        self.mask == 0
+    }
+
+    // Returns the variants that are in self or in other.
+    pub fn join(&self, other: &Self) -> Self {
+        VariantSetDomain {
+            mask: self.mask | other.mask,
+        }
+    }
+
+    // Returns true if every variant in self is also in other.
+    pub fn subset(&self, other: &Self) -> bool {
+        self.mask & !other.mask == 0
+    }
+}
@@ -5,26 +5,34 @@
 
 use crate::abstract_domains::AbstractDomain;
 use crate::abstract_value::{self, AbstractValue, Path, PathSelector};
+use crate::bdd;
 use crate::constant_domain::{ConstantDomain, ConstantValueCache};
 use crate::environment::Environment;
 use crate::expression::{Expression, ExpressionType};
+use crate::interval_domain::{self, IntervalDomain};
 use crate::k_limits;
+use crate::loop_analysis::{self, LoopBound, NaturalLoop};
+use crate::options::{DiagLevel, Options};
 use crate::smt_solver::{SmtResult, SmtSolver};
 use crate::summaries;
 use crate::summaries::{PersistentSummaryCache, Summary};
 use crate::utils::{self, is_public};
+use crate::variant_set_domain::{self, VariantSetDomain};
 
 use rustc::session::Session;
-use rustc::ty::{Const, LazyConst, Ty, TyCtxt, TyKind, UserTypeAnnotationIndex};
+use rustc::ty::layout::VariantIdx;
+use rustc::ty::{AdtDef, Const, LazyConst, Ty, TyCtxt, TyKind, UserTypeAnnotationIndex};
 use rustc::{hir, mir};
 use std::borrow::Borrow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::iter::FromIterator;
 use syntax::errors::{Diagnostic, DiagnosticBuilder};
 use syntax_pos;
 
 pub struct MirVisitorCrateContext<'a, 'b: 'a, 'tcx: 'b, E> {
+    /// The options that control the analysis, obtained from the command line.
+    pub options: &'a Options,
     /// A place where diagnostic messages can be buffered by the test harness.
     pub buffered_diagnostics: &'a mut Vec<Diagnostic>,
     /// A call back that the test harness can use to buffer the diagnostic message.
@@ -41,6 +49,7 @@ pub struct MirVisitorCrateContext<'a, 'b: 'a, 'tcx: 'b, E> {
 
 /// Holds the state for the MIR test visitor.
 pub struct MirVisitor<'a, 'b: 'a, 'tcx: 'b, E> {
+    options: &'a Options,
     buffered_diagnostics: &'a mut Vec<Diagnostic>,
     emit_diagnostic: fn(&mut DiagnosticBuilder<'_>, buf: &mut Vec<Diagnostic>) -> (),
     session: &'tcx Session,
@@ -51,16 +60,27 @@ pub struct MirVisitor<'a, 'b: 'a, 'tcx: 'b, E> {
     summary_cache: &'a mut PersistentSummaryCache<'b, 'tcx>,
     smt_solver: &'a mut dyn SmtSolver<E>,
 
+    /// The paths of the byte offsets returned by CharIndices::next, along with the string slices
+    /// whose char boundaries they are.
+    char_boundaries: HashMap<Path, AbstractDomain>,
     check_for_errors: bool,
     current_environment: Environment,
     current_location: mir::Location,
     current_span: syntax_pos::Span,
     exit_environment: Environment,
     heap_addresses: HashMap<mir::Location, AbstractValue>,
+    /// The natural loops of the body, along with their induction variables.
+    loops: Vec<NaturalLoop>,
     post_conditions: Vec<AbstractValue>,
     preconditions: Vec<(AbstractValue, String)>,
+    /// The largest number of times that the loop with the given head can iterate, for the loops
+    /// of the body for which this is known.
+    trip_counts: HashMap<mir::BasicBlock, u128>,
     unwind_condition: Option<AbstractValue>,
     unwind_environment: Environment,
+    /// The integer constants found in the body (and its promoted constants), such as array
+    /// lengths, loop bounds and comparison operands. Widened intervals snap to these values.
+    widening_thresholds: BTreeSet<i128>,
 }
 
 /// A visitor that simply traverses enough of the MIR associated with a particular code body
@@ -69,7 +89,10 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
     pub fn new(
         crate_context: MirVisitorCrateContext<'a, 'b, 'tcx, E>,
     ) -> MirVisitor<'a, 'b, 'tcx, E> {
+        // The diagrams of the path conditions of other bodies are of little use for this one.
+        bdd::reset();
         MirVisitor {
+            options: crate_context.options,
             buffered_diagnostics: crate_context.buffered_diagnostics,
             emit_diagnostic: crate_context.emit_diagnostic,
             session: crate_context.session,
@@ -80,31 +103,39 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             summary_cache: crate_context.summary_cache,
             smt_solver: crate_context.smt_solver,
 
+            char_boundaries: HashMap::default(),
             check_for_errors: false,
             current_environment: Environment::default(),
             current_location: mir::Location::START,
             current_span: syntax_pos::DUMMY_SP,
             exit_environment: Environment::default(),
             heap_addresses: HashMap::default(),
+            loops: Vec::new(),
             post_conditions: Vec::new(),
             preconditions: Vec::new(),
+            trip_counts: HashMap::default(),
             unwind_condition: None,
             unwind_environment: Environment::default(),
+            widening_thresholds: BTreeSet::new(),
         }
     }
 
     /// Restores the method only state to its initial state.
     fn reset_visitor_state(&mut self) {
+        self.char_boundaries = HashMap::default();
         self.check_for_errors = false;
         self.current_environment = Environment::default();
         self.current_location = mir::Location::START;
         self.current_span = syntax_pos::DUMMY_SP;
         self.exit_environment = Environment::default();
         self.heap_addresses = HashMap::default();
+        self.loops = Vec::new();
         self.post_conditions = Vec::new();
         self.preconditions = Vec::new();
+        self.trip_counts = HashMap::default();
         self.unwind_condition = None;
         self.unwind_environment = Environment::default();
+        bdd::reset();
     }
 
     /// Use the local and global environments to resolve Path to an abstract value.
@@ -179,6 +210,19 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         (&loc.ty.sty).into()
     }
 
+    /// Returns true if the current function should be assumed to get called with any possible
+    /// arguments, which is the case for public functions, or for all functions if the diagnostic
+    /// level is paranoid.
+    fn assume_function_is_called(&self) -> bool {
+        self.options.diag_level == DiagLevel::Paranoid || is_public(self.def_id, &self.tcx)
+    }
+
+    /// Returns true if conditions that might fail at runtime should be reported as diagnostics,
+    /// rather than only being promoted to preconditions of the current function.
+    fn report_possible_errors(&self) -> bool {
+        self.options.diag_level != DiagLevel::Relaxed && self.assume_function_is_called()
+    }
+
     /// Analyze the body and store a summary of its behavior in self.summary_cache.
     /// Returns true if the newly computed summary is different from the summary (if any)
     /// that is already in the cache.
@@ -195,6 +239,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         // The entry block has no predecessors and its initial state is the function parameters
         // as well any promoted constants.
         let first_state = self.promote_constants();
+        self.loops = loop_analysis::find_loops(self.mir);
 
         // Compute a fixed point, which is a value of out_state that will not grow with more iterations.
         let mut changed = true;
@@ -202,55 +247,18 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         while changed {
             changed = false;
             for bb in self.mir.basic_blocks().indices() {
-                // Merge output states of predecessors of bb
-                let i_state = if bb.index() == 0 {
-                    first_state.clone()
-                } else {
-                    let mut predecessor_states_and_conditions: Vec<(
-                        &Environment,
-                        Option<&AbstractValue>,
-                    )> = self
-                        .mir
-                        .predecessors_for(bb)
-                        .iter()
-                        .map(|pred_bb| {
-                            let pred_state = &out_state[pred_bb];
-                            let pred_exit_condition = pred_state.exit_conditions.get(&bb);
-                            (pred_state, pred_exit_condition)
-                        })
-                        .filter(|(_, pred_exit_condition)| pred_exit_condition.is_some())
-                        .collect();
-                    if predecessor_states_and_conditions.is_empty() {
-                        // unreachable block
-                        let mut i_state = in_state[&bb].clone();
-                        i_state.entry_condition = abstract_value::FALSE;
-                        i_state
-                    } else {
-                        // We want to do right associative operations and that is easier if we reverse.
-                        predecessor_states_and_conditions.reverse();
-                        let (p_state, pred_exit_condition) = predecessor_states_and_conditions[0];
-                        let mut i_state = p_state.clone();
-                        i_state.entry_condition = pred_exit_condition
-                            .unwrap()
-                            .with_provenance(self.current_span);
-                        for (p_state, pred_exit_condition) in
-                            predecessor_states_and_conditions.iter().skip(1)
-                        {
-                            let join_condition = pred_exit_condition.unwrap();
-                            // Once all paths have already been analyzed for a second time (iteration_count >= 3)
-                            // we to abstract more aggressively in order to ensure reaching a fixed point.
-                            let mut j_state = if iteration_count < 3 {
-                                p_state.join(&i_state, join_condition)
-                            } else {
-                                p_state.widen(&i_state, join_condition)
-                            };
-                            j_state.entry_condition = join_condition
-                                .or(&i_state.entry_condition, Some(self.current_span));
-                            i_state = j_state;
-                        }
-                        i_state
-                    }
-                };
+                // Merge output states of predecessors of bb.
+                // Once all paths have already been analyzed for a second time
+                // (iteration_count >= 3) we to abstract more aggressively in order to ensure
+                // reaching a fixed point.
+                let mut i_state = self.merge_predecessor_states(
+                    bb,
+                    &first_state,
+                    &in_state,
+                    &out_state,
+                    iteration_count >= 3,
+                );
+                self.restrict_induction_variables(bb, &mut i_state, &out_state);
                 // Analyze the basic block.
                 in_state.insert(bb, i_state.clone());
                 self.current_environment = i_state;
@@ -269,26 +277,50 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                 }
             }
             iteration_count += 1;
-            if iteration_count > 50 {
-                println!("fixed point loop diverged");
+            if changed && iteration_count >= k_limits::MAX_FIXPOINT_ITERATIONS {
+                info!("fixed point loop diverged for {:?}", self.def_id);
+                self.widen_to_top(&mut in_state, &mut out_state);
                 break;
             }
         }
 
+        debug!("Fixed point loop took {} iterations.", iteration_count);
+
+        // Widening may have made the fixed point much less precise than it needs to be.
+        // Recover some of the precision by analyzing the blocks again, starting from the fixed
+        // point, and narrowing the in states with the joins of the new out states of their
+        // predecessors. Every step leaves the states sound, so the narrowing can be stopped
+        // at any time.
+        changed = true;
+        iteration_count = 0;
+        while changed && iteration_count < k_limits::MAX_NARROWING_ITERATIONS {
+            changed = false;
+            for bb in self.mir.basic_blocks().indices() {
+                let j_state =
+                    self.merge_predecessor_states(bb, &first_state, &in_state, &out_state, false);
+                let mut i_state = in_state[&bb].narrow(&j_state);
+                i_state.entry_condition = j_state.entry_condition;
+                self.restrict_induction_variables(bb, &mut i_state, &out_state);
+                if i_state == in_state[&bb] {
+                    continue;
+                }
+                changed = true;
+                in_state.insert(bb, i_state.clone());
+                self.current_environment = i_state;
+                self.visit_basic_block(bb);
+                out_state.insert(bb, self.current_environment.clone());
+            }
+            iteration_count += 1;
+        }
+
         // Now traverse the blocks again, doing checks and emitting diagnostics.
         // in_state[bb] is now complete for every basic block bb in the body.
         debug!(
-            "Fixed point loop took {} iterations, now checking for errors.",
+            "Narrowing took {} iterations, now checking for errors.",
             iteration_count
         );
         self.check_for_errors = true;
-        for bb in self.mir.basic_blocks().indices() {
-            let i_state = (&in_state[&bb]).clone();
-            if i_state.entry_condition.as_bool_if_known().unwrap_or(true) {
-                self.current_environment = i_state;
-                self.visit_basic_block(bb);
-            }
-        }
+        self.check_blocks_in_dominator_order(&in_state);
 
         // Now create a summary of the body that can be in-lined into call sites.
         let summary = summaries::summarize(
@@ -310,6 +342,286 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         }
     }
 
+    /// Called when the fixed point loop has not converged. Gives every path that has a value in
+    /// any of the states the value Top in the in state of every block and makes the entry
+    /// conditions of all blocks, other than the entry block, unknown. Since these in states
+    /// include every state that more iterations could produce, the out states that are then
+    /// computed from them form a (very imprecise) fixed point.
+    fn widen_to_top(
+        &mut self,
+        in_state: &mut HashMap<mir::BasicBlock, Environment>,
+        out_state: &mut HashMap<mir::BasicBlock, Environment>,
+    ) {
+        let paths: HashSet<Path> = in_state
+            .values()
+            .chain(out_state.values())
+            .flat_map(|state| state.value_map.keys().cloned())
+            .collect();
+        for bb in self.mir.basic_blocks().indices() {
+            let mut i_state = in_state[&bb].clone();
+            for path in paths.iter() {
+                i_state.update_value_at(path.clone(), abstract_value::TOP);
+            }
+            if bb != mir::START_BLOCK {
+                i_state.entry_condition = abstract_value::TOP;
+            }
+            in_state.insert(bb, i_state.clone());
+            self.current_environment = i_state;
+            self.visit_basic_block(bb);
+            out_state.insert(bb, self.current_environment.clone());
+        }
+    }
+
+    /// Returns the join (or widening, if widen is true) of the out states of the predecessors
+    /// of bb, with an entry condition that is the disjunction of their exit conditions for bb.
+    /// The in state of the entry block is first_state.
+    fn merge_predecessor_states(
+        &self,
+        bb: mir::BasicBlock,
+        first_state: &Environment,
+        in_state: &HashMap<mir::BasicBlock, Environment>,
+        out_state: &HashMap<mir::BasicBlock, Environment>,
+        widen: bool,
+    ) -> Environment {
+        if bb.index() == 0 {
+            return first_state.clone();
+        }
+        let mut predecessor_states_and_conditions: Vec<(Environment, &AbstractValue)> = self
+            .mir
+            .predecessors_for(bb)
+            .iter()
+            .filter_map(|pred_bb| {
+                let pred_exit_condition = out_state[pred_bb].exit_conditions.get(&bb)?;
+                if !pred_exit_condition.as_bool_if_known().unwrap_or(true) {
+                    // The edge to bb is never taken, for instance because it leads to a match
+                    // arm for a variant that the enum cannot have.
+                    return None;
+                }
+                // The octagon of the predecessor relates the values compared by the exit
+                // condition, which may show that the edge to bb is never taken.
+                let mut pred_state = out_state[pred_bb].clone();
+                pred_state.refine_variants_for(bb);
+                pred_state.refine_octagon_with(pred_exit_condition);
+                if pred_state.octagon.is_bottom() {
+                    None
+                } else {
+                    Some((pred_state, pred_exit_condition))
+                }
+            })
+            .collect();
+        if predecessor_states_and_conditions.is_empty() {
+            // unreachable block
+            let mut i_state = in_state[&bb].clone();
+            i_state.entry_condition = abstract_value::FALSE;
+            return i_state;
+        }
+        // We want to do right associative operations and that is easier if we reverse.
+        predecessor_states_and_conditions.reverse();
+        let (p_state, pred_exit_condition) = &predecessor_states_and_conditions[0];
+        let mut i_state = p_state.clone();
+        i_state.entry_condition = pred_exit_condition.with_provenance(self.current_span);
+        for (p_state, join_condition) in predecessor_states_and_conditions.iter().skip(1) {
+            let mut j_state = if widen {
+                p_state.widen(&i_state, join_condition, bb, &self.widening_thresholds)
+            } else {
+                p_state.join(
+                    &i_state,
+                    join_condition,
+                    bb,
+                    self.options.max_expression_depth,
+                    self.options.max_expression_node_count,
+                )
+            };
+            j_state.entry_condition =
+                join_condition.or(&i_state.entry_condition, Some(self.current_span));
+            i_state = j_state;
+        }
+        i_state
+    }
+
+    /// If bb is the head of a loop, restricts the values of the induction variables of the loop
+    /// in i_state to the ranges that they have at the head of the loop, given their values and
+    /// the values of their bounds when the loop is entered. This keeps the bounds that the loop
+    /// guard puts on the variables when their values are widened. Also records the trip count
+    /// of the loop, which is the smallest of the trip counts that its induction variables imply,
+    /// and uses it to restrict the values of the counters of the loop.
+    fn restrict_induction_variables(
+        &mut self,
+        bb: mir::BasicBlock,
+        i_state: &mut Environment,
+        out_state: &HashMap<mir::BasicBlock, Environment>,
+    ) {
+        let natural_loop = match self.loops.iter().find(|l| l.head == bb) {
+            Some(natural_loop) if bb != mir::START_BLOCK => natural_loop,
+            _ => return,
+        };
+        let mut trip_count: Option<u128> = None;
+        for induction_variable in &natural_loop.induction_variables {
+            let ordinal = induction_variable.local.as_usize();
+            let path = Path::LocalVariable { ordinal };
+            let initial = self.get_loop_entry_interval(natural_loop, &path, out_state);
+            let bound = match induction_variable.bound {
+                LoopBound::Constant(value) => IntervalDomain::from(value),
+                LoopBound::Local(local) => {
+                    let bound_path = Path::LocalVariable {
+                        ordinal: local.as_usize(),
+                    };
+                    self.get_loop_entry_interval(natural_loop, &bound_path, out_state)
+                }
+            };
+            let interval = induction_variable.get_head_interval(&initial, &bound);
+            // Values beyond the range of the type would wrap around if the additions do not
+            // check for overflow, so the range would not be an invariant.
+            if interval.is_bottom() || !interval.is_contained_in(&self.get_type_for_local(ordinal))
+            {
+                continue;
+            }
+            if let Some(count) = induction_variable.get_trip_count(&initial, &bound) {
+                trip_count = Some(trip_count.map_or(count, |c| c.min(count)));
+            }
+            debug!("loop at {:?}: {:?} in {:?}", bb, path, interval);
+            i_state.restrict_value_at(&path, bb, &interval);
+        }
+        match trip_count {
+            Some(count) => {
+                debug!("loop at {:?}: at most {:?} iterations", bb, count);
+                self.trip_counts.insert(bb, count);
+            }
+            None => {
+                self.trip_counts.remove(&bb);
+            }
+        }
+        self.restrict_counters(bb, i_state, out_state);
+    }
+
+    /// If the trip count of the loop with head bb is known, restricts the values of the counters
+    /// of the loop in i_state to the ranges that they have at the head of the loop, given their
+    /// values when the loop is entered. A counter changes at most once per iteration, so adding
+    /// to it in the loop cannot overflow if the trip count keeps it within its type.
+    fn restrict_counters(
+        &self,
+        bb: mir::BasicBlock,
+        i_state: &mut Environment,
+        out_state: &HashMap<mir::BasicBlock, Environment>,
+    ) {
+        let trip_count = match self.get_trip_count(bb) {
+            Some(trip_count) => trip_count,
+            None => return,
+        };
+        let natural_loop = match self.loops.iter().find(|l| l.head == bb) {
+            Some(natural_loop) => natural_loop,
+            None => return,
+        };
+        for counter in &natural_loop.counters {
+            let ordinal = counter.local.as_usize();
+            let path = Path::LocalVariable { ordinal };
+            let initial = self.get_loop_entry_interval(natural_loop, &path, out_state);
+            let interval = counter.get_head_interval(&initial, trip_count);
+            if interval.is_bottom() || !interval.is_contained_in(&self.get_type_for_local(ordinal))
+            {
+                continue;
+            }
+            debug!("loop at {:?}: counter {:?} in {:?}", bb, path, interval);
+            i_state.restrict_value_at(&path, bb, &interval);
+        }
+    }
+
+    /// Returns the largest number of times that the loop with the given head can iterate, if the
+    /// analysis of the body has found a bound on the induction variables of the loop.
+    fn get_trip_count(&self, loop_head: mir::BasicBlock) -> Option<u128> {
+        self.trip_counts.get(&loop_head).cloned()
+    }
+
+    /// Returns the join of the intervals of the values at path in the out states of the
+    /// predecessors of the head of the loop that are not part of the loop. Returns bottom if
+    /// the value is not known to be an integer, or if the loop has not been entered yet.
+    fn get_loop_entry_interval(
+        &self,
+        natural_loop: &NaturalLoop,
+        path: &Path,
+        out_state: &HashMap<mir::BasicBlock, Environment>,
+    ) -> IntervalDomain {
+        let mut entry_interval: Option<IntervalDomain> = None;
+        for pred_bb in self.mir.predecessors_for(natural_loop.head).iter() {
+            if natural_loop.body.contains(pred_bb) {
+                continue;
+            }
+            let pred_state = &out_state[pred_bb];
+            match pred_state.exit_conditions.get(&natural_loop.head) {
+                Some(exit_condition) if exit_condition.as_bool_if_known().unwrap_or(true) => (),
+                _ => continue,
+            }
+            let interval = match pred_state.value_at(path) {
+                Some(value) => value.domain.get_as_interval(),
+                None => match path {
+                    Path::LocalVariable { ordinal } => {
+                        IntervalDomain::for_type(&self.get_type_for_local(*ordinal))
+                    }
+                    _ => interval_domain::BOTTOM,
+                },
+            };
+            if interval.is_bottom() {
+                return interval_domain::BOTTOM;
+            }
+            entry_interval = Some(match entry_interval {
+                Some(entry_interval) => entry_interval.join(&interval),
+                None => interval,
+            });
+        }
+        entry_interval.unwrap_or(interval_domain::BOTTOM)
+    }
+
+    /// Traverses the basic blocks in the order of a depth first walk of the dominator tree,
+    /// doing checks and emitting diagnostics.
+    /// Since every path to a block goes through the blocks that dominate it, the entry conditions
+    /// of the dominators hold whenever the block is entered. The walk therefore pushes a solver
+    /// context and asserts the entry condition of a block when it enters the block, and pops the
+    /// context only after all of the blocks that it dominates have been checked. The solver
+    /// queries made while checking a block then only need to assert what is specific to them.
+    fn check_blocks_in_dominator_order(
+        &mut self,
+        in_state: &HashMap<mir::BasicBlock, Environment>,
+    ) {
+        let dominators = self.mir.dominators();
+        let mut dominated_blocks: HashMap<mir::BasicBlock, Vec<mir::BasicBlock>> = HashMap::new();
+        for bb in self.mir.basic_blocks().indices() {
+            if bb != mir::START_BLOCK && dominators.is_reachable(bb) {
+                dominated_blocks
+                    .entry(dominators.immediate_dominator(bb))
+                    .or_insert_with(Vec::new)
+                    .push(bb);
+            }
+        }
+        // Each entered block is pushed again with is_exit set to true, so that its solver context
+        // is popped once all of the blocks it dominates have been visited.
+        let mut blocks_to_visit = vec![(mir::START_BLOCK, false)];
+        while let Some((bb, is_exit)) = blocks_to_visit.pop() {
+            if is_exit {
+                self.smt_solver.backtrack();
+                continue;
+            }
+            let i_state = in_state[&bb].clone();
+            let entry_cond_as_bool = i_state.entry_condition.as_bool_if_known();
+            if !entry_cond_as_bool.unwrap_or(true) {
+                // This block is unreachable and so are all of the blocks it dominates.
+                continue;
+            }
+            self.smt_solver.set_backtrack_position();
+            if entry_cond_as_bool.is_none() {
+                let ec = &i_state.entry_condition.domain.expression;
+                let smt_expr = self.smt_solver.get_as_smt_predicate(ec);
+                self.smt_solver.assert(&smt_expr);
+            }
+            self.current_environment = i_state;
+            self.visit_basic_block(bb);
+            blocks_to_visit.push((bb, true));
+            if let Some(dominated) = dominated_blocks.get(&bb) {
+                // Push them in reverse, so that they get visited in the order of their indices.
+                blocks_to_visit.extend(dominated.iter().rev().map(|b| (*b, false)));
+            }
+        }
+    }
+
     /// Use the visitor to compute the state corresponding to promoted constants.
     fn promote_constants(&mut self) -> Environment {
         let mut state_with_parameters = Environment::default();
@@ -361,6 +673,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             ref kind,
         }) = *terminator
         {
+            self.current_location = location;
             self.visit_terminator(*source_info, kind);
         }
     }
@@ -402,23 +715,31 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
     }
 
     /// Write the discriminant for a variant to the enum Place.
-    fn visit_set_discriminant(
-        &mut self,
-        place: &mir::Place<'tcx>,
-        variant_index: rustc::ty::layout::VariantIdx,
-    ) {
+    /// Enum aggregates are deaggregated into field assignments followed by this statement,
+    /// so this is also where the variant of a newly constructed enum becomes known.
+    fn visit_set_discriminant(&mut self, place: &mir::Place<'tcx>, variant_index: VariantIdx) {
         debug!(
             "default visit_set_discriminant(place: {:?}, variant_index: {:?})",
             place, variant_index
         );
         let target_path = self.visit_place(place);
-        let index_val = self
+        // Switches on the discriminant compare it with the discriminant values of the variants,
+        // which need not be the same as their indices.
+        let discriminant = match self.get_enum_def(place) {
+            Some(def) => def.discriminant_for_variant(self.tcx, variant_index).val,
+            None => variant_index.as_usize() as u128,
+        };
+        let discriminant_val = self
             .constant_value_cache
-            .get_u128_for(variant_index.as_usize() as u128)
+            .get_u128_for(discriminant)
             .clone()
             .into();
         self.current_environment
-            .update_value_at(target_path, index_val);
+            .update_value_at(target_path.clone(), discriminant_val);
+        if let Some(variants) = VariantSetDomain::singleton(variant_index.as_usize()) {
+            self.current_environment
+                .set_variants_at(target_path, variants);
+        }
     }
 
     /// Start a live range for the storage of the local.
@@ -533,6 +854,9 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
 
     /// `discr` evaluates to an integer; jump depending on its value
     /// to one of the targets, and otherwise fallback to last element of `targets`.
+    /// If `discr` is the discriminant of an enum, the variants that the enum may have when
+    /// control goes to a target are recorded, and targets that are only taken for variants
+    /// that the enum cannot have are never taken.
     ///
     /// # Arguments
     /// * `discr` - Discriminant value being tested
@@ -553,21 +877,98 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             discr, switch_ty, values, targets
         );
         let mut default_exit_condition = self.current_environment.entry_condition.clone();
+        let switched_on_enum = self.get_switched_on_enum(discr);
+        let mut untested_variants = switched_on_enum.as_ref().map(|(_, variants, _)| *variants);
         let discr = self.visit_operand(discr);
         let discr = discr.as_int_if_known().unwrap_or(discr);
+        // Several values can have the same target, which is then taken if any of them matches.
+        let mut exit_conditions: HashMap<mir::BasicBlock, AbstractValue> = HashMap::new();
+        let mut exit_variants: HashMap<mir::BasicBlock, (Path, VariantSetDomain)> = HashMap::new();
         for i in 0..values.len() {
             let val: AbstractValue = ConstantDomain::U128(values[i]).into();
-            let cond = discr.equals(&val, None);
+            let mut cond = discr.equals(&val, None);
             let not_cond = cond.not(None);
             default_exit_condition = default_exit_condition.and(&not_cond, None);
             let target = targets[i];
-            self.current_environment
-                .exit_conditions
-                .insert(target, cond);
+            if let Some((enum_path, variants, discriminants)) = &switched_on_enum {
+                let tested_variants = discriminants
+                    .iter()
+                    .position(|discriminant| *discriminant == values[i])
+                    .and_then(VariantSetDomain::singleton)
+                    .unwrap_or(variant_set_domain::BOTTOM);
+                untested_variants = untested_variants.map(|v| v.difference(&tested_variants));
+                let target_variants = variants.intersect(&tested_variants);
+                if target_variants.is_bottom() {
+                    cond = abstract_value::FALSE;
+                } else {
+                    let target_variants = match exit_variants.get(&target) {
+                        Some((_, other_variants)) => target_variants.join(other_variants),
+                        None => target_variants,
+                    };
+                    exit_variants.insert(target, (enum_path.clone(), target_variants));
+                }
+            }
+            let cond = match exit_conditions.get(&target) {
+                Some(other_cond) => other_cond.or(&cond, None),
+                None => cond,
+            };
+            exit_conditions.insert(target, cond);
         }
+        let default_target = targets[values.len()];
+        if let (Some((enum_path, ..)), Some(untested_variants)) =
+            (switched_on_enum, untested_variants)
+        {
+            if untested_variants.is_bottom() {
+                // The otherwise target of an exhaustive match is never taken.
+                default_exit_condition = abstract_value::FALSE;
+            } else {
+                exit_variants.insert(default_target, (enum_path, untested_variants));
+            }
+        }
+        exit_conditions.insert(default_target, default_exit_condition);
         self.current_environment
             .exit_conditions
-            .insert(targets[values.len()], default_exit_condition);
+            .extend(exit_conditions);
+        self.current_environment.exit_variants = exit_variants;
+    }
+
+    /// If discr is a local to which the current block assigns the discriminant of an enum,
+    /// returns the path of the enum, the variants that it may have and the discriminant values
+    /// of all of the variants of its type, in the order of the variants.
+    fn get_switched_on_enum(
+        &mut self,
+        discr: &mir::Operand<'tcx>,
+    ) -> Option<(Path, VariantSetDomain, Vec<u128>)> {
+        let discr_place = match discr {
+            mir::Operand::Copy(place) | mir::Operand::Move(place) => place,
+            mir::Operand::Constant(..) => return None,
+        };
+        let mir = self.mir;
+        let enum_place = mir[self.current_location.block]
+            .statements
+            .iter()
+            .rev()
+            .find_map(|statement| match &statement.kind {
+                mir::StatementKind::Assign(place, rvalue) if place == discr_place => {
+                    Some(&**rvalue)
+                }
+                _ => None,
+            })
+            .and_then(|rvalue| match rvalue {
+                mir::Rvalue::Discriminant(enum_place) => Some(enum_place),
+                _ => None,
+            })?;
+        let def = self.get_enum_def(enum_place)?;
+        let discriminants: Vec<u128> = def
+            .discriminants(self.tcx)
+            .map(|(_, discriminant)| discriminant.val)
+            .collect();
+        let enum_path = self.visit_place(enum_place);
+        let variants = match self.current_environment.variants.get(&enum_path) {
+            Some(variants) => *variants,
+            None => VariantSetDomain::all(discriminants.len())?,
+        };
+        Some((enum_path, variants, discriminants))
     }
 
     /// Indicates that the landing pad is finished and unwinding should
@@ -594,6 +995,9 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                 self.exit_environment = self.current_environment.join(
                     &self.exit_environment,
                     &self.current_environment.entry_condition,
+                    self.current_location.block,
+                    self.options.max_expression_depth,
+                    self.options.max_expression_node_count,
                 );
             }
         }
@@ -674,8 +1078,17 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         let func_to_call = self.visit_operand(func);
         let actual_args: Vec<AbstractValue> =
             args.iter().map(|arg| self.visit_operand(arg)).collect();
+        if self.try_to_model_wrapping_operation(&func_to_call, &actual_args, destination)
+            || self.try_to_model_slice_len(&func_to_call, &actual_args, destination)
+            || self.try_to_model_str_slice(&func_to_call, args, &actual_args, destination)
+            || self.try_to_model_char_indices(&func_to_call, args, &actual_args, destination)
+        {
+            self.transfer_and_refine_cleanup_state(cleanup);
+            return;
+        }
         let function_summary = self.get_function_summary(&func_to_call);
         if self.check_for_errors {
+            self.check_for_unwrap_of_none_or_err(&func_to_call, args);
             self.check_function_preconditions(&actual_args, &function_summary);
         }
         self.transfer_and_refine_normal_return_state(destination, &actual_args, &function_summary);
@@ -685,12 +1098,441 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         }
     }
 
+    /// If the function to call is one of the wrapping arithmetic operations of the standard
+    /// library, such as u64::wrapping_add, assigns the result of the operation, reduced modulo
+    /// 2^n into the range of the result type, to the destination and returns true.
+    /// This is much more precise than the summary of the function, which is usually not available,
+    /// and lets the interval domain see the wrap around.
+    fn try_to_model_wrapping_operation(
+        &mut self,
+        func_to_call: &AbstractValue,
+        actual_args: &[AbstractValue],
+        destination: &Option<(mir::Place<'tcx>, mir::BasicBlock)>,
+    ) -> bool {
+        let (place, target) = match destination {
+            Some((place, target)) if actual_args.len() == 2 => (place, target),
+            _ => return false,
+        };
+        let summary_cache_key = match &func_to_call.domain.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Function {
+                summary_cache_key, ..
+            }) if summary_cache_key.starts_with("core.")
+                || summary_cache_key.starts_with("std.") =>
+            {
+                summary_cache_key
+            }
+            _ => return false,
+        };
+        let result_type = self.get_place_type(place);
+        if !result_type.is_integer() {
+            return false;
+        }
+        let (left, right) = (&actual_args[0], &actual_args[1]);
+        let span = Some(self.current_span);
+        let result = if summary_cache_key.ends_with(".wrapping_add") {
+            left.add(right, span)
+        } else if summary_cache_key.ends_with(".wrapping_mul") {
+            left.mul(right, span)
+        } else if summary_cache_key.ends_with(".wrapping_sub") {
+            left.sub(right, span)
+        } else {
+            return false;
+        };
+        let target_path = self.visit_place(place);
+        self.current_environment
+            .update_value_at(target_path, result.cast(result_type, span));
+        self.current_environment
+            .exit_conditions
+            .insert(*target, self.current_environment.entry_condition.clone());
+        true
+    }
+
+    /// If the function to call is the len method of slices or of string slices, assigns the length
+    /// of the slice that the argument refers to, to the destination and returns true. This makes
+    /// the result the same value as the length that is used by the bounds checks of the slice, so
+    /// that the octagon can relate the result to the indices that are checked.
+    fn try_to_model_slice_len(
+        &mut self,
+        func_to_call: &AbstractValue,
+        actual_args: &[AbstractValue],
+        destination: &Option<(mir::Place<'tcx>, mir::BasicBlock)>,
+    ) -> bool {
+        let (place, target) = match destination {
+            Some((place, target)) if actual_args.len() == 1 => (place, target),
+            _ => return false,
+        };
+        match &func_to_call.domain.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Function {
+                summary_cache_key, ..
+            }) if (summary_cache_key.starts_with("core.slice.")
+                || summary_cache_key.starts_with("core.str."))
+                && summary_cache_key.ends_with(".len") => {}
+            _ => return false,
+        };
+        let len_value = match self.get_slice_len(&actual_args[0]) {
+            Some(len_value) => len_value,
+            None => return false,
+        };
+        let target_path = self.visit_place(place);
+        self.current_environment
+            .update_value_at(target_path, len_value);
+        self.current_environment
+            .exit_conditions
+            .insert(*target, self.current_environment.entry_condition.clone());
+        true
+    }
+
+    /// Returns the length of the given slice or string slice, if it is a constant string, a
+    /// slice of a string or a reference to a slice.
+    fn get_slice_len(&mut self, slice: &AbstractValue) -> Option<AbstractValue> {
+        let slice = self.dereference_str(slice);
+        match &slice.domain.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Str(s)) => Some(
+                self.constant_value_cache
+                    .get_u128_for(s.len() as u128)
+                    .clone()
+                    .into(),
+            ),
+            Expression::StrSlice { start, end, .. } => Some(AbstractValue {
+                provenance: slice.provenance.clone(),
+                domain: end.sub(start),
+            }),
+            Expression::Reference(path) => Some(self.get_len(path.clone())),
+            // A path whose value is not known to be a reference is dereferenced by dropping the
+            // deref, just like visit_place does.
+            Expression::Variable { path, .. } => Some(self.get_len((**path).clone())),
+            _ => None,
+        }
+    }
+
+    /// Returns the string slice at the path that the given value refers to, if it is a constant or
+    /// a slice of a string, since a reference to it is just a reborrow. Otherwise returns value.
+    fn dereference_str(&self, value: &AbstractValue) -> AbstractValue {
+        if let Expression::Reference(path) = &value.domain.expression {
+            if let Some(string) = self.current_environment.value_at(path) {
+                match string.domain.expression {
+                    Expression::CompileTimeConstant(ConstantDomain::Str(..))
+                    | Expression::StrSlice { .. } => return string.clone(),
+                    _ => (),
+                }
+            }
+        }
+        value.clone()
+    }
+
+    /// If the function to call is the index method of string slices and the index is a range of
+    /// byte offsets, assigns the slice of the string to the destination and returns true.
+    /// Since slicing panics unless the range is within bounds, the range is known to be within
+    /// bounds after the call.
+    fn try_to_model_str_slice(
+        &mut self,
+        func_to_call: &AbstractValue,
+        args: &[mir::Operand<'tcx>],
+        actual_args: &[AbstractValue],
+        destination: &Option<(mir::Place<'tcx>, mir::BasicBlock)>,
+    ) -> bool {
+        let (place, target) = match destination {
+            Some((place, target)) if actual_args.len() == 2 => (place, target),
+            _ => return false,
+        };
+        match &func_to_call.domain.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Function {
+                summary_cache_key, ..
+            }) if summary_cache_key.starts_with("core.")
+                && summary_cache_key.ends_with(".Index.index") => {}
+            _ => return false,
+        };
+        if !self.is_str_reference(&args[0]) {
+            return false;
+        }
+        let string = &self.dereference_str(&actual_args[0]);
+        let (mut start, mut end) = match self.get_str_range(&args[1], string) {
+            Some(range) => range,
+            None => return false,
+        };
+        let mut len_value = match self.get_slice_len(string) {
+            Some(len_value) => len_value,
+            None => return false,
+        };
+        let in_range = start
+            .less_or_equal(&mut end, None)
+            .and(&end.clone().less_or_equal(&mut len_value, None), None);
+        if self.check_for_errors {
+            self.check_str_slice(string, &start, &end, &len_value, &in_range);
+        }
+        let slice = string.str_slice(&start, &end, Some(self.current_span));
+        let target_path = self.visit_place(place);
+        self.current_environment.update_value_at(target_path, slice);
+        let exit_condition = self
+            .current_environment
+            .entry_condition
+            .and(&in_range, None);
+        self.current_environment
+            .exit_conditions
+            .insert(*target, exit_condition);
+        true
+    }
+
+    /// Returns the start and the end of the byte range that the given operand denotes, if it is
+    /// a range of one of the types that can be used to slice the given string.
+    fn get_str_range(
+        &mut self,
+        range: &mir::Operand<'tcx>,
+        string: &AbstractValue,
+    ) -> Option<(AbstractValue, AbstractValue)> {
+        let range_key = self.get_adt_key(range)?;
+        let range_path = match range {
+            mir::Operand::Copy(place) | mir::Operand::Move(place) => Some(self.visit_place(place)),
+            mir::Operand::Constant(..) => None,
+        };
+        let zero: AbstractValue = self.constant_value_cache.get_u128_for(0).clone().into();
+        match range_key.as_str() {
+            "core.ops.range.Range" => {
+                let range_path = range_path?;
+                Some((
+                    self.get_field_value(&range_path, 0),
+                    self.get_field_value(&range_path, 1),
+                ))
+            }
+            "core.ops.range.RangeFrom" => Some((
+                self.get_field_value(&range_path?, 0),
+                self.get_slice_len(string)?,
+            )),
+            "core.ops.range.RangeFull" => Some((zero, self.get_slice_len(string)?)),
+            "core.ops.range.RangeTo" => Some((zero, self.get_field_value(&range_path?, 0))),
+            "core.ops.range.RangeToInclusive" => {
+                let one: AbstractValue = self.constant_value_cache.get_u128_for(1).clone().into();
+                let end = self.get_field_value(&range_path?, 0).add(&one, None);
+                Some((zero, end))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the usize field with the given ordinal of the struct at path.
+    fn get_field_value(&mut self, path: &Path, ordinal: usize) -> AbstractValue {
+        let field_path = Path::QualifiedPath {
+            length: path.path_length() + 1,
+            qualifier: box path.clone(),
+            selector: box PathSelector::Field(ordinal),
+        };
+        self.lookup_path_and_refine_result(field_path, ExpressionType::Usize)
+    }
+
+    /// Slicing a string panics if the range is not within bounds, or if it does not start and end
+    /// at char boundaries, so warn if that might happen. An index that is not known to be a char
+    /// boundary is reported as a possible error, unless it is zero, the length of the string or
+    /// an offset returned by the CharIndices iterator of the string.
+    fn check_str_slice(
+        &mut self,
+        string: &AbstractValue,
+        start: &AbstractValue,
+        end: &AbstractValue,
+        len_value: &AbstractValue,
+        in_range: &AbstractValue,
+    ) {
+        let (mut in_range_as_bool, entry_cond_as_bool) =
+            self.check_condition_value_and_reachability(in_range);
+        if !entry_cond_as_bool.unwrap_or(true) {
+            // We never get here, so nothing to report.
+            return;
+        }
+        if in_range_as_bool.is_none() {
+            // The range may be one of the conjuncts of the entry condition, for instance because
+            // its end is an offset returned by CharIndices::next.
+            let entry_condition = &self.current_environment.entry_condition.domain;
+            if entry_condition.implies(&in_range.domain) {
+                in_range_as_bool = Some(true);
+            }
+        }
+        let warning = match in_range_as_bool {
+            Some(true) => {
+                let start_is_char_boundary = self.is_char_boundary(string, start, len_value);
+                let end_is_char_boundary = self.is_char_boundary(string, end, len_value);
+                match (start_is_char_boundary, end_is_char_boundary) {
+                    (Some(true), Some(true)) => return,
+                    (Some(false), _) | (_, Some(false)) => {
+                        "string slice index is not a char boundary"
+                    }
+                    _ if self.report_possible_errors() => {
+                        "possible string slice index is not a char boundary"
+                    }
+                    _ => return,
+                }
+            }
+            Some(false) => "string slice index is out of range",
+            None if self.report_possible_errors() => "possible string slice index is out of range",
+            None => return,
+        };
+        let span = self.current_span;
+        let mut warning = self.session.struct_span_warn(span, warning);
+        (self.emit_diagnostic)(&mut warning, &mut self.buffered_diagnostics);
+    }
+
+    /// Returns Some(true) if index is known to be a char boundary of the given string slice,
+    /// Some(false) if it is known not to be one and None if this is not known. The length of the
+    /// slice and the offsets returned by its CharIndices iterator are always char boundaries.
+    fn is_char_boundary(
+        &self,
+        string: &AbstractValue,
+        index: &AbstractValue,
+        len_value: &AbstractValue,
+    ) -> Option<bool> {
+        if index.domain == len_value.domain {
+            return Some(true);
+        }
+        if let Expression::Variable { path, .. } = &index.domain.expression {
+            if self.char_boundaries.get(path) == Some(&string.domain) {
+                return Some(true);
+            }
+        }
+        string
+            .domain
+            .get_as_string()
+            .is_char_boundary(&index.domain.get_as_interval())
+    }
+
+    /// If the function to call creates a str::CharIndices iterator, or turns it into an iterator
+    /// or advances it, models the call and returns true. The iterator is represented by the
+    /// string slice that it iterates over, and the byte offsets that it returns are at most the
+    /// length of the string slice. Since this holds for the offset that is part of the result of
+    /// next whether or not there is a result, it is added to the path condition right away.
+    /// The offset is also recorded as a char boundary of the string slice.
+    fn try_to_model_char_indices(
+        &mut self,
+        func_to_call: &AbstractValue,
+        args: &[mir::Operand<'tcx>],
+        actual_args: &[AbstractValue],
+        destination: &Option<(mir::Place<'tcx>, mir::BasicBlock)>,
+    ) -> bool {
+        let (place, target) = match destination {
+            Some((place, target)) if actual_args.len() == 1 => (place, target),
+            _ => return false,
+        };
+        let summary_cache_key = match &func_to_call.domain.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Function {
+                summary_cache_key, ..
+            }) if summary_cache_key.starts_with("core.") => summary_cache_key,
+            _ => return false,
+        };
+        let target_path = self.visit_place(place);
+        let mut exit_condition = self.current_environment.entry_condition.clone();
+        if summary_cache_key.starts_with("core.str.")
+            && summary_cache_key.ends_with(".char_indices")
+        {
+            let string = self.dereference_str(&actual_args[0]);
+            self.current_environment
+                .update_value_at(target_path, string);
+        } else if self
+            .get_adt_key(&args[0])
+            .map_or(true, |key| key != "core.str.CharIndices")
+        {
+            return false;
+        } else if summary_cache_key.ends_with(".IntoIterator.into_iter") {
+            self.current_environment
+                .update_value_at(target_path, actual_args[0].clone());
+        } else if summary_cache_key.ends_with(".Iterator.next") {
+            let string = match &actual_args[0].domain.expression {
+                Expression::Reference(path) => match self.current_environment.value_at(path) {
+                    Some(string) => string.clone(),
+                    None => return false,
+                },
+                _ => return false,
+            };
+            let mut len_value = match self.get_slice_len(&string) {
+                Some(len_value) => len_value,
+                None => return false,
+            };
+            // The offset is the first field of the tuple in the Some variant of the result.
+            let mut offset_path = target_path;
+            for selector in &[
+                PathSelector::Downcast(1),
+                PathSelector::Field(0),
+                PathSelector::Field(0),
+            ] {
+                offset_path = Path::QualifiedPath {
+                    length: offset_path.path_length() + 1,
+                    qualifier: box offset_path,
+                    selector: box selector.clone(),
+                };
+            }
+            // Store the offset at its path, so that it is copied along with the tuple.
+            let mut offset: AbstractValue = Expression::Variable {
+                path: box offset_path.clone(),
+                var_type: ExpressionType::Usize,
+            }
+            .into();
+            self.current_environment
+                .update_value_at(offset_path.clone(), offset.clone());
+            self.char_boundaries
+                .insert(offset_path, string.domain.clone());
+            exit_condition = exit_condition.and(&offset.less_or_equal(&mut len_value, None), None);
+        } else {
+            return false;
+        }
+        self.current_environment
+            .exit_conditions
+            .insert(*target, exit_condition);
+        true
+    }
+
+    /// If the function to call is Option::unwrap or Result::unwrap and the variant set of the
+    /// enum that it unwraps shows that the enum is None or an Err, respectively, reports that
+    /// the call panics.
+    fn check_for_unwrap_of_none_or_err(
+        &mut self,
+        func_to_call: &AbstractValue,
+        args: &[mir::Operand<'tcx>],
+    ) {
+        debug_assert!(self.check_for_errors);
+        let (failing_variant, warning) = match &func_to_call.domain.expression {
+            Expression::CompileTimeConstant(ConstantDomain::Function {
+                summary_cache_key, ..
+            }) if summary_cache_key.ends_with(".unwrap") => {
+                if summary_cache_key.starts_with("core.option.") {
+                    (0, "called `Option::unwrap()` on a `None` value")
+                } else if summary_cache_key.starts_with("core.result.") {
+                    (1, "called `Result::unwrap()` on an `Err` value")
+                } else {
+                    return;
+                }
+            }
+            _ => return,
+        };
+        let enum_path = match args.get(0) {
+            Some(mir::Operand::Copy(place)) | Some(mir::Operand::Move(place)) => {
+                self.visit_place(place)
+            }
+            _ => return,
+        };
+        let variant = self
+            .current_environment
+            .variants
+            .get(&enum_path)
+            .and_then(VariantSetDomain::as_singleton);
+        if variant != Some(failing_variant) {
+            return;
+        }
+        let mut entry_cond_as_bool = self.current_environment.entry_condition.as_bool_if_known();
+        if entry_cond_as_bool.is_none() && self.smt_solver.solve() == SmtResult::Unsatisfiable {
+            // The entry condition is already asserted in the solver context.
+            entry_cond_as_bool = Some(false);
+        }
+        if !entry_cond_as_bool.unwrap_or(true) {
+            // We never get to this call, so nothing to report.
+            return;
+        }
+        let span = self.current_span;
+        let mut warning = self.session.struct_span_warn(span, warning);
+        (self.emit_diagnostic)(&mut warning, &mut self.buffered_diagnostics);
+    }
+
     /// Returns a summary of the function to call, obtained from the summary cache.
     fn get_function_summary(&mut self, func_to_call: &AbstractValue) -> Summary {
         if let Expression::CompileTimeConstant(ConstantDomain::Function {
             def_id: Some(def_id),
             ..
-        }) = func_to_call.domain.expression
+        }) = *func_to_call.domain.expression
         {
             self.summary_cache
                 .get_summary_for(def_id, Some(self.def_id))
@@ -729,16 +1571,21 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                 if entry_cond_as_bool.unwrap_or(false) {
                     // We always get here if the function is called, and the precondition is always
                     // false, so complain loudly.
-                    self.emit_diagnostic_for_precondition(precondition, &message);
+                    self.emit_diagnostic_for_precondition(precondition, &message, None);
                     // Don't promote a false precondition. The callers cannot possibly satisfy it,
                     // so there is no point in complaining at call sites.
                     continue;
                 } else {
                     // We might never get here since it depends on the parameter values used to call
                     // this function. If the function is public, let's warn that we might get here.
-                    if is_public(self.def_id, &self.tcx) {
+                    if self.report_possible_errors() {
                         let warning = format!("possible error: {}", message.as_str());
-                        self.emit_diagnostic_for_precondition(precondition, &warning);
+                        let counterexample = self.get_counterexample_note(&refined_precondition);
+                        self.emit_diagnostic_for_precondition(
+                            precondition,
+                            &warning,
+                            counterexample,
+                        );
                     } else {
                         // Since the function is not public, we assume that we get to see
                         // every call to this function, so just rely on the inferred precondition.
@@ -762,7 +1609,13 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
 
     /// Emit a diagnostic to the effect that the current call might violate a the given precondition
     /// of the called function. Use the provenance of the precondition to point out related locations.
-    fn emit_diagnostic_for_precondition(&mut self, precondition: &AbstractValue, diagnostic: &str) {
+    /// If a counterexample is provided, it is attached to the diagnostic as a note.
+    fn emit_diagnostic_for_precondition(
+        &mut self,
+        precondition: &AbstractValue,
+        diagnostic: &str,
+        counterexample: Option<String>,
+    ) {
         // This call is definitely going to be reached
         let span = self.current_span;
         let mut err = self.session.struct_span_warn(span, diagnostic);
@@ -771,9 +1624,84 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         for related_span in related_spans.iter() {
             err.span_note(**related_span, "related location");
         }
+        if let Some(counterexample) = counterexample {
+            err.note(&counterexample);
+        }
         (self.emit_diagnostic)(&mut err, &mut self.buffered_diagnostics);
     }
 
+    /// If the SMT solver can find values for the variables in the current entry condition and
+    /// the given condition, such that the entry condition is true and the given condition is false,
+    /// returns a note that lists the values, for example "fails when i = 3, arr.len() = 2".
+    /// The entry condition is expected to have been asserted in the current solver context.
+    fn get_counterexample_note(&mut self, cond_val: &AbstractValue) -> Option<String> {
+        let not_cond_smt_expr = {
+            let not_cond = cond_val.not(None);
+            self.smt_solver.get_as_smt_predicate(&not_cond.domain.expression)
+        };
+        self.smt_solver.set_backtrack_position();
+        self.smt_solver.assert(&not_cond_smt_expr);
+        let model = if self.smt_solver.solve() == SmtResult::Satisfiable {
+            self.smt_solver.get_model()
+        } else {
+            Vec::new()
+        };
+        self.smt_solver.backtrack();
+        if model.is_empty() {
+            return None;
+        }
+        let values: Vec<String> = model
+            .iter()
+            .map(|(path, value)| {
+                let value = match value {
+                    ConstantDomain::Char(ch) => format!("{:?}", ch),
+                    ConstantDomain::False => String::from("false"),
+                    ConstantDomain::I128(val) => val.to_string(),
+                    ConstantDomain::True => String::from("true"),
+                    ConstantDomain::U128(val) => val.to_string(),
+                    _ => format!("{:?}", value),
+                };
+                format!("{} = {}", self.get_path_as_source_string(path), value)
+            })
+            .collect();
+        Some(format!("fails when {}", values.join(", ")))
+    }
+
+    /// Returns a string that describes the place identified by the given path, using the names
+    /// of variables as they appear in the source code, where possible.
+    fn get_path_as_source_string(&self, path: &Path) -> String {
+        match path {
+            Path::LocalVariable { ordinal } => {
+                let loc = &self.mir.local_decls[mir::Local::from(*ordinal)];
+                match loc.name {
+                    Some(name) => name.to_string(),
+                    None => format!("_{}", ordinal),
+                }
+            }
+            Path::QualifiedPath {
+                qualifier,
+                selector,
+                ..
+            } => {
+                let qualifier = self.get_path_as_source_string(qualifier);
+                match **selector {
+                    PathSelector::ArrayLength => format!("{}.len()", qualifier),
+                    PathSelector::Deref => format!("*{}", qualifier),
+                    PathSelector::Field(ordinal) => format!("{}.{}", qualifier, ordinal),
+                    PathSelector::Index(..) => format!("{}[..]", qualifier),
+                    PathSelector::Downcast(ordinal) => {
+                        format!("{} as variant#{}", qualifier, ordinal)
+                    }
+                    _ => format!("{}[{:?}]", qualifier, selector),
+                }
+            }
+            Path::StaticVariable {
+                summary_cache_key, ..
+            } => summary_cache_key.clone(),
+            _ => format!("{:?}", path),
+        }
+    }
+
     /// Updates the current state to reflect the effects of a normal return from the function call.
     fn transfer_and_refine_normal_return_state(
         &mut self,
@@ -849,10 +1777,8 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             if cache.check_if_std_panicking_begin_panic_function(&fun) {
                 let mut path_cond = self.current_environment.entry_condition.as_bool_if_known();
                 if path_cond.is_none() {
-                    // Try the SMT solver
-                    let path_expr = &self.current_environment.entry_condition.domain.expression;
-                    let path_smt = self.smt_solver.get_as_smt_predicate(path_expr);
-                    if self.smt_solver.solve_expression(&path_smt) == SmtResult::Unsatisfiable {
+                    // Try the SMT solver. The entry condition is already asserted in its context.
+                    if self.smt_solver.solve() == SmtResult::Unsatisfiable {
                         path_cond = Some(false)
                     }
                 }
@@ -875,7 +1801,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                 };
                 let span = self.current_span;
 
-                if path_cond.unwrap_or(false) && is_public(self.def_id, &self.tcx) {
+                if path_cond.unwrap_or(false) && self.assume_function_is_called() {
                     // We always get to this call and we have to assume that the function will
                     // get called, so keep the message certain.
                     let mut err = self.session.struct_span_warn(span, msg.as_str());
@@ -894,8 +1820,10 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
 
                     let mut maybe_message = String::from("possible error: ");
                     maybe_message.push_str(msg.as_str());
-                    let mut err = self.session.struct_span_warn(span, maybe_message.as_str());
-                    (self.emit_diagnostic)(&mut err, &mut self.buffered_diagnostics);
+                    if self.options.diag_level != DiagLevel::Relaxed {
+                        let mut err = self.session.struct_span_warn(span, maybe_message.as_str());
+                        (self.emit_diagnostic)(&mut err, &mut self.buffered_diagnostics);
+                    }
 
                     // We also push a precondition in both cases.
                     self.preconditions.push((
@@ -988,13 +1916,21 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
 
                 // At this point, we don't know that this assert is unreachable and we don't know
                 // that the condition is as expected, so we need to warn about it somewhere.
-                if is_public(self.def_id, &self.tcx) {
+                if self.report_possible_errors() {
                     // We expect public functions to have programmer supplied preconditions
                     // that preclude any assertions from failing. So, if at this stage we get to
                     // complain a bit.
                     let warning = format!("possible {}", msg.description());
                     let span = self.current_span;
                     let mut warning = self.session.struct_span_warn(span, warning.as_str());
+                    let expected_cond = if expected {
+                        cond_val.clone()
+                    } else {
+                        cond_val.not(None)
+                    };
+                    if let Some(counterexample) = self.get_counterexample_note(&expected_cond) {
+                        warning.note(&counterexample);
+                    }
                     (self.emit_diagnostic)(&mut warning, &mut self.buffered_diagnostics);
                 }
 
@@ -1026,19 +1962,18 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
     ) -> (Option<bool>, Option<bool>) {
         // Check if the condition is always true (or false) if we get here.
         let mut cond_as_bool = cond_val.as_bool_if_known();
+        if cond_as_bool.is_none() {
+            // The condition may compare values whose relation is known to the octagon.
+            cond_as_bool = self.current_environment.decide_using_octagon(cond_val);
+        }
         // Check if we can prove that every call to the current function will reach this call site.
         let mut entry_cond_as_bool = self.current_environment.entry_condition.as_bool_if_known();
         // Use SMT solver if need be.
         if entry_cond_as_bool.is_none() {
             // The abstract domains are unable to decide if the entry condition is always true.
             // (If it could decide that the condition is always false, we wouldn't be here.)
-            // See if the SMT solver can prove that the entry condition is always true.
-            let smt_expr = {
-                let ec = &self.current_environment.entry_condition.domain.expression;
-                self.smt_solver.get_as_smt_predicate(ec)
-            };
-            self.smt_solver.set_backtrack_position();
-            self.smt_solver.assert(&smt_expr);
+            // The entry condition has already been asserted in the current solver context,
+            // so see if the SMT solver can prove that it is always false.
             if self.smt_solver.solve() == SmtResult::Unsatisfiable {
                 // The solver can prove that the entry condition is always false.
                 entry_cond_as_bool = Some(false);
@@ -1047,11 +1982,12 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                 // The abstract domains are unable to decide what the value of cond is.
                 cond_as_bool = self.solve_condition(cond_val)
             }
-            self.smt_solver.backtrack();
         }
         (cond_as_bool, entry_cond_as_bool)
     }
 
+    /// Uses the SMT solver to decide if cond_val is always true (or false) in the current solver
+    /// context, which includes the current entry condition.
     fn solve_condition(&mut self, cond_val: &AbstractValue) -> Option<bool> {
         let ce = &cond_val.domain.expression;
         let cond_smt_expr = self.smt_solver.get_as_smt_predicate(ce);
@@ -1136,7 +2072,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                     literal,
                 } = constant.borrow();
                 let const_value: AbstractValue = self.visit_constant(ty, *user_ty, literal);
-                if let Expression::AbstractHeapAddress(ordinal) = const_value.domain.expression {
+                if let Expression::AbstractHeapAddress(ordinal) = *const_value.domain.expression {
                     let rtype = ExpressionType::NonPrimitive;
                     let rpath = Path::AbstractHeapAddress { ordinal };
                     self.copy_or_move_elements(path, rpath, rtype, false);
@@ -1290,15 +2226,29 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             value_map = value_map.insert(qualified_path, value.with_provenance(self.current_span));
         }
         // Now move (rpath, value) itself.
-        let value = self.lookup_path_and_refine_result(rpath.clone(), rtype);
+        let value = self.lookup_path_and_refine_result(rpath.clone(), rtype.clone());
         if move_elements {
             debug!("moving {:?} to {:?}", value, target_path);
             value_map = value_map.remove(&rpath);
         } else {
             debug!("copying {:?} to {:?}", value, target_path);
         };
-        value_map = value_map.insert(target_path, value.with_provenance(self.current_span));
+        value_map = value_map.insert(
+            target_path.clone(),
+            value.with_provenance(self.current_span),
+        );
         self.current_environment.value_map = value_map;
+        // The octagon only relates integers, but whatever used to be at target_path is gone.
+        let octagon = &mut self.current_environment.octagon;
+        if rtype.is_integer() {
+            octagon.assign(&target_path, &rpath, 0);
+        } else {
+            octagon.forget(&target_path);
+        }
+        if move_elements {
+            octagon.forget(&rpath);
+        }
+        self.current_environment.copy_variants(&rpath, &target_path);
     }
 
     /// For each (path', value) pair in the environment where path' is rooted in place,
@@ -1321,6 +2271,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             path, operand, count
         );
         self.visit_operand(operand);
+        self.insert_widening_threshold(i128::from(count));
         //todo: needs #62
         // get a heap address and put it in Path::AbstractHeapAddress
         // get an abs value for x
@@ -1373,7 +2324,8 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         self.lookup_path_and_refine_result(length_path, ExpressionType::Usize)
     }
 
-    /// path = operand. The cast is a no-op for the interpreter.
+    /// path = operand as ty. Only numeric conversions change the value, the other casts are
+    /// no-ops for the interpreter.
     fn visit_cast(
         &mut self,
         path: Path,
@@ -1385,7 +2337,50 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             "default visit_cast(path: {:?}, cast_kind: {:?}, operand: {:?}, ty: {:?})",
             path, cast_kind, operand, ty
         );
-        self.visit_use(path, operand)
+        match cast_kind {
+            mir::CastKind::Misc => {
+                let target_type: ExpressionType = (&ty.sty).into();
+                if target_type == ExpressionType::NonPrimitive {
+                    self.visit_use(path, operand);
+                } else {
+                    let value = self.visit_operand(operand);
+                    if self.check_for_errors && target_type.is_integer() {
+                        self.check_float_to_int_cast(&value, &target_type);
+                    }
+                    let result = value.cast(target_type, Some(self.current_span));
+                    self.current_environment.update_value_at(path, result);
+                }
+            }
+            _ => self.visit_use(path, operand),
+        }
+    }
+
+    /// Float to integer casts saturate, so warn if value is a floating point value that might
+    /// not fit into target_type. The comparisons in the entry condition constrain the value.
+    fn check_float_to_int_cast(&mut self, value: &AbstractValue, target_type: &ExpressionType) {
+        let entry_condition = &self.current_environment.entry_condition;
+        if !entry_condition.as_bool_if_known().unwrap_or(true) {
+            // We never get here, so nothing to report.
+            return;
+        }
+        let mut bounds = HashMap::new();
+        entry_condition
+            .domain
+            .add_float_interval_bounds(true, &mut bounds);
+        let float_interval = value.domain.get_as_float_interval_using(&bounds);
+        if float_interval.is_bottom() {
+            // Not a floating point value.
+            return;
+        }
+        let warning = match float_interval.fits_in(target_type) {
+            Some(true) => return,
+            Some(false) => "float to integer cast saturates",
+            None if self.report_possible_errors() => "float to integer cast might saturate",
+            None => return,
+        };
+        let span = self.current_span;
+        let mut warning = self.session.struct_span_warn(span, warning);
+        (self.emit_diagnostic)(&mut warning, &mut self.buffered_diagnostics);
     }
 
     /// Apply the given binary operator to the two operands and assign result to path.
@@ -1402,6 +2397,16 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         );
         let mut left = self.visit_operand(left_operand);
         let mut right = self.visit_operand(right_operand);
+        if self.check_for_errors {
+            match bin_op {
+                mir::BinOp::Eq
+                | mir::BinOp::Ge
+                | mir::BinOp::Gt
+                | mir::BinOp::Le
+                | mir::BinOp::Lt => self.check_for_nan_operand(&left, &right),
+                _ => (),
+            }
+        }
         let result = match bin_op {
             mir::BinOp::Add => left.add(&right, Some(self.current_span)),
             mir::BinOp::BitAnd => left.bit_and(&right, Some(self.current_span)),
@@ -1417,7 +2422,11 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
             mir::BinOp::Ne => left.not_equals(&right, Some(self.current_span)),
             mir::BinOp::Offset => left.offset(&right, Some(self.current_span)),
             mir::BinOp::Rem => left.rem(&right, Some(self.current_span)),
-            mir::BinOp::Shl => left.shl(&right, Some(self.current_span)),
+            mir::BinOp::Shl => {
+                // We assume that path is a temporary used to track the operation result.
+                let target_type = self.get_target_path_type(&path);
+                left.shl(&right, target_type, Some(self.current_span))
+            }
             mir::BinOp::Shr => {
                 // We assume that path is a temporary used to track the operation result.
                 let target_type = self.get_target_path_type(&path);
@@ -1428,6 +2437,29 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         self.current_environment.update_value_at(path, result);
     }
 
+    /// Warns about a comparison that is always false because one of its operands is NaN.
+    fn check_for_nan_operand(&mut self, left: &AbstractValue, right: &AbstractValue) {
+        if !self
+            .current_environment
+            .entry_condition
+            .as_bool_if_known()
+            .unwrap_or(true)
+        {
+            // We never get here, so nothing to report.
+            return;
+        }
+        if left.domain.get_as_float_interval().is_nan()
+            || right.domain.get_as_float_interval().is_nan()
+        {
+            let span = self.current_span;
+            let mut warning = self.session.struct_span_warn(
+                span,
+                "this comparison is always false because an operand is NaN",
+            );
+            (self.emit_diagnostic)(&mut warning, &mut self.buffered_diagnostics);
+        }
+    }
+
     /// Apply the given binary operator to the two operands, with overflow checking where appropriate
     /// and assign the result to path.
     fn visit_checked_binary_op(
@@ -1452,7 +2484,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                 (&mut left).mul_overflows(&mut right, target_type, Some(self.current_span)),
             ),
             mir::BinOp::Shl => (
-                left.shl(&right, Some(self.current_span)),
+                left.shl(&right, target_type.clone(), Some(self.current_span)),
                 left.shl_overflows(&mut right, target_type, Some(self.current_span)),
             ),
             mir::BinOp::Shr => (
@@ -1533,7 +2565,25 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         );
         let adtd_path = self.visit_place(place);
         let adtd_type = self.get_place_type(place);
-        let adtd_value = self.lookup_path_and_refine_result(adtd_path, adtd_type);
+        let mut adtd_value = self.lookup_path_and_refine_result(adtd_path.clone(), adtd_type);
+        if let Some(def) = self.get_enum_def(place) {
+            // If the enum can have only one variant, its discriminant is known, even if nothing
+            // else about its value is.
+            let variant = match self.current_environment.variants.get(&adtd_path) {
+                Some(variants) => variants.as_singleton(),
+                None if def.variants.len() == 1 => Some(0),
+                None => None,
+            };
+            if let Some(variant) = variant {
+                let variant_index = VariantIdx::from_usize(variant);
+                let discriminant = def.discriminant_for_variant(self.tcx, variant_index).val;
+                adtd_value = self
+                    .constant_value_cache
+                    .get_u128_for(discriminant)
+                    .clone()
+                    .into();
+            }
+        }
         self.current_environment.update_value_at(path, adtd_value);
     }
 
@@ -1614,8 +2664,8 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
     fn visit_operand(&mut self, operand: &mir::Operand<'tcx>) -> AbstractValue {
         let span = self.current_span;
         let (expression_domain, span) = match operand {
-            mir::Operand::Copy(place) => (self.visit_copy(place).domain.expression, span),
-            mir::Operand::Move(place) => (self.visit_move(place).domain.expression, span),
+            mir::Operand::Copy(place) => (self.visit_copy(place).domain, span),
+            mir::Operand::Move(place) => (self.visit_move(place).domain, span),
             mir::Operand::Constant(constant) => {
                 let mir::Constant {
                     span,
@@ -1624,12 +2674,12 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                     literal,
                 } = constant.borrow();
                 let const_value = self.visit_constant(ty, *user_ty, literal);
-                (const_value.domain.expression, *span)
+                (const_value.domain, *span)
             }
         };
         AbstractValue {
             provenance: vec![span],
-            domain: expression_domain.into(),
+            domain: expression_domain,
         }
     }
 
@@ -1844,11 +2894,35 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                         result = &ConstantDomain::Unimplemented;
                     }
                 };
-                result.clone().into()
+                let result = result.clone();
+                self.add_widening_threshold(&result);
+                result.into()
             }
         }
     }
 
+    /// Records integer constants so that widening can use them as interval bounds.
+    fn add_widening_threshold(&mut self, constant: &ConstantDomain) {
+        match constant {
+            ConstantDomain::I128(val) => self.insert_widening_threshold(*val),
+            ConstantDomain::U128(val) => {
+                if let Ok(val) = i128::try_from(*val) {
+                    self.insert_widening_threshold(val);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Adds the given value to the widening thresholds, unless there are already
+    /// k_limits::MAX_WIDENING_THRESHOLDS of them. Since a widened bound can move past each
+    /// threshold in turn, more thresholds would let the fixed point loop take too long.
+    fn insert_widening_threshold(&mut self, threshold: i128) {
+        if self.widening_thresholds.len() < k_limits::MAX_WIDENING_THRESHOLDS {
+            self.widening_thresholds.insert(threshold);
+        }
+    }
+
     /// Deserializes the given bytes into a constant array of the given element type and then
     /// stores the array elements in the environment with a path for each element, rooted
     /// in a new abstract heap address that represents the array itself and which is returned
@@ -1866,7 +2940,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
     ) -> AbstractValue {
         let array_value = self.get_new_heap_address();
         let ordinal =
-            if let Expression::AbstractHeapAddress(ordinal) = array_value.domain.expression {
+            if let Expression::AbstractHeapAddress(ordinal) = *array_value.domain.expression {
                 ordinal
             } else {
                 unreachable!()
@@ -2081,7 +3155,7 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
                     // Strip the Deref in order to canonicalize paths
                     let base_val = self.lookup_path_and_refine_result(base.clone(), base_type);
                     return match base_val.domain.expression {
-                        Expression::Reference(dereferenced_path) => dereferenced_path,
+                        Expression::Reference(dereferenced_path) => dereferenced_path.clone(),
                         _ => {
                             // If we are dereferencing a path whose value is not known to be a
                             // reference, we just drop the deref so that the path can be found
@@ -2159,6 +3233,48 @@ impl<'a, 'b: 'a, 'tcx: 'b, E> MirVisitor<'a, 'b, 'tcx, E> {
         }
     }
 
+    /// Returns the rustc TyKind of the value of the given operand.
+    fn get_rustc_operand_type(&self, operand: &mir::Operand<'tcx>) -> &TyKind<'tcx> {
+        match operand {
+            mir::Operand::Copy(place) | mir::Operand::Move(place) => {
+                self.get_rustc_place_type(place)
+            }
+            mir::Operand::Constant(constant) => &constant.ty.sty,
+        }
+    }
+
+    /// Returns the summary key of the struct or enum type of the given operand, or of the type
+    /// that the operand refers to, if it is a reference.
+    fn get_adt_key(&self, operand: &mir::Operand<'tcx>) -> Option<String> {
+        let ty = match self.get_rustc_operand_type(operand) {
+            TyKind::Ref(_, ty, _) => &ty.sty,
+            ty => ty,
+        };
+        match ty {
+            TyKind::Adt(def, _) => Some(utils::summary_key_str(&self.tcx, def.did)),
+            _ => None,
+        }
+    }
+
+    /// Returns the type definition of the enum at the given place, if the place is an enum.
+    fn get_enum_def(&self, place: &mir::Place<'tcx>) -> Option<&'tcx AdtDef> {
+        match self.get_rustc_place_type(place) {
+            TyKind::Adt(def, _) if def.is_enum() => Some(*def),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the value of the given operand is a string slice.
+    fn is_str_reference(&self, operand: &mir::Operand<'tcx>) -> bool {
+        match self.get_rustc_operand_type(operand) {
+            TyKind::Ref(_, ty, _) => match ty.sty {
+                TyKind::Str => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
     /// Returns the rustc TyKind of the element selected by projection_elem.
     fn get_type_for_projection_element(&self, place: &mir::Place<'tcx>) -> &TyKind<'tcx> {
         if let mir::Place::Projection(boxed_place_projection) = place {
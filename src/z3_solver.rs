@@ -0,0 +1,731 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::abstract_domains::AbstractDomain;
+use crate::abstract_value::Path;
+use crate::constant_domain::ConstantDomain;
+use crate::expression::{Expression, ExpressionType};
+use crate::interval_domain::IntervalDomain;
+use crate::k_limits;
+use crate::smt_solver::{SmtResult, SmtSolver};
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use z3_sys::{Z3_ast, Z3_context, Z3_solver, Z3_sort};
+
+/// An implementation of SmtSolver that uses the Z3 library (via its C API) to solve queries.
+///
+/// Integral values (including bools when used as numbers and the discriminants of enums) are
+/// modeled as mathematical integers, so that arithmetic never wraps silently. Operations whose
+/// meaning depends on a machine representation, such as the bitwise operations, are modeled
+/// via bit vectors that are wide enough to hold any Rust integer. The checks for arithmetic
+/// overflow are encoded bit precisely, using bit vectors that are exactly as wide as the type of
+/// the result. Since integer variables are constrained to the ranges of their types, converting
+/// their values into such bit vectors never loses information. Values that cannot be modeled
+/// precisely, such as floating point numbers, become unconstrained (fresh) constants, which
+/// keeps the translation sound, albeit imprecise.
+pub struct Z3Solver {
+    z3_context: Z3_context,
+    z3_solver: Z3_solver,
+    bool_sort: Z3_sort,
+    int_sort: Z3_sort,
+    /// The variables (along with the constants that model them and their types) that appear in
+    /// terms that have been translated, but not yet asserted.
+    translated_variables: Vec<(Path, (Z3_ast, ExpressionType))>,
+    /// The variables that appear in the assertions of each of the nested contexts of the solver.
+    /// The last element corresponds to the current context. Used to construct models.
+    asserted_variables: Vec<Vec<(Path, (Z3_ast, ExpressionType))>>,
+    /// Constraints that limit the values of the integer variables in terms that have been
+    /// translated, but not yet asserted, to the ranges of their types, or to their intervals
+    /// if they are widened values.
+    range_constraints: Vec<Z3_ast>,
+}
+
+/// The number of bits in the bit vectors used to model bitwise operations. This is one more than
+/// the width of the widest integer type, so that every value of every integer type, signed or
+/// unsigned, has the same two's complement representation in a bit vector of this width.
+const BIT_VECTOR_WIDTH: u32 = 129;
+
+impl Default for Z3Solver {
+    fn default() -> Self {
+        Z3Solver::new()
+    }
+}
+
+impl Z3Solver {
+    pub fn new() -> Z3Solver {
+        unsafe {
+            let z3_config = z3_sys::Z3_mk_config();
+            let timeout_param = CString::new("timeout").unwrap();
+            let timeout_value =
+                CString::new(k_limits::SMT_SOLVER_TIMEOUT_MILLISECONDS.to_string()).unwrap();
+            z3_sys::Z3_set_param_value(z3_config, timeout_param.as_ptr(), timeout_value.as_ptr());
+            let z3_context = z3_sys::Z3_mk_context(z3_config);
+            z3_sys::Z3_del_config(z3_config);
+            let z3_solver = z3_sys::Z3_mk_solver(z3_context);
+            z3_sys::Z3_solver_inc_ref(z3_context, z3_solver);
+            let bool_sort = z3_sys::Z3_mk_bool_sort(z3_context);
+            let int_sort = z3_sys::Z3_mk_int_sort(z3_context);
+            Z3Solver {
+                z3_context,
+                z3_solver,
+                bool_sort,
+                int_sort,
+                translated_variables: Vec::new(),
+                asserted_variables: vec![Vec::new()],
+                range_constraints: Vec::new(),
+            }
+        }
+    }
+}
+
+impl Drop for Z3Solver {
+    fn drop(&mut self) {
+        unsafe {
+            z3_sys::Z3_solver_dec_ref(self.z3_context, self.z3_solver);
+            z3_sys::Z3_del_context(self.z3_context);
+        }
+    }
+}
+
+impl SmtSolver<Z3_ast> for Z3Solver {
+    fn as_debug_string(&self, expression: &Z3_ast) -> String {
+        unsafe {
+            let debug_str = z3_sys::Z3_ast_to_string(self.z3_context, *expression);
+            CStr::from_ptr(debug_str).to_string_lossy().into_owned()
+        }
+    }
+
+    fn assert(&mut self, expression: &Z3_ast) {
+        let range_constraints = std::mem::replace(&mut self.range_constraints, Vec::new());
+        let mut translated_variables =
+            std::mem::replace(&mut self.translated_variables, Vec::new());
+        if let Some(variables) = self.asserted_variables.last_mut() {
+            variables.append(&mut translated_variables);
+        }
+        unsafe {
+            for range_constraint in range_constraints {
+                z3_sys::Z3_solver_assert(self.z3_context, self.z3_solver, range_constraint);
+            }
+            z3_sys::Z3_solver_assert(self.z3_context, self.z3_solver, *expression);
+        }
+    }
+
+    fn backtrack(&mut self) {
+        if self.asserted_variables.len() > 1 {
+            self.asserted_variables.pop();
+        }
+        unsafe {
+            z3_sys::Z3_solver_pop(self.z3_context, self.z3_solver, 1);
+        }
+    }
+
+    fn get_as_smt_predicate(&mut self, mirai_expression: &Expression) -> Z3_ast {
+        self.get_as_bool_z3_ast(mirai_expression)
+    }
+
+    fn get_model(&mut self) -> Vec<(Path, ConstantDomain)> {
+        let mut result = Vec::new();
+        let variables: HashMap<Path, (Z3_ast, ExpressionType)> = self
+            .asserted_variables
+            .iter()
+            .flat_map(|vars| vars.iter().cloned())
+            .collect();
+        unsafe {
+            let model = z3_sys::Z3_solver_get_model(self.z3_context, self.z3_solver);
+            if model.is_null() {
+                return result;
+            }
+            z3_sys::Z3_model_inc_ref(self.z3_context, model);
+            for (path, (variable, var_type)) in variables.iter() {
+                let mut value: Z3_ast = std::ptr::null_mut();
+                // Without model completion, variables that do not appear in the current
+                // context evaluate to themselves, rather than to a value.
+                if !z3_sys::Z3_model_eval(self.z3_context, model, *variable, false, &mut value) {
+                    continue;
+                }
+                if let Some(constant) = self.get_as_constant(value, var_type) {
+                    result.push((path.clone(), constant));
+                }
+            }
+            z3_sys::Z3_model_dec_ref(self.z3_context, model);
+        }
+        result.sort();
+        result
+    }
+
+    fn set_backtrack_position(&mut self) {
+        self.asserted_variables.push(Vec::new());
+        unsafe {
+            z3_sys::Z3_solver_push(self.z3_context, self.z3_solver);
+        }
+    }
+
+    fn solve(&mut self) -> SmtResult {
+        unsafe {
+            match z3_sys::Z3_solver_check(self.z3_context, self.z3_solver) {
+                z3_sys::Z3_L_TRUE => SmtResult::Satisfiable,
+                z3_sys::Z3_L_FALSE => SmtResult::Unsatisfiable,
+                _ => SmtResult::Undefined,
+            }
+        }
+    }
+}
+
+impl Z3Solver {
+    /// Returns true if the given expression results in a Boolean value, rather than a number.
+    fn is_bool_expression(expression: &Expression) -> bool {
+        use self::Expression::*;
+        match expression {
+            AddOverflows { .. }
+            | And { .. }
+            | Equals { .. }
+            | GreaterOrEqual { .. }
+            | GreaterThan { .. }
+            | LessOrEqual { .. }
+            | LessThan { .. }
+            | MulOverflows { .. }
+            | Ne { .. }
+            | Not { .. }
+            | Or { .. }
+            | ShlOverflows { .. }
+            | ShrOverflows { .. }
+            | SubOverflows { .. } => true,
+            CompileTimeConstant(ConstantDomain::True)
+            | CompileTimeConstant(ConstantDomain::False) => true,
+            ConditionalExpression { consequent, .. } => {
+                Self::is_bool_expression(&consequent.expression)
+            }
+            Variable { var_type, .. } => *var_type == ExpressionType::Bool,
+            _ => false,
+        }
+    }
+
+    /// Translates the given expression into a Z3 expression of sort Bool.
+    /// Numeric expressions are treated as true if they are not equal to zero.
+    fn get_as_bool_z3_ast(&mut self, expression: &Expression) -> Z3_ast {
+        use self::Expression::*;
+        match expression {
+            AddOverflows {
+                left,
+                right,
+                result_type,
+            } => self.get_as_bit_vector_overflow_check(
+                left,
+                right,
+                result_type,
+                |ctx, l, r, is_signed| unsafe {
+                    let no_overflow = z3_sys::Z3_mk_bvadd_no_overflow(ctx, l, r, is_signed);
+                    if !is_signed {
+                        return no_overflow;
+                    }
+                    let no_underflow = z3_sys::Z3_mk_bvadd_no_underflow(ctx, l, r);
+                    z3_sys::Z3_mk_and(ctx, 2, [no_overflow, no_underflow].as_ptr())
+                },
+            ),
+            And { left, right } => {
+                let left_ast = self.get_as_bool_z3_ast(&left.expression);
+                let right_ast = self.get_as_bool_z3_ast(&right.expression);
+                unsafe { z3_sys::Z3_mk_and(self.z3_context, 2, [left_ast, right_ast].as_ptr()) }
+            }
+            CompileTimeConstant(ConstantDomain::False) => unsafe {
+                z3_sys::Z3_mk_false(self.z3_context)
+            },
+            CompileTimeConstant(ConstantDomain::True) => unsafe {
+                z3_sys::Z3_mk_true(self.z3_context)
+            },
+            ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                let condition_ast = self.get_as_bool_z3_ast(&condition.expression);
+                let consequent_ast = self.get_as_bool_z3_ast(&consequent.expression);
+                let alternate_ast = self.get_as_bool_z3_ast(&alternate.expression);
+                unsafe {
+                    z3_sys::Z3_mk_ite(
+                        self.z3_context,
+                        condition_ast,
+                        consequent_ast,
+                        alternate_ast,
+                    )
+                }
+            }
+            Equals { left, right } => self.get_as_equality(left, right),
+            GreaterOrEqual { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_ge(self.z3_context, left_ast, right_ast) }
+            }
+            GreaterThan { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_gt(self.z3_context, left_ast, right_ast) }
+            }
+            LessOrEqual { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_le(self.z3_context, left_ast, right_ast) }
+            }
+            LessThan { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_lt(self.z3_context, left_ast, right_ast) }
+            }
+            MulOverflows {
+                left,
+                right,
+                result_type,
+            } => self.get_as_bit_vector_overflow_check(
+                left,
+                right,
+                result_type,
+                |ctx, l, r, is_signed| unsafe {
+                    let no_overflow = z3_sys::Z3_mk_bvmul_no_overflow(ctx, l, r, is_signed);
+                    if !is_signed {
+                        return no_overflow;
+                    }
+                    let no_underflow = z3_sys::Z3_mk_bvmul_no_underflow(ctx, l, r);
+                    z3_sys::Z3_mk_and(ctx, 2, [no_overflow, no_underflow].as_ptr())
+                },
+            ),
+            Ne { left, right } => {
+                let equality = self.get_as_equality(left, right);
+                unsafe { z3_sys::Z3_mk_not(self.z3_context, equality) }
+            }
+            Not { operand } => {
+                let operand_ast = self.get_as_bool_z3_ast(&operand.expression);
+                unsafe { z3_sys::Z3_mk_not(self.z3_context, operand_ast) }
+            }
+            Or { left, right } => {
+                let left_ast = self.get_as_bool_z3_ast(&left.expression);
+                let right_ast = self.get_as_bool_z3_ast(&right.expression);
+                unsafe { z3_sys::Z3_mk_or(self.z3_context, 2, [left_ast, right_ast].as_ptr()) }
+            }
+            ShlOverflows {
+                right, result_type, ..
+            }
+            | ShrOverflows {
+                right, result_type, ..
+            } => {
+                // Shifts overflow if the number of bits to shift by is not less than the width of
+                // the type of the value being shifted. Since the shift amount is an integer that
+                // is constrained to the range of its type, this is just as precise as comparing
+                // bit vectors.
+                let right_ast = self.get_as_numeric_z3_ast(&right.expression);
+                let zero = self.get_numeral(&0);
+                let bit_length = self.get_numeral(&result_type.bit_length());
+                unsafe {
+                    let is_negative = z3_sys::Z3_mk_lt(self.z3_context, right_ast, zero);
+                    let is_too_large = z3_sys::Z3_mk_ge(self.z3_context, right_ast, bit_length);
+                    z3_sys::Z3_mk_or(self.z3_context, 2, [is_negative, is_too_large].as_ptr())
+                }
+            }
+            SubOverflows {
+                left,
+                right,
+                result_type,
+            } => self.get_as_bit_vector_overflow_check(
+                left,
+                right,
+                result_type,
+                |ctx, l, r, is_signed| unsafe {
+                    let no_underflow = z3_sys::Z3_mk_bvsub_no_underflow(ctx, l, r, is_signed);
+                    if !is_signed {
+                        return no_underflow;
+                    }
+                    let no_overflow = z3_sys::Z3_mk_bvsub_no_overflow(ctx, l, r);
+                    z3_sys::Z3_mk_and(ctx, 2, [no_overflow, no_underflow].as_ptr())
+                },
+            ),
+            Top | Bottom => self.get_fresh_constant(self.bool_sort),
+            Variable { path, var_type } if *var_type == ExpressionType::Bool => {
+                self.get_variable(path, var_type)
+            }
+            _ => {
+                // A number used as a condition. This should not really happen, but be conservative.
+                let numeric_ast = self.get_as_numeric_z3_ast(expression);
+                let zero = self.get_numeral(&0);
+                unsafe {
+                    let is_zero = z3_sys::Z3_mk_eq(self.z3_context, numeric_ast, zero);
+                    z3_sys::Z3_mk_not(self.z3_context, is_zero)
+                }
+            }
+        }
+    }
+
+    /// Translates the given expression into a Z3 expression of sort Int.
+    /// Boolean expressions are treated as 1 if true and 0 if false.
+    fn get_as_numeric_z3_ast(&mut self, expression: &Expression) -> Z3_ast {
+        use self::Expression::*;
+        if Self::is_bool_expression(expression) {
+            let condition = self.get_as_bool_z3_ast(expression);
+            let one = self.get_numeral(&1);
+            let zero = self.get_numeral(&0);
+            return unsafe { z3_sys::Z3_mk_ite(self.z3_context, condition, one, zero) };
+        }
+        match expression {
+            AbstractHeapAddress(ordinal) => {
+                self.get_named_constant(&format!("address_{}", ordinal), self.int_sort)
+            }
+            Add { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_add(self.z3_context, 2, [left_ast, right_ast].as_ptr()) }
+            }
+            BitAnd { left, right } => {
+                self.get_as_bit_vector_operation(left, right, |ctx, l, r| unsafe {
+                    z3_sys::Z3_mk_bvand(ctx, l, r)
+                })
+            }
+            BitOr { left, right } => {
+                self.get_as_bit_vector_operation(left, right, |ctx, l, r| unsafe {
+                    z3_sys::Z3_mk_bvor(ctx, l, r)
+                })
+            }
+            BitXor { left, right } => {
+                self.get_as_bit_vector_operation(left, right, |ctx, l, r| unsafe {
+                    z3_sys::Z3_mk_bvxor(ctx, l, r)
+                })
+            }
+            CompileTimeConstant(ConstantDomain::Char(ch)) => self.get_numeral(&(*ch as u32)),
+            CompileTimeConstant(ConstantDomain::I128(val)) => self.get_numeral(val),
+            CompileTimeConstant(ConstantDomain::U128(val)) => self.get_numeral(val),
+            Cast {
+                operand,
+                target_type,
+            } => self.get_as_cast(&operand.expression, target_type),
+            ConditionalExpression {
+                condition,
+                consequent,
+                alternate,
+            } => {
+                let condition_ast = self.get_as_bool_z3_ast(&condition.expression);
+                let consequent_ast = self.get_as_numeric_z3_ast(&consequent.expression);
+                let alternate_ast = self.get_as_numeric_z3_ast(&alternate.expression);
+                unsafe {
+                    z3_sys::Z3_mk_ite(
+                        self.z3_context,
+                        condition_ast,
+                        consequent_ast,
+                        alternate_ast,
+                    )
+                }
+            }
+            Div { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                self.get_truncated_division(left_ast, right_ast)
+            }
+            Mul { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_mul(self.z3_context, 2, [left_ast, right_ast].as_ptr()) }
+            }
+            Neg { operand } => {
+                let operand_ast = self.get_as_numeric_z3_ast(&operand.expression);
+                unsafe { z3_sys::Z3_mk_unary_minus(self.z3_context, operand_ast) }
+            }
+            Reference(path) => self.get_named_constant(&format!("&{:?}", path), self.int_sort),
+            Rem { left, right } => {
+                // Rust uses truncated division, so left % right == left - right * (left / right)
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                let quotient = self.get_truncated_division(left_ast, right_ast);
+                unsafe {
+                    let product =
+                        z3_sys::Z3_mk_mul(self.z3_context, 2, [right_ast, quotient].as_ptr());
+                    z3_sys::Z3_mk_sub(self.z3_context, 2, [left_ast, product].as_ptr())
+                }
+            }
+            Shl {
+                left,
+                right,
+                result_type,
+            } => {
+                if !result_type.is_integer() {
+                    return self.get_fresh_constant(self.int_sort);
+                }
+                // The bits that are shifted out of a value of result_type are lost, so the shift
+                // is done on bit vectors that are exactly as wide as the type.
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                let width = u32::from(result_type.bit_length());
+                unsafe {
+                    let left_bv = z3_sys::Z3_mk_int2bv(self.z3_context, width, left_ast);
+                    let right_bv = z3_sys::Z3_mk_int2bv(self.z3_context, width, right_ast);
+                    let result_bv = z3_sys::Z3_mk_bvshl(self.z3_context, left_bv, right_bv);
+                    let is_signed = result_type.is_signed_integer();
+                    z3_sys::Z3_mk_bv2int(self.z3_context, result_bv, is_signed)
+                }
+            }
+            Shr {
+                left,
+                right,
+                result_type,
+            } => {
+                if let CompileTimeConstant(ConstantDomain::U128(shift)) = *right.expression {
+                    if shift < u128::from(result_type.bit_length()) {
+                        // An arithmetic (or logical, for unsigned values) shift right is the same
+                        // as a division that rounds towards negative infinity.
+                        let left_ast = self.get_as_numeric_z3_ast(&left.expression);
+                        let divisor = self.get_numeral(&(1u128 << shift));
+                        return unsafe { z3_sys::Z3_mk_div(self.z3_context, left_ast, divisor) };
+                    }
+                }
+                let is_signed = result_type.is_signed_integer();
+                self.get_as_bit_vector_operation(left, right, |ctx, l, r| unsafe {
+                    if is_signed {
+                        z3_sys::Z3_mk_bvashr(ctx, l, r)
+                    } else {
+                        z3_sys::Z3_mk_bvlshr(ctx, l, r)
+                    }
+                })
+            }
+            Sub { left, right } => {
+                let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+                unsafe { z3_sys::Z3_mk_sub(self.z3_context, 2, [left_ast, right_ast].as_ptr()) }
+            }
+            Variable { path, var_type } => match var_type {
+                // Floating point values are not modeled yet. Since NaN != NaN, a float variable
+                // cannot even be assumed to be equal to itself, so each occurrence is fresh.
+                ExpressionType::F32 | ExpressionType::F64 => self.get_fresh_constant(self.int_sort),
+                _ => self.get_variable(path, var_type),
+            },
+            Widen {
+                path,
+                block,
+                interval,
+                ..
+            } => self.get_widened_variable(path, *block, interval),
+            // Values that cannot be modeled precisely as integers become unconstrained values.
+            Top | Bottom | CompileTimeConstant(..) | Offset { .. } => {
+                self.get_fresh_constant(self.int_sort)
+            }
+            AddOverflows { .. }
+            | And { .. }
+            | Equals { .. }
+            | GreaterOrEqual { .. }
+            | GreaterThan { .. }
+            | LessOrEqual { .. }
+            | LessThan { .. }
+            | MulOverflows { .. }
+            | Ne { .. }
+            | Not { .. }
+            | Or { .. }
+            | ShlOverflows { .. }
+            | ShrOverflows { .. }
+            | SubOverflows { .. } => {
+                // These are Boolean expressions and have been handled above.
+                unreachable!()
+            }
+        }
+    }
+
+    /// Translates both operands into Z3 expressions of sort Int.
+    fn get_as_numeric_operands(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+    ) -> (Z3_ast, Z3_ast) {
+        let left_ast = self.get_as_numeric_z3_ast(&left.expression);
+        let right_ast = self.get_as_numeric_z3_ast(&right.expression);
+        (left_ast, right_ast)
+    }
+
+    /// Translates "left == right" into a Z3 expression of sort Bool, comparing the operands
+    /// as Booleans if either of them is Boolean, and as numbers otherwise.
+    fn get_as_equality(&mut self, left: &AbstractDomain, right: &AbstractDomain) -> Z3_ast {
+        let (left_ast, right_ast) = if Self::is_bool_expression(&left.expression)
+            || Self::is_bool_expression(&right.expression)
+        {
+            (
+                self.get_as_bool_z3_ast(&left.expression),
+                self.get_as_bool_z3_ast(&right.expression),
+            )
+        } else {
+            self.get_as_numeric_operands(left, right)
+        };
+        unsafe { z3_sys::Z3_mk_eq(self.z3_context, left_ast, right_ast) }
+    }
+
+    /// Converts the operands into bit vectors, applies the given bit vector operation and
+    /// converts the result back to an integer, interpreting it as a two's complement value.
+    fn get_as_bit_vector_operation<F>(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        operation: F,
+    ) -> Z3_ast
+    where
+        F: Fn(Z3_context, Z3_ast, Z3_ast) -> Z3_ast,
+    {
+        let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+        unsafe {
+            let left_bv = z3_sys::Z3_mk_int2bv(self.z3_context, BIT_VECTOR_WIDTH, left_ast);
+            let right_bv = z3_sys::Z3_mk_int2bv(self.z3_context, BIT_VECTOR_WIDTH, right_ast);
+            let result_bv = operation(self.z3_context, left_bv, right_bv);
+            z3_sys::Z3_mk_bv2int(self.z3_context, result_bv, true)
+        }
+    }
+
+    /// Converts the operands into bit vectors that are exactly as wide as result_type and returns
+    /// a Z3 expression that is true if the operation overflows. The given function returns a bit
+    /// vector predicate that is true if the operation does not overflow. Its last argument is true
+    /// if the operands are two's complement values, rather than unsigned values.
+    fn get_as_bit_vector_overflow_check<F>(
+        &mut self,
+        left: &AbstractDomain,
+        right: &AbstractDomain,
+        result_type: &ExpressionType,
+        no_overflow: F,
+    ) -> Z3_ast
+    where
+        F: Fn(Z3_context, Z3_ast, Z3_ast, bool) -> Z3_ast,
+    {
+        if !result_type.is_integer() {
+            return self.get_fresh_constant(self.bool_sort);
+        }
+        let (left_ast, right_ast) = self.get_as_numeric_operands(left, right);
+        let width = u32::from(result_type.bit_length());
+        unsafe {
+            let left_bv = z3_sys::Z3_mk_int2bv(self.z3_context, width, left_ast);
+            let right_bv = z3_sys::Z3_mk_int2bv(self.z3_context, width, right_ast);
+            let is_signed = result_type.is_signed_integer();
+            let no_overflow = no_overflow(self.z3_context, left_bv, right_bv, is_signed);
+            z3_sys::Z3_mk_not(self.z3_context, no_overflow)
+        }
+    }
+
+    /// Translates "operand as target_type" into a Z3 expression of sort Int.
+    /// A conversion between integer types keeps the bits of the operand that fit into the target
+    /// type, which is what a round trip through a bit vector of that width does.
+    /// Conversions to other types are not modeled.
+    fn get_as_cast(&mut self, operand: &Expression, target_type: &ExpressionType) -> Z3_ast {
+        if !target_type.is_integer() {
+            return self.get_fresh_constant(self.int_sort);
+        }
+        let operand_ast = if Self::is_bool_expression(operand) {
+            let operand_ast = self.get_as_bool_z3_ast(operand);
+            let one = self.get_numeral(&1);
+            let zero = self.get_numeral(&0);
+            unsafe { z3_sys::Z3_mk_ite(self.z3_context, operand_ast, one, zero) }
+        } else {
+            self.get_as_numeric_z3_ast(operand)
+        };
+        let width = u32::from(target_type.bit_length());
+        unsafe {
+            let operand_bv = z3_sys::Z3_mk_int2bv(self.z3_context, width, operand_ast);
+            z3_sys::Z3_mk_bv2int(self.z3_context, operand_bv, target_type.is_signed_integer())
+        }
+    }
+
+    /// Returns a Z3 expression for left / right, where the quotient is truncated towards zero,
+    /// as is the case in Rust. Z3 divisions on the other hand round towards negative infinity
+    /// for positive divisors.
+    fn get_truncated_division(&mut self, left_ast: Z3_ast, right_ast: Z3_ast) -> Z3_ast {
+        let zero = self.get_numeral(&0);
+        unsafe {
+            let left_is_non_negative = z3_sys::Z3_mk_ge(self.z3_context, left_ast, zero);
+            let quotient = z3_sys::Z3_mk_div(self.z3_context, left_ast, right_ast);
+            let negated_left = z3_sys::Z3_mk_unary_minus(self.z3_context, left_ast);
+            let negated_quotient = z3_sys::Z3_mk_div(self.z3_context, negated_left, right_ast);
+            let adjusted_quotient = z3_sys::Z3_mk_unary_minus(self.z3_context, negated_quotient);
+            z3_sys::Z3_mk_ite(
+                self.z3_context,
+                left_is_non_negative,
+                quotient,
+                adjusted_quotient,
+            )
+        }
+    }
+
+    /// Returns a new constant of the given sort that is distinct from all other constants.
+    fn get_fresh_constant(&mut self, sort: Z3_sort) -> Z3_ast {
+        let prefix = CString::new("fresh").unwrap();
+        unsafe { z3_sys::Z3_mk_fresh_const(self.z3_context, prefix.as_ptr(), sort) }
+    }
+
+    /// Returns the constant of the given sort with the given name.
+    /// All calls with the same name and sort return the same constant.
+    fn get_named_constant(&mut self, name: &str, sort: Z3_sort) -> Z3_ast {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let symbol = z3_sys::Z3_mk_string_symbol(self.z3_context, name.as_ptr());
+            z3_sys::Z3_mk_const(self.z3_context, symbol, sort)
+        }
+    }
+
+    /// Returns a Z3 expression of sort Int for the given numeric value.
+    fn get_numeral<T: ToString>(&mut self, value: &T) -> Z3_ast {
+        let numeral = CString::new(value.to_string()).unwrap();
+        unsafe { z3_sys::Z3_mk_numeral(self.z3_context, numeral.as_ptr(), self.int_sort) }
+    }
+
+    /// Returns the Z3 constant used to model the value of the variable at the given path.
+    /// If the variable is an integer, a constraint that limits it to the range of its type is
+    /// asserted along with the term that is being translated.
+    fn get_variable(&mut self, path: &Path, var_type: &ExpressionType) -> Z3_ast {
+        let sort = if *var_type == ExpressionType::Bool {
+            self.bool_sort
+        } else {
+            self.int_sort
+        };
+        let variable = self.get_named_constant(&format!("{:?}", path), sort);
+        if var_type.is_integer() {
+            let min_value = self.get_numeral(&var_type.min_value());
+            let max_value = self.get_numeral(&var_type.max_value());
+            unsafe {
+                let not_too_small = z3_sys::Z3_mk_le(self.z3_context, min_value, variable);
+                let not_too_large = z3_sys::Z3_mk_le(self.z3_context, variable, max_value);
+                let range_constraint =
+                    z3_sys::Z3_mk_and(self.z3_context, 2, [not_too_small, not_too_large].as_ptr());
+                self.range_constraints.push(range_constraint);
+            }
+        }
+        self.translated_variables
+            .push((path.clone(), (variable, var_type.clone())));
+        variable
+    }
+
+    /// Returns the Z3 constant used to model the value of the variable at the given path, widened
+    /// at the entry of the given block. A constraint that limits it to the given interval is
+    /// asserted along with the term that is being translated.
+    fn get_widened_variable(
+        &mut self,
+        path: &Path,
+        block: usize,
+        interval: &IntervalDomain,
+    ) -> Z3_ast {
+        let name = format!("widen:{:?}:{}:{:?}", path, block, interval);
+        let variable = self.get_named_constant(&name, self.int_sort);
+        if let Some(lower_bound) = interval.lower_bound() {
+            let lower_bound = self.get_numeral(&lower_bound);
+            let constraint = unsafe { z3_sys::Z3_mk_le(self.z3_context, lower_bound, variable) };
+            self.range_constraints.push(constraint);
+        }
+        if let Some(upper_bound) = interval.upper_bound() {
+            let upper_bound = self.get_numeral(&upper_bound);
+            let constraint = unsafe { z3_sys::Z3_mk_le(self.z3_context, variable, upper_bound) };
+            self.range_constraints.push(constraint);
+        }
+        variable
+    }
+
+    /// Converts a value from a Z3 model into the corresponding constant.
+    /// Returns None if the value is not a Boolean or numeric literal.
+    fn get_as_constant(&self, value: Z3_ast, var_type: &ExpressionType) -> Option<ConstantDomain> {
+        unsafe {
+            if *var_type == ExpressionType::Bool {
+                return match z3_sys::Z3_get_bool_value(self.z3_context, value) {
+                    z3_sys::Z3_L_TRUE => Some(ConstantDomain::True),
+                    z3_sys::Z3_L_FALSE => Some(ConstantDomain::False),
+                    _ => None,
+                };
+            }
+            if !z3_sys::Z3_is_numeral_ast(self.z3_context, value) {
+                return None;
+            }
+            let numeral = z3_sys::Z3_get_numeral_string(self.z3_context, value);
+            let numeral = CStr::from_ptr(numeral).to_str().ok()?;
+            if var_type.is_signed_integer() {
+                numeral.parse::<i128>().ok().map(ConstantDomain::I128)
+            } else {
+                numeral.parse::<u128>().ok().map(ConstantDomain::U128)
+            }
+        }
+    }
+}
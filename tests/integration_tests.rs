@@ -24,6 +24,9 @@ extern crate syntax;
 extern crate tempdir;
 
 use mirai::callbacks;
+use mirai::options::Options;
+#[cfg(feature = "z3")]
+use mirai::options::SolverChoice;
 use mirai::utils;
 use rustc_rayon::iter::IntoParallelIterator;
 use rustc_rayon::iter::ParallelIterator;
@@ -42,13 +45,26 @@ use tempdir::TempDir;
 #[test]
 fn run_pass() {
     let run_pass_path = PathBuf::from_str("tests/run-pass").unwrap();
-    assert_eq!(run_directory(run_pass_path), 0);
+    assert_eq!(run_directory(run_pass_path, Options::default()), 0);
+}
+
+// Run the tests in the tests/z3-run-pass directory. These cases have conditions that the abstract
+// domains cannot decide on their own, so they are analyzed with Z3 as the SMT solver.
+#[cfg(feature = "z3")]
+#[test]
+fn z3_run_pass() {
+    let run_pass_path = PathBuf::from_str("tests/z3-run-pass").unwrap();
+    let options = Options {
+        solver: SolverChoice::Z3,
+        ..Options::default()
+    };
+    assert_eq!(run_directory(run_pass_path, options), 0);
 }
 
 // Iterates through the files in the directory at the given path and runs each as a separate test
-// case. For each case, a temporary output directory is created. The cases are then iterated in
-// parallel and run via invoke_driver.
-fn run_directory(directory_path: PathBuf) -> usize {
+// case, using the given options. For each case, a temporary output directory is created.
+// The cases are then iterated in parallel and run via invoke_driver.
+fn run_directory(directory_path: PathBuf, options: Options) -> usize {
     let sys_root = utils::find_sysroot();
     let mut files_and_temp_dirs = Vec::new();
     for entry in fs::read_dir(directory_path).expect("failed to read run-pass dir") {
@@ -72,7 +88,12 @@ fn run_directory(directory_path: PathBuf) -> usize {
         .fold(
             || 0,
             |acc, (file_name, temp_dir_path)| {
-                acc + self::invoke_driver(file_name, temp_dir_path, sys_root.clone())
+                acc + self::invoke_driver(
+                    file_name,
+                    temp_dir_path,
+                    sys_root.clone(),
+                    options.clone(),
+                )
             },
         )
         .reduce(|| 0, |acc, code| acc + code)
@@ -80,7 +101,12 @@ fn run_directory(directory_path: PathBuf) -> usize {
 
 // Runs the single test case found in file_name, using temp_dir_path as the place
 // to put compiler output, which for Mirai includes the persistent summary store.
-fn invoke_driver(file_name: String, temp_dir_path: String, sys_root: String) -> usize {
+fn invoke_driver(
+    file_name: String,
+    temp_dir_path: String,
+    sys_root: String,
+    options: Options,
+) -> usize {
     let f_name = file_name.clone();
     let result = std::panic::catch_unwind(|| {
         rustc_driver::run(|| {
@@ -105,6 +131,7 @@ fn invoke_driver(file_name: String, temp_dir_path: String, sys_root: String) ->
             ];
 
             let call_backs = callbacks::MiraiCallbacks::with_buffered_diagnostics(
+                options,
                 box move |diagnostics| {
                     let mut expected_errors = ExpectedErrors::new(&f_name);
                     expected_errors.check_messages(diagnostics)
@@ -0,0 +1,35 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// A test that decides conditions that follow from the boolean structure of the path condition.
+
+pub fn t1(a: bool, b: bool) {
+    if a && b || a && !b {
+        debug_assert!(a);
+    }
+}
+
+pub fn t2(a: bool, b: bool) {
+    if a || b {
+        if !a {
+            debug_assert!(b);
+        }
+    }
+}
+
+pub fn t3(a: bool, b: bool, c: bool) {
+    if (a || b) && (!a || c) {
+        debug_assert!(b || c);
+    }
+}
+
+pub fn t4(a: bool, b: bool) {
+    if a || b {
+        debug_assert!(a); //~ possible error: assertion failed: a
+    }
+}
+
+pub fn main() {}
@@ -0,0 +1,36 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that the congruence domain proves alignment and stride facts
+
+pub fn t1(i: u8) {
+    let j = (i as u32) * 4 + 2;
+    debug_assert!(j != 8);
+    debug_assert!(j % 4 != 0);
+    debug_assert!(j % 2 == 0);
+}
+
+pub fn t2() {
+    let mut i: usize = 0;
+    while i < 100 {
+        debug_assert!(i % 2 == 0);
+        i += 2;
+    }
+}
+
+pub fn t3(x: u32) {
+    if x % 4 == 2 {
+        debug_assert!(x % 2 == 0);
+        debug_assert!(x != 8);
+        debug_assert!(x & 3 == 2);
+    }
+}
+
+pub fn t4(x: u32) {
+    if x % 4 == 2 {
+        debug_assert!(x % 8 == 2); //~ possible error: assertion failed: x % 8 == 2
+    }
+}
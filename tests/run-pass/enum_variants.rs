@@ -0,0 +1,48 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests the variant set domain, which tracks the variants that enums may have, so that match arms
+// that can never be taken are unreachable and unwrapping a None or an Err is known to panic
+
+pub enum Color {
+    Red,
+    Green,
+    Blue,
+}
+
+pub fn t1(c: bool) -> i32 {
+    let color = if c { Color::Red } else { Color::Green };
+    match color {
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Blue => panic!("the color is never blue"),
+    }
+}
+
+pub fn t2(x: Option<i32>) -> i32 {
+    match x {
+        Some(i) => match x {
+            Some(_) => i,
+            None => panic!("x is known to be Some"),
+        },
+        None => 0,
+    }
+}
+
+pub fn t3() -> i32 {
+    let x: Option<i32> = None;
+    x.unwrap() //~ called `Option::unwrap()` on a `None` value
+}
+
+pub fn t4() -> i32 {
+    let r: Result<i32, ()> = Err(());
+    r.unwrap() //~ called `Result::unwrap()` on an `Err` value
+}
+
+pub fn t5(c: bool) -> i32 {
+    let x = if c { Some(1) } else { Some(2) };
+    x.unwrap()
+}
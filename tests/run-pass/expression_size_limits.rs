@@ -0,0 +1,44 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that a value that is joined at the merge points of many conditionals gets an expression
+// that is abstracted once it becomes too large, without losing the range of the value.
+
+macro_rules! count_bits {
+    ($x:ident, $bits:ident, $($shift:expr),*) => {
+        $(if ($bits >> $shift) & 1 != 0 { $x += 1; })*
+    };
+}
+
+pub fn t1(bits: u128) -> u8 {
+    let mut x: u8 = 0;
+    count_bits!(
+        x,
+        bits,
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+        24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45,
+        46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67,
+        68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89,
+        90, 91, 92, 93, 94, 95, 96, 97, 98, 99
+    );
+    debug_assert!(x <= 100);
+    x
+}
+
+pub fn t2(bits: u128) -> u8 {
+    let mut x: u8 = 0;
+    count_bits!(
+        x,
+        bits,
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+        24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45,
+        46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 65, 66, 67,
+        68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83, 84, 85, 86, 87, 88, 89,
+        90, 91, 92, 93, 94, 95, 96, 97, 98, 99
+    );
+    debug_assert!(x < 100); //~ possible error: assertion failed: x < 100
+    x
+}
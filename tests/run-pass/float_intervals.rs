@@ -0,0 +1,40 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests the floating point interval domain, which tracks NaN and infinities, and the checks
+// for float to integer casts and comparisons with NaN
+
+pub fn t1() {
+    let x = 2.75f64;
+    debug_assert!(x as u8 == 2);
+    let y = -1.5f64;
+    debug_assert!(y as u8 == 0); //~ float to integer cast saturates
+    let z = 1e10f32;
+    debug_assert!(z as i32 == std::i32::MAX); //~ float to integer cast saturates
+    debug_assert!(300 as f32 == 300.0);
+}
+
+pub fn t2(x: f64) -> u8 {
+    if x >= 0.0 && x < 256.0 {
+        x as u8
+    } else {
+        0
+    }
+}
+
+pub fn t3(x: f64) -> u8 {
+    x as u8 //~ float to integer cast might saturate
+}
+
+pub fn t4(x: f64) -> bool {
+    x < std::f64::NAN //~ this comparison is always false because an operand is NaN
+}
+
+pub fn t5(x: u8) {
+    let y = x as f64 / 2.0;
+    debug_assert!(y <= 127.5);
+    debug_assert!(y as u8 <= 127);
+}
@@ -0,0 +1,47 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that relations between indices and lengths prove bounds checks
+
+pub fn t1(arr: &mut [i32]) {
+    let mut i = 0;
+    while i < arr.len() {
+        arr[i] = 0;
+        i += 1;
+    }
+}
+
+pub fn t2(arr: &mut [i32], n: usize) {
+    if n <= arr.len() {
+        let mut i = 0;
+        while i < n {
+            arr[i] = 0;
+            i += 1;
+        }
+    }
+}
+
+pub fn t3(arr: &mut [i32], i: usize) {
+    if i < arr.len() && i > 0 {
+        arr[i - 1] = 0;
+    }
+}
+
+pub fn t4(arr: &mut [i32], i: usize) {
+    if i <= arr.len() {
+        arr[i] = 0; //~ possible array index out of bounds
+    }
+}
+
+pub fn t5(n: usize, m: usize) {
+    if n < m {
+        let mut i = 0;
+        while i < n {
+            debug_assert!(i < m);
+            i += 1;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// A test that uses the ranges of the induction variables of loops at the loop heads to check
+// array accesses with indices whose values are widened, and the trip counts of the loops to
+// check additions to counters.
+
+pub fn t1(arr: &mut [i32; 10]) {
+    let mut i = 0;
+    while i < 10 {
+        arr[i] = 0;
+        i += 1;
+    }
+}
+
+pub fn t2(arr: &mut [i32; 10]) {
+    let mut i = 0;
+    while i < 9 {
+        arr[i + 1] = 0;
+        i += 2;
+    }
+}
+
+pub fn t3(arr: &mut [i32; 10]) {
+    let mut i = 10;
+    while i > 0 {
+        i -= 1;
+        arr[i] = 0;
+    }
+}
+
+pub fn t4(arr: &mut [i32; 10]) {
+    let mut i = 0;
+    while i <= 10 {
+        arr[i] = 0; //~ possible array index out of bounds
+        i += 1;
+    }
+}
+
+pub fn t5(arr: &[i32; 10]) -> u8 {
+    let mut zeros: u8 = 0;
+    let mut i = 0;
+    while i < 10 {
+        if arr[i] == 0 {
+            zeros += 1;
+        }
+        i += 1;
+    }
+    zeros
+}
+
+pub fn t6(arr: &[i32; 10]) -> u8 {
+    let mut zeros: u8 = 250;
+    let mut i = 0;
+    while i < 10 {
+        if arr[i] == 0 {
+            zeros += 1; //~ possible attempt to add with overflow
+        }
+        i += 1;
+    }
+    zeros
+}
+
+pub fn main() {}
@@ -0,0 +1,31 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests the interval transfer functions for division, remainder, bitwise operations, shifts
+// and casts
+
+pub fn t1(h: usize) -> i32 {
+    let arr = [1; 16];
+    arr[h & 15]
+}
+
+pub fn t2(h: usize) -> i32 {
+    let arr = [1; 16];
+    arr[h % 16]
+}
+
+pub fn t3(x: u64) -> i32 {
+    let arr = [1; 16];
+    arr[(x >> 60) as usize]
+}
+
+pub fn t4(x: u32) {
+    debug_assert!((x & 255) / 2 < 128);
+    debug_assert!(((x & 15) << 4) < 256);
+    debug_assert!((x | 1) & 7 < 8);
+    debug_assert!((x as u8) as u32 <= 255);
+    debug_assert!(x >> 1 < 100); //~ possible error: assertion failed: x >> 1 < 100
+}
@@ -0,0 +1,42 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that the known bits domain tracks the bits that masks set and clear
+
+pub fn t1(x: u8) -> i32 {
+    let arr = [1; 244];
+    let i = (x & 0xf0) | 0x03;
+    debug_assert!(i & 0x03 == 0x03);
+    debug_assert!(i != 0);
+    arr[i as usize]
+}
+
+pub fn t2(x: u8) -> u8 {
+    let digit = (x & 0x0f) ^ 0x30;
+    digit - 0x30
+}
+
+pub fn t3(flags: u32) {
+    if flags & 4 != 0 {
+        debug_assert!(flags != 0);
+        debug_assert!(flags & 4 == 4);
+        debug_assert!((flags & !3) & 4 != 0);
+    }
+}
+
+pub fn t4(flags: u32) {
+    if flags & 0xff == 0x12 {
+        debug_assert!(flags & 0x10 != 0);
+        debug_assert!(flags & 1 == 0);
+        debug_assert!(flags & 0x100 == 0); //~ possible error: assertion failed: flags & 0x100 == 0
+    }
+}
+
+pub fn t5(x: u128) {
+    let y = (x | (1u128 << 127)) >> 1;
+    debug_assert!(y & (1u128 << 127) == 0);
+    debug_assert!(y & (1u128 << 126) != 0);
+}
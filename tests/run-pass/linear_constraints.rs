@@ -0,0 +1,32 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// A test that uses the built-in linear solver, which is the default solver, to decide
+// conditions that depend on linear relations that the abstract domains do not track.
+
+pub fn t1(x: u32, y: u32) {
+    if x <= 100 && y <= 100 && 2 * x + 3 * y <= 12 {
+        debug_assert!(x <= 6);
+        debug_assert!(y <= 4);
+        debug_assert!(x <= 5); //~ possible error: assertion failed: x <= 5
+    }
+}
+
+pub fn t2(x: i64, y: i64) {
+    if x >= 0 && y >= 0 && x <= 1000 && y <= 1000 && x + y == 10 {
+        debug_assert!(x - y <= 10);
+    }
+}
+
+pub fn t3(arr: &[i32; 10], i: usize, j: usize) -> i32 {
+    if i < 100 && j < 10 && i + 3 <= j {
+        arr[i]
+    } else {
+        0
+    }
+}
+
+pub fn main() {}
@@ -0,0 +1,28 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that loop exit conditions give precise values for loop variables after the loop
+
+pub fn t1() {
+    let mut i = 0;
+    while i < 10 {
+        i += 1;
+    }
+    debug_assert!(i == 10);
+}
+
+pub fn t2(cond: bool) {
+    let mut i = 0;
+    let mut found = false;
+    while i < 100 {
+        if cond {
+            found = true;
+        }
+        i += 1;
+    }
+    debug_assert!(i == 100);
+    debug_assert!(!found); //~ possible error: assertion failed: !found
+}
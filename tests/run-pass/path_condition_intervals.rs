@@ -0,0 +1,50 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// A test that uses the intervals that the path condition implies for variables to discharge
+// array bounds checks, division by zero checks and overflow checks.
+
+pub fn t1(arr: &[i32; 20], i: usize) -> i32 {
+    if i < 10 {
+        arr[i]
+    } else {
+        0
+    }
+}
+
+pub fn t2(x: i32) -> i32 {
+    if x > 0 {
+        100 / x
+    } else {
+        0
+    }
+}
+
+pub fn t3(arr: &[i32; 4], i: usize, j: usize) -> i32 {
+    if i < 2 && j <= 1 {
+        arr[i + j]
+    } else {
+        0
+    }
+}
+
+pub fn t4(x: u32) -> u32 {
+    if x < 10 {
+        100 / x //~ possible attempt to divide by zero
+    } else {
+        0
+    }
+}
+
+pub fn t5(x: u8) -> u8 {
+    if x < 10 {
+        x + 1
+    } else {
+        0
+    }
+}
+
+pub fn main() {}
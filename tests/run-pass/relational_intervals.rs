@@ -16,11 +16,13 @@ pub fn t1(cond: bool) {
 
 pub fn t2(cond: bool) {
     let interval = if cond { 0 } else { 10usize };
-    let top = interval % 2;
     let raw = &cond as *const _;
+    let top = raw as usize;
+    let remainder = interval % 2;
     let bottom = interval - (raw as usize); //~ possible attempt to subtract with overflow
     debug_assert!((interval as isize) > -2);
     debug_assert!(top < 3); //~ possible error: assertion failed: top < 3
-    debug_assert!(bottom <= bottom); //~ possible error: assertion failed: bottom <= bottom
+    debug_assert!(remainder < 2);
+    debug_assert!(bottom <= bottom);
 }
 
@@ -206,15 +206,13 @@ pub fn ti64_shr_safe(cond: bool) -> i64 {
     a >> a
 }
 
-// Interval domains can only represent finite intervals contained in [i128::MIN+1, ..., i128::MAX-1]
-
 pub fn ti128_add_overflows(cond: bool) -> i128 {
-    let a: i128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFE } else { 1 };  //~ possible attempt to add with overflow
+    let a: i128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF } else { 1 };  //~ possible attempt to add with overflow
     a + 1
 }
 
 pub fn ti128_add_safe(cond: bool) -> i128 {
-    let a: i128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFC } else { 1 };
+    let a: i128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFE } else { 1 };
     a + 1
 }
 
@@ -224,17 +222,17 @@ pub fn ti128_mul_overflows(cond: bool) -> i128 {
 }
 
 pub fn ti128_mul_safe(cond: bool) -> i128 {
-    let a: i128 = if cond { 0x3FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFE } else { 1 };
+    let a: i128 = if cond { 0x3FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF } else { 1 };
     a * 2
 }
 
 pub fn ti128_sub_overflows(cond: bool) -> i128 {
-    let a: i128 = if cond { -170141183460469231731687303715884105727i128 } else { 0 }; //~ possible attempt to subtract with overflow
+    let a: i128 = if cond { -170141183460469231731687303715884105728i128 } else { 0 }; //~ possible attempt to subtract with overflow
     a - 1
 }
 
 pub fn ti128_sub_safe(cond: bool) -> i128 {
-    let a: i128 = if cond { -170141183460469231731687303715884105726i128 } else { 0 };
+    let a: i128 = if cond { -170141183460469231731687303715884105727i128 } else { 0 };
     a - 1
 }
 
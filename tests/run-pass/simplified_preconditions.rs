@@ -0,0 +1,21 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// A test that infers preconditions that are simplified before they are stored in the summary
+// of the function, and reports a failure to satisfy them.
+
+pub fn main() {
+    let mut a = [1, 2];
+    foo(&mut a, 1, true);
+    foo(&mut a, 0, false);
+    foo(&mut a, 3, true); //~ array index out of bounds
+}
+
+fn foo(arr: &mut [i32], i: usize, b: bool) {
+    if b || !b && i > 0 {
+        arr[i + 2 - 2] = 12; //~ related location
+    }
+}
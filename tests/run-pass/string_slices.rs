@@ -0,0 +1,51 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests the string domain, which tracks the lengths and the known prefixes and suffixes of
+// string slices, and the checks for slicing strings out of range or inside of a character
+
+pub fn t1() {
+    let s = "hello world";
+    debug_assert!(s.len() == 11);
+    let world = &s[6..];
+    debug_assert!(world.len() == 5);
+    let hello = &s[..5];
+    debug_assert!(hello.len() == 5);
+    debug_assert!(s[..].len() == 11);
+}
+
+pub fn t2() {
+    let s = "héllo";
+    debug_assert!(s.len() == 6);
+    debug_assert!(s[3..].len() == 3);
+    debug_assert!(s[0..2].len() == 2); //~ string slice index is not a char boundary
+}
+
+pub fn t3() {
+    let s = "abc";
+    debug_assert!(s[1..4].len() == 3); //~ string slice index is out of range
+}
+
+pub fn t4(s: &str, i: usize) -> &str {
+    if i <= s.len() {
+        &s[i..] //~ possible string slice index is not a char boundary
+    } else {
+        s
+    }
+}
+
+pub fn t5(s: &str, i: usize) -> &str {
+    &s[..i] //~ possible string slice index is out of range
+}
+
+pub fn t6(s: &str) -> &str {
+    for (i, c) in s.char_indices() {
+        if c == ' ' {
+            return &s[..i];
+        }
+    }
+    s
+}
@@ -206,25 +206,23 @@ pub fn tu64_shr_safe(cond: bool) -> u64 {
     a >> a
 }
 
-// Interval domains can only represent finite intervals contained in [i128::MIN+1, ..., i128::MAX-1]
-
 pub fn tu128_add_overflows(cond: bool) -> u128 {
-    let a: u128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF } else { 1 };  //~ possible attempt to add with overflow
+    let a: u128 = if cond { 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF } else { 1 };  //~ possible attempt to add with overflow
     a + 1
 }
 
 pub fn tu128_add_safe(cond: bool) -> u128 {
-    let a: u128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFC } else { 1 };
+    let a: u128 = if cond { 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFE } else { 1 };
     a + 1
 }
 
 pub fn tu128_mul_overflows(cond: bool) -> u128 {
-    let a: u128 = if cond { 0x40000000000000000000000000000000 } else { 1 }; //~ possible attempt to multiply with overflow
+    let a: u128 = if cond { 0x80000000000000000000000000000000 } else { 1 }; //~ possible attempt to multiply with overflow
     a * 2
 }
 
 pub fn tu128_mul_safe(cond: bool) -> u128 {
-    let a: u128 = if cond { 0x3FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFE } else { 1 };
+    let a: u128 = if cond { 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF } else { 1 };
     a * 2
 }
 
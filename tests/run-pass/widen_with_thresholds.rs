@@ -0,0 +1,25 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that widening keeps loop bounds that are constants found in the function body
+
+pub fn t1() {
+    let mut arr = [0i32; 512];
+    let mut i = 0;
+    while i < 512 {
+        arr[i] = 1;
+        i += 1;
+    }
+    debug_assert!(i <= 512);
+}
+
+pub fn t2(n: usize) {
+    let mut i = 0;
+    while i < n {
+        i += 1;
+    }
+    debug_assert!(i <= 512); //~ possible error: assertion failed: i <= 512
+}
@@ -0,0 +1,41 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that intervals keep exact u128 bounds and model values that wrap around
+
+pub fn t1(x: u8) -> i32 {
+    let arr = [1; 8];
+    arr[(x | 0xF8).wrapping_add(8) as usize]
+}
+
+pub fn t2(x: u8) -> i32 {
+    let arr = [1; 8];
+    arr[(x | 0xF8).wrapping_add(9) as usize] //~ possible array index out of bounds
+}
+
+pub fn t3(x: u8) {
+    let y = (x | 0xF0) as i8;
+    debug_assert!(y < 0);
+}
+
+pub fn t4(x: u128) {
+    let y = x | 0x8000_0000_0000_0000_0000_0000_0000_0000;
+    debug_assert!(y > 0x7FFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF_FFFF);
+}
+
+pub fn t5(x: u64) -> i32 {
+    let arr = [1; 256];
+    let h = x.wrapping_mul(0x9E37_79B9_7F4A_7C15).wrapping_add(1);
+    arr[(h >> 56) as usize]
+}
+
+pub fn checksum(data: &[u8]) -> u8 {
+    let mut sum: u8 = 0;
+    for b in data {
+        sum = sum.wrapping_add(*b);
+    }
+    sum
+}
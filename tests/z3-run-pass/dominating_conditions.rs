@@ -0,0 +1,22 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests that the conditions of the blocks that dominate a block are taken into account
+// when the solver decides if the block can be reached.
+
+pub fn t1(x: i32, y: i32) {
+    if y > 0 && y < 100 && x == 2 * y {
+        if x <= y {
+            // Only the condition of the dominating block rules this out.
+            panic!("x is at most y");
+        }
+        if x > 100 {
+            panic!("x is large"); //~ possible error: x is large
+        }
+    }
+}
+
+pub fn main() {}
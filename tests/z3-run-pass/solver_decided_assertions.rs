@@ -0,0 +1,30 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests assertions that only an SMT solver can show to hold, since they depend on relations
+// between variables that the abstract domains do not track.
+
+pub fn t1(x: i32, y: i32) {
+    if y > 0 && y < 1000 && x == 3 * y {
+        debug_assert!(x > y);
+    }
+}
+
+pub fn t2(x: i64, y: i64, z: i64) {
+    if x < y && y < z {
+        debug_assert!(x < z);
+    }
+}
+
+pub fn t3(a: bool, b: bool) {
+    if a || b {
+        if !a {
+            debug_assert!(b);
+        }
+    }
+}
+
+pub fn main() {}
@@ -0,0 +1,34 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+//
+
+// Tests overflow checks that only an SMT solver can discharge, since the operands are
+// constrained by relations that the abstract domains do not track.
+
+pub fn t1(x: u8, y: u8) -> u8 {
+    if 2 * (x as u16) + (y as u16) <= 255 {
+        2 * x + y
+    } else {
+        0
+    }
+}
+
+pub fn t2(x: i32, y: i32) -> i32 {
+    if x >= 0 && y >= 0 && x <= 1000 - y {
+        x + y
+    } else {
+        0
+    }
+}
+
+pub fn t3(x: u64, y: u64, z: u64) -> u64 {
+    if x <= y && y <= z {
+        z - x
+    } else {
+        0
+    }
+}
+
+pub fn main() {}